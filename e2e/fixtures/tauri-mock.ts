@@ -24,7 +24,7 @@ export const TAURI_MOCK_SCRIPT = String.raw`
     historical_sessions: [],       // { id, score, completed_at, answers_snapshot }
     historical_responses: [],      // { session_id, question_number, answer_value, recorded_at }
     _sharingEnabled: false,        // persisted sharing opt-in state
-    _publishCalls: [],             // records each publish_result invocation for test assertions
+    _publishCalls: [],             // records each publish_answers invocation for test assertions
     _openCalls: [],                // records each plugin:opener|open_url invocation for test assertions
     // Optional fake update metadata returned by plugin:updater|check.
     // Tests can pre-set window.__tauriPresetUpdate (init script) or call
@@ -183,13 +183,12 @@ export const TAURI_MOCK_SCRIPT = String.raw`
         return 0;
       }
 
-      case 'publish_result': {
+      case 'publish_answers': {
         // Record the call so E2E tests can assert it was called with the right args.
         // Sharing must be enabled for this command to be invoked (enforced by Rust),
         // but in the mock we just record unconditionally.
         memDB._publishCalls.push({
-          score: payload.score,
-          categoryScores: payload.categoryScores
+          answers: payload.answers
         });
         return 'bafy-mock-cid';
       }
@@ -336,9 +336,9 @@ test.describe('Questionnaire – sticky header', () => {
   });
 });
 
-// ── Network sharing publish_result integration ────────────────────────────────
+// ── Network sharing publish_answers integration ────────────────────────────────
 
-test.describe('Questionnaire – publish_result called when sharing is enabled', () => {
+test.describe('Questionnaire – publish_answers called when sharing is enabled', () => {
   test.beforeEach(async ({ page }) => {
     // Seed _sharingEnabled = true BEFORE the page loads so that App.vue's
     // onMounted → loadSharingState() → get_network_sharing returns true.
@@ -347,7 +347,7 @@ test.describe('Questionnaire – publish_result called when sharing is enabled',
     }, { _sharingEnabled: true });
   });
 
-  test('submitting a session invokes publish_result with score and categoryScores', async ({
+  test('submitting a session invokes publish_answers with the answers', async ({
     questionnairePage,
     page,
     getDB,
@@ -358,16 +358,15 @@ test.describe('Questionnaire – publish_result called when sharing is enabled',
     await page.waitForURL('/dashboard', { timeout: 15_000 });
 
     const db = await getDB();
-    const publishCalls = db._publishCalls as Array<{ score: number; categoryScores: Record<string, number> }>;
+    const publishCalls = db._publishCalls as Array<{ answers: Record<string, number> }>;
     expect(publishCalls).toHaveLength(1);
-    expect(typeof publishCalls[0].score).toBe('number');
-    expect(publishCalls[0].score).toBeGreaterThan(0);
-    expect(typeof publishCalls[0].categoryScores).toBe('object');
+    expect(typeof publishCalls[0].answers).toBe('object');
+    expect(Object.values(publishCalls[0].answers).some(rating => rating > 0)).toBe(true);
   });
 });
 
-test.describe('Questionnaire – publish_result NOT called when sharing is disabled', () => {
-  test('submitting a session does not invoke publish_result when sharing is off', async ({
+test.describe('Questionnaire – publish_answers NOT called when sharing is disabled', () => {
+  test('submitting a session does not invoke publish_answers when sharing is off', async ({
     questionnairePage,
     page,
     getDB,
@@ -188,7 +188,7 @@ test.describe('Settings – sharing toggle', () => {
     let db = await getDB();
     expect((db._publishCalls as unknown[]).length).toBe(0);
 
-    // Enable the toggle — this should immediately invoke publish_result
+    // Enable the toggle — this should immediately invoke publish_answers
     await page.locator('label.toggle-label').click();
     await expect(page.locator('[data-testid="sharing-checkbox"]')).toBeChecked();
 
@@ -202,9 +202,9 @@ test.describe('Settings – sharing toggle', () => {
     );
 
     db = await getDB();
-    const publishCalls = db._publishCalls as Array<{ score: number; categoryScores: Record<string, number> }>;
+    const publishCalls = db._publishCalls as Array<{ answers: Record<string, number> }>;
     expect(publishCalls).toHaveLength(1);
-    expect(publishCalls[0].score).toBe(8250);
+    expect(publishCalls[0].answers).toEqual({ '1a': 9 });
   });
 });
 
@@ -0,0 +1,95 @@
+//! Cost of refreshing network statistics after one result arrives:
+//! re-sorting every series (the approach `get_stats` used before) versus
+//! updating the incrementally sorted `ScoreDistribution`s.
+//!
+//! Run with `cargo bench --bench quantiles`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/network/cohort.rs"]
+mod cohort;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/network/histogram.rs"]
+mod histogram;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/network/quantiles.rs"]
+mod quantiles;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/network/views.rs"]
+mod views;
+
+use quantiles::{ScoreDistribution, MAX_SORTED_SCORES};
+
+/// Total score plus one series per rated question.
+const SERIES: usize = 1 + 54;
+
+/// Deterministic pseudo-random values in `0..max`.
+fn values(n: usize, max: f64, seed: u64) -> Vec<f64> {
+    let mut state = seed;
+    (0..n)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64 * max
+        })
+        .collect()
+}
+
+fn sorted_p90(scores: &[f64]) -> Option<f64> {
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let idx = (sorted.len() as f64 * 0.9) as usize;
+    sorted.get(idx).or(sorted.last()).copied()
+}
+
+fn bench_update_and_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stats_after_one_result");
+    for n in [1_000, MAX_SORTED_SCORES] {
+        let series: Vec<Vec<f64>> = (0..SERIES).map(|s| values(n, 10.0, s as u64 + 1)).collect();
+        let incoming = values(1024, 10.0, 99);
+
+        group.bench_with_input(BenchmarkId::new("sort_every_read", n), &n, |b, _| {
+            let mut series = series.clone();
+            let mut i = 0;
+            b.iter(|| {
+                for s in series.iter_mut() {
+                    s[i % n] = incoming[i % incoming.len()];
+                }
+                i += 1;
+                for s in &series {
+                    black_box(sorted_p90(s));
+                }
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("incremental", n), &n, |b, _| {
+            let mut current = series.clone();
+            let mut distributions: Vec<ScoreDistribution> = series
+                .iter()
+                .map(|s| {
+                    let mut d = ScoreDistribution::default();
+                    s.iter().for_each(|&v| d.insert(v));
+                    d
+                })
+                .collect();
+            let mut i = 0;
+            b.iter(|| {
+                let value = incoming[i % incoming.len()];
+                for (s, d) in current.iter_mut().zip(distributions.iter_mut()) {
+                    d.remove(s[i % n]);
+                    d.insert(value);
+                    s[i % n] = value;
+                }
+                i += 1;
+                for d in &distributions {
+                    black_box(d.quantiles());
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_update_and_read);
+criterion_main!(benches);
@@ -1,3 +1,5 @@
 fn main() {
+    // Only the desktop app needs Tauri's generated context and resources.
+    #[cfg(feature = "gui")]
     tauri_build::build()
 }
@@ -0,0 +1,1130 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::{backup, keyring, keystore, load_or_generate_keypair, network, questions, scoring};
+use network::{Command, NodeConfig, PeerNode, TransportConfig};
+use network::app_command::AppCommand;
+use network::protocol::GossipMessage;
+use network::types::{ManifestationResult, SignedManifestation};
+use network::aggregate::AggregatorConfig;
+use network::cohort::DEFAULT_MIN_COHORT_SIZE;
+use network::privacy::LocalPrivacy;
+use network::publish_timing::{PublicationSequence, PublishTiming};
+use network::unlinkable::UnlinkableSettings;
+use crate::identity::UserIdentity;
+use keystore::{passphrase, KdfParams};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Emitter, Manager, State};
+use tokio::sync::{mpsc, oneshot};
+use zeroize::Zeroizing;
+
+struct NetworkState {
+    sender: Mutex<Option<mpsc::Sender<Command>>>,
+    identity: Mutex<Option<UserIdentity>>,
+    /// Controls whether `publish_answers` forwards data to the P2P network.
+    /// Default: false (explicit opt-in required, per PRD Feature 3.6).
+    /// Shared with `PeerNode`, which re-checks it before releasing queued results.
+    sharing_enabled: Arc<AtomicBool>,
+    /// Timestamp and sequence number of the latest publication, persisted as
+    /// `publication_sequence`.
+    publication_sequence: Mutex<PublicationSequence>,
+    /// Sign with keys derived per epoch instead of the root identity key.
+    /// Opt-in, persisted as `unlinkable_publishing`.
+    unlinkable: Mutex<Option<UnlinkableSettings>>,
+    /// Local differential-privacy noise applied before signing; `None`
+    /// publishes exact scores. Opt-in, persisted as `local_privacy`.
+    local_privacy: Mutex<Option<LocalPrivacy>>,
+    /// Coarse timestamps and a random, padded, queued release instead of
+    /// publishing immediately. Opt-in, persisted as `publish_timing`.
+    publish_timing: Mutex<Option<PublishTiming>>,
+    /// Path to the app settings JSON file for persisting sharing opt-in state.
+    settings_path: Mutex<Option<PathBuf>>,
+    /// Path to `identity.key`; `user_identity.json` sits next to it.
+    key_path: Mutex<Option<PathBuf>>,
+}
+
+/// Whether the identity files are passphrase-encrypted, and whether the
+/// user identity is currently locked out of memory.
+#[derive(serde::Serialize)]
+struct KeyProtection {
+    encrypted: bool,
+    locked: bool,
+}
+
+/// Load sharing opt-in state from the app settings file.
+fn load_settings(path: &Path) -> (bool, Vec<libp2p::Multiaddr>) {
+    let Ok(content) = std::fs::read_to_string(path) else { return (false, vec![]); };
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) else { return (false, vec![]); };
+    let sharing = json.get("sharing_enabled").and_then(|v| v.as_bool()).unwrap_or(false);
+    let peers: Vec<libp2p::Multiaddr> = json
+        .get("bootstrap_peers")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|s| s.as_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    (sharing, peers)
+}
+
+/// Load one key of the app settings file; missing or malformed values give `None`.
+fn load_setting<T: serde::de::DeserializeOwned>(path: &Path, key: &str) -> Option<T> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let json = serde_json::from_str::<serde_json::Value>(&content).ok()?;
+    serde_json::from_value(json.get(key)?.clone()).ok()
+}
+
+/// Load the local differential-privacy setting; invalid values count as off.
+fn load_local_privacy(path: &Path) -> Option<LocalPrivacy> {
+    let privacy: LocalPrivacy = load_setting(path, "local_privacy")?;
+    privacy.validate().ok().map(|_| privacy)
+}
+
+/// Load the publish-timing setting; invalid values count as off.
+fn load_publish_timing(path: &Path) -> Option<PublishTiming> {
+    let timing: PublishTiming = load_setting(path, "publish_timing")?;
+    timing.validate().ok().map(|_| timing)
+}
+
+/// Load the transport setting; missing or invalid means the defaults.
+fn load_transports(path: &Path) -> TransportConfig {
+    load_setting(path, "transports").unwrap_or_default()
+}
+
+/// Load the configured relays; entries that do not parse are skipped.
+fn load_relays(path: &Path) -> Vec<libp2p::Multiaddr> {
+    load_setting::<Vec<String>>(path, "relays")
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|s| s.parse().ok())
+        .collect()
+}
+
+/// Load the aggregate retention in days; missing or invalid means results
+/// are kept until evicted by the author cap.
+fn load_retention_days(path: &Path) -> Option<u64> {
+    let days = load_setting(path, "retention_days")?;
+    AggregatorConfig::default().with_retention_days(Some(days)).ok().map(|_| days)
+}
+
+/// Load the minimum cohort size; missing or invalid means the default.
+fn load_min_cohort_size(path: &Path) -> usize {
+    load_setting(path, "min_cohort_size")
+        .filter(|&size| AggregatorConfig::default().with_min_cohort_size(size).is_ok())
+        .unwrap_or(DEFAULT_MIN_COHORT_SIZE)
+}
+
+/// Limits for the node's aggregate, from the app settings file.
+fn load_aggregator_config(path: &Path) -> AggregatorConfig {
+    AggregatorConfig::default()
+        .with_retention_days(load_retention_days(path))
+        .and_then(|config| config.with_min_cohort_size(load_min_cohort_size(path)))
+        .unwrap_or_default()
+}
+
+/// Persist sharing opt-in state to the app settings file.
+fn save_settings(path: &Path, sharing_enabled: bool) -> Result<(), String> {
+    write_setting(path, "sharing_enabled", serde_json::Value::Bool(sharing_enabled))
+}
+
+/// Set one key of the app settings file.
+/// Preserves any existing keys (e.g. bootstrap_peers) by doing a read-merge-write.
+fn write_setting(path: &Path, key: &str, value: serde_json::Value) -> Result<(), String> {
+    let mut existing: serde_json::Value = if path.exists() {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        serde_json::from_str(&content).unwrap_or(serde_json::json!({}))
+    } else {
+        serde_json::json!({})
+    };
+    existing[key] = value;
+    let content = serde_json::to_string(&existing).map_err(|e| e.to_string())?;
+    std::fs::write(path, content).map_err(|e| {
+        eprintln!("[settings] Failed to write {:?}: {}", path, e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+async fn get_peer_count(state: State<'_, NetworkState>) -> Result<usize, String> {
+    let sender = {
+        let guard = state.sender.lock().map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+
+    if let Some(tx) = sender {
+        let (repl_tx, repl_rx) = oneshot::channel();
+        tx.send(Command::App(AppCommand::GetPeers { sender: repl_tx }))
+            .await
+            .map_err(|e| e.to_string())?;
+            
+        let peers = repl_rx.await.map_err(|e| e.to_string())?;
+        Ok(peers.len())
+    } else {
+        Err("Node not running".into())
+    }
+}
+
+/// Return the peers banned for misbehaviour (gossipsub score below the ban threshold).
+/// Bans last a day and persist across restarts in `banned_peers.json`.
+#[tauri::command]
+async fn get_banned_peers(state: State<'_, NetworkState>) -> Result<Vec<network::types::BannedPeer>, String> {
+    let sender = {
+        let guard = state.sender.lock().map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+
+    if let Some(tx) = sender {
+        let (repl_tx, repl_rx) = oneshot::channel();
+        tx.send(Command::App(AppCommand::GetBannedPeers { sender: repl_tx }))
+            .await
+            .map_err(|e| e.to_string())?;
+        repl_rx.await.map_err(|e| e.to_string())
+    } else {
+        Err("Node not running".into())
+    }
+}
+
+/// Lift a ban before it expires. Returns `false` if the peer was not banned.
+#[tauri::command]
+async fn unban_peer(peer_id: String, state: State<'_, NetworkState>) -> Result<bool, String> {
+    let peer_id: libp2p::PeerId = peer_id.parse().map_err(|e| format!("Invalid peer id: {}", e))?;
+    let sender = {
+        let guard = state.sender.lock().map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+
+    if let Some(tx) = sender {
+        let (repl_tx, repl_rx) = oneshot::channel();
+        tx.send(Command::App(AppCommand::UnbanPeer { peer_id, sender: repl_tx }))
+            .await
+            .map_err(|e| e.to_string())?;
+        repl_rx.await.map_err(|e| e.to_string())
+    } else {
+        Err("Node not running".into())
+    }
+}
+
+/// Histograms of the network aggregate: total scores in 100-point bins and
+/// each category's ratings in 1-point bins. Fetched on demand rather than
+/// pushed with every `network-stats` event.
+#[tauri::command]
+async fn get_score_histograms(state: State<'_, NetworkState>) -> Result<network::views::ScoreHistograms, String> {
+    let sender = {
+        let guard = state.sender.lock().map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+
+    if let Some(tx) = sender {
+        let (repl_tx, repl_rx) = oneshot::channel();
+        tx.send(Command::App(AppCommand::GetHistograms { sender: repl_tx }))
+            .await
+            .map_err(|e| e.to_string())?;
+        repl_rx.await.map_err(|e| e.to_string())
+    } else {
+        Err("Node not running".into())
+    }
+}
+
+/// Network statistics over the results published within `window` ("7d",
+/// "30d", "90d" or "all"), optionally weighting each by `0.5^(age / half_life_days)`.
+#[tauri::command]
+async fn get_windowed_stats(
+    window: network::window::TimeWindow,
+    half_life_days: Option<f64>,
+    state: State<'_, NetworkState>
+) -> Result<network::window::WindowedStats, String> {
+    let query = network::window::WindowQuery { window, half_life_days };
+    query.validate()?;
+    let sender = {
+        let guard = state.sender.lock().map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+
+    if let Some(tx) = sender {
+        let (repl_tx, repl_rx) = oneshot::channel();
+        tx.send(Command::App(AppCommand::GetWindowedStats { query, sender: repl_tx }))
+            .await
+            .map_err(|e| e.to_string())?;
+        repl_rx.await.map_err(|e| e.to_string())
+    } else {
+        Err("Node not running".into())
+    }
+}
+
+/// Percentile rank of the user's own score, overall and per category, against
+/// the current network aggregate. Purely local: nothing is signed or published,
+/// and it works whether or not sharing is enabled.
+#[tauri::command]
+async fn get_my_rank(
+    score: f64,
+    category_scores: std::collections::HashMap<String, f64>,
+    state: State<'_, NetworkState>
+) -> Result<network::views::ScoreRank, String> {
+    if !score.is_finite() || category_scores.values().any(|v| !v.is_finite()) {
+        return Err("Scores must be finite numbers".into());
+    }
+    if category_scores.len() > network::types::MAX_CATEGORIES {
+        return Err(format!(
+            "Too many categories: {} (maximum {})",
+            category_scores.len(),
+            network::types::MAX_CATEGORIES
+        ));
+    }
+    if let Some(category) = category_scores.keys().find(|c| !questions::is_known_category(c)) {
+        return Err(format!("Category '{}' is not a question in this question set", category));
+    }
+    let sender = {
+        let guard = state.sender.lock().map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+
+    if let Some(tx) = sender {
+        let (repl_tx, repl_rx) = oneshot::channel();
+        tx.send(Command::App(AppCommand::RankScore { score, category_scores, sender: repl_tx }))
+            .await
+            .map_err(|e| e.to_string())?;
+        repl_rx.await.map_err(|e| e.to_string())
+    } else {
+        Err("Node not running".into())
+    }
+}
+
+/// Score raw answers (question id → rating 0–10) with the canonical formula,
+/// sign the result with the user's persistent Ed25519 identity key and
+/// publish it to the gossipsub network. Scores are never taken from the
+/// webview, so a published result always matches the formula.
+/// Returns the IPFS CID of the published payload.
+/// Fails if network sharing has not been enabled via `set_network_sharing`.
+#[tauri::command]
+async fn publish_answers(
+    answers: std::collections::HashMap<String, f64>,
+    state: State<'_, NetworkState>
+) -> Result<String, String> {
+    let scores = scoring::score_answers(&answers)?;
+    sign_and_publish(scores.total, scores.category_scores, &state).await
+}
+
+async fn sign_and_publish(
+    score: f64,
+    category_scores: std::collections::HashMap<String, f64>,
+    state: &NetworkState,
+) -> Result<String, String> {
+    // Opt-in gate: sharing must be explicitly enabled (PRD Feature 3.6)
+    if !state.sharing_enabled.load(Ordering::Relaxed) {
+        return Err("Network sharing is disabled. Enable it in Settings to share results anonymously.".into());
+    }
+    // Build the result, rounding the timestamp if timing privacy is on
+    let timing = *state.publish_timing.lock().map_err(|e| e.to_string())?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    let timestamp = timing.map_or(now, |t| t.precision.coarsen(now));
+    let sequence = claim_sequence(timestamp, state)?;
+    let mut result = ManifestationResult {
+        score,
+        timestamp,
+        category_scores,
+        question_set: questions::question_set_hash().to_string(),
+        privacy: None,
+        unlinkable: false,
+        sequence,
+    };
+
+    // Validate before signing
+    result.validate()?;
+
+    // Get identity
+    let identity = {
+        let guard = state.identity.lock().map_err(|e| e.to_string())?;
+        guard.clone().ok_or_else(|| "Identity is locked or not initialized".to_string())?
+    };
+
+    // Unlinkable publishing signs with a key derived from the identity
+    let unlinkable = *state.unlinkable.lock().map_err(|e| e.to_string())?;
+    let identity = match unlinkable {
+        Some(settings) => {
+            result.unlinkable = true;
+            network::unlinkable::signing_identity(&identity, settings, timestamp)
+        }
+        None => identity,
+    };
+
+    // Opt-in local differential privacy: noise the validated scores
+    let privacy = *state.local_privacy.lock().map_err(|e| e.to_string())?;
+    if let Some(privacy) = privacy {
+        privacy.perturb(&mut result, &mut rand::thread_rng());
+    }
+
+    // Sign the result
+    let signed = SignedManifestation::new(result, &identity)?;
+    let cid = signed.payload.get_cid()?;
+    let payload_bytes = GossipMessage::Result(signed).encode()?;
+
+    // Publish via gossipsub, or hand to the node's delay queue
+    let sender = {
+        let guard = state.sender.lock().map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+    let Some(tx) = sender else {
+        return Err("Node not running".into());
+    };
+    let topic = network::types::GLOBAL_TOPIC.to_string();
+    match timing {
+        Some(timing) => {
+            let (ack_tx, ack_rx) = tokio::sync::oneshot::channel();
+            tx.send(Command::App(AppCommand::QueuePublish {
+                topic,
+                message: payload_bytes,
+                max_delay_secs: timing.max_delay_secs,
+                sender: ack_tx,
+            })).await.map_err(|e| e.to_string())?;
+            let release_at = ack_rx.await.map_err(|e| e.to_string())??;
+            println!("[publish] Queued result for release at {}", release_at);
+        }
+        None => {
+            let (ack_tx, ack_rx) = tokio::sync::oneshot::channel();
+            tx.send(Command::App(AppCommand::Publish { topic, message: payload_bytes, sender: ack_tx }))
+                .await.map_err(|e| e.to_string())?;
+            ack_rx.await.map_err(|e| e.to_string())?.map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(cid)
+}
+
+/// Number the next result stamped `timestamp`, so peers do not mistake a
+/// second result under the same rounded timestamp for a replay.
+fn claim_sequence(timestamp: u64, state: &NetworkState) -> Result<u32, String> {
+    let mut sequence = state.publication_sequence.lock().map_err(|e| e.to_string())?;
+    let claimed = sequence.claim(timestamp);
+    let path = state.settings_path.lock().map_err(|e| e.to_string())?.clone();
+    match path {
+        Some(path) => {
+            let value = serde_json::to_value(*sequence).map_err(|e| e.to_string())?;
+            write_setting(&path, "publication_sequence", value)?;
+        }
+        None => eprintln!("[settings] settings_path not set; publication sequence not persisted"),
+    }
+    Ok(claimed)
+}
+
+/// Enable or disable anonymous P2P result sharing.
+/// Sharing is **opt-in** and disabled by default (PRD Feature 3.6).
+/// The setting is persisted to disk and restored on next launch. Disabling
+/// also drops results still waiting in the publish queue.
+#[tauri::command]
+async fn set_network_sharing(enabled: bool, state: State<'_, NetworkState>, app: tauri::AppHandle) -> Result<(), String> {
+    state.sharing_enabled.store(enabled, Ordering::Relaxed);
+
+    // Resolve path from stored state; fall back to computing it from AppHandle
+    // so a None settings_path never silently skips the write.
+    let path: PathBuf = {
+        let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+        match path_guard.clone() {
+            Some(p) => p,
+            None => {
+                eprintln!("[settings] settings_path not set, resolving from app_data_dir");
+                let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+                if let Err(e) = std::fs::create_dir_all(&dir) {
+                    eprintln!("Failed to create dir: {}", e);
+                }
+                dir.join("app_settings.json")
+            }
+        }
+    };
+
+    save_settings(&path, enabled)?;
+    println!("[settings] Network sharing {} — persisted to {:?}", if enabled { "enabled" } else { "disabled" }, path);
+
+    // Without a running node the queue is dropped on its first release tick.
+    let sender = state.sender.lock().map_err(|e| e.to_string())?.clone();
+    if let (false, Some(tx)) = (enabled, sender) {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        tx.send(Command::App(AppCommand::ClearPublishQueue { sender: ack_tx })).await.map_err(|e| e.to_string())?;
+        let dropped = ack_rx.await.map_err(|e| e.to_string())?;
+        println!("[settings] Dropped {} queued publication(s)", dropped);
+    }
+    Ok(())
+}
+
+/// Return the current sharing opt-in state.
+#[tauri::command]
+fn get_network_sharing(state: State<'_, NetworkState>) -> Result<bool, String> {
+    Ok(state.sharing_enabled.load(Ordering::Relaxed))
+}
+
+/// Return the unlinkable publishing setting; `None` when results are signed
+/// with the root identity key.
+#[tauri::command]
+fn get_unlinkable_publishing(state: State<'_, NetworkState>) -> Result<Option<UnlinkableSettings>, String> {
+    let guard = state.unlinkable.lock().map_err(|e| e.to_string())?;
+    Ok(*guard)
+}
+
+/// Enable (with a key rotation) or disable (`None`) unlinkable publishing.
+/// Persisted next to `sharing_enabled`. Each derived key counts as its own
+/// author on the network, so a user gets one vote per day.
+#[tauri::command]
+fn set_unlinkable_publishing(settings: Option<UnlinkableSettings>, state: State<'_, NetworkState>) -> Result<(), String> {
+    let path = {
+        let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+        path_guard.clone().ok_or("Settings path not initialised")?
+    };
+    let value = serde_json::to_value(settings).map_err(|e| e.to_string())?;
+    write_setting(&path, "unlinkable_publishing", value)?;
+    *state.unlinkable.lock().map_err(|e| e.to_string())? = settings;
+    println!("[settings] Unlinkable publishing set to {:?}", settings);
+    Ok(())
+}
+
+/// Return the local differential-privacy setting; `None` when results are published exact.
+#[tauri::command]
+fn get_local_privacy(state: State<'_, NetworkState>) -> Result<Option<LocalPrivacy>, String> {
+    let guard = state.local_privacy.lock().map_err(|e| e.to_string())?;
+    Ok(*guard)
+}
+
+/// Enable (with a mechanism and epsilon) or disable (`None`) local
+/// differential privacy for future publications. Persisted to disk.
+#[tauri::command]
+fn set_local_privacy(privacy: Option<LocalPrivacy>, state: State<'_, NetworkState>) -> Result<(), String> {
+    if let Some(ref privacy) = privacy {
+        privacy.validate()?;
+    }
+    let path = {
+        let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+        path_guard.clone().ok_or("Settings path not initialised")?
+    };
+    let value = serde_json::to_value(privacy).map_err(|e| e.to_string())?;
+    write_setting(&path, "local_privacy", value)?;
+    *state.local_privacy.lock().map_err(|e| e.to_string())? = privacy;
+    println!("[settings] Local privacy set to {:?}", privacy);
+    Ok(())
+}
+
+/// Return the publish-timing setting; `None` when results are stamped to the
+/// second and published immediately.
+#[tauri::command]
+fn get_publish_timing(state: State<'_, NetworkState>) -> Result<Option<PublishTiming>, String> {
+    let guard = state.publish_timing.lock().map_err(|e| e.to_string())?;
+    Ok(*guard)
+}
+
+/// Enable (with a timestamp precision and maximum delay) or disable (`None`)
+/// timing privacy for future publications. Persisted to disk.
+#[tauri::command]
+fn set_publish_timing(timing: Option<PublishTiming>, state: State<'_, NetworkState>) -> Result<(), String> {
+    if let Some(ref timing) = timing {
+        timing.validate()?;
+    }
+    let path = {
+        let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+        path_guard.clone().ok_or("Settings path not initialised")?
+    };
+    let value = serde_json::to_value(timing).map_err(|e| e.to_string())?;
+    write_setting(&path, "publish_timing", value)?;
+    *state.publish_timing.lock().map_err(|e| e.to_string())? = timing;
+    println!("[settings] Publish timing set to {:?}", timing);
+    Ok(())
+}
+
+/// Paths of `identity.key` and `user_identity.json`.
+fn identity_paths(state: &NetworkState) -> Result<(PathBuf, PathBuf), String> {
+    let guard = state.key_path.lock().map_err(|e| e.to_string())?;
+    let key_path = guard.clone().ok_or("Identity path not initialised")?;
+    let user_id_path = key_path.with_file_name("user_identity.json");
+    Ok((key_path, user_id_path))
+}
+
+/// When the key files are encrypted, require `passphrase` and check it
+/// against `identity.key` before anything is written with it. Plaintext key
+/// files take none: encrypting only the files being written would leave
+/// `identity.key` in plaintext, so `set_key_passphrase` does them all.
+fn check_key_passphrase(key_path: &Path, user_id_path: &Path, passphrase: Option<&str>) -> Result<(), String> {
+    if passphrase::is_encrypted(key_path) || passphrase::is_encrypted(user_id_path) {
+        let passphrase = passphrase.ok_or("Key files are passphrase-protected; the passphrase is required")?;
+        keystore::read_key_file(key_path, Some(passphrase)).map_err(|e| e.to_string())?;
+    } else if passphrase.is_some() {
+        return Err("Key files are not passphrase-protected. Set a passphrase first.".into());
+    }
+    Ok(())
+}
+
+/// Report whether the identity files are encrypted and the identity locked.
+#[tauri::command]
+fn get_key_protection(state: State<'_, NetworkState>) -> Result<KeyProtection, String> {
+    let (key_path, user_id_path) = identity_paths(&state)?;
+    let encrypted = passphrase::is_encrypted(&key_path) || passphrase::is_encrypted(&user_id_path);
+    let unlocked = state.identity.lock().map_err(|e| e.to_string())?.is_some();
+    Ok(KeyProtection { encrypted, locked: encrypted && !unlocked })
+}
+
+/// Decrypt both identity files with `passphrase`, keep the user identity in
+/// memory for signing and start the P2P node if it is not running yet.
+/// Key derivation takes about a second, so it runs off the async runtime.
+#[tauri::command]
+async fn unlock_identity(passphrase: String, app: tauri::AppHandle, state: State<'_, NetworkState>) -> Result<(), String> {
+    let passphrase = Zeroizing::new(passphrase);
+    let (key_path, user_id_path) = identity_paths(&state)?;
+    let node_key_path = key_path.clone();
+    let (identity, id_keys) = tauri::async_runtime::spawn_blocking(move || -> Result<_, String> {
+        let identity = UserIdentity::load_or_create(&user_id_path, Some(passphrase.as_str()))
+            .map_err(|e| e.to_string())?;
+        let id_keys = load_or_generate_keypair(&node_key_path, Some(passphrase.as_str()))
+            .map_err(|e| e.to_string())?;
+        Ok((identity, id_keys))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    *state.identity.lock().map_err(|e| e.to_string())? = Some(identity);
+    start_node(app, key_path, id_keys);
+    println!("[identity] Unlocked");
+    Ok(())
+}
+
+/// Drop the user identity from memory; publishing fails until it is
+/// unlocked again. The running node keeps its transport key until restart.
+#[tauri::command]
+fn lock_identity(state: State<'_, NetworkState>) -> Result<(), String> {
+    let (_, user_id_path) = identity_paths(&state)?;
+    if !passphrase::is_encrypted(&user_id_path) {
+        return Err("Identity is not passphrase-protected. Set a passphrase first.".into());
+    }
+    *state.identity.lock().map_err(|e| e.to_string())? = None;
+    println!("[identity] Locked");
+    Ok(())
+}
+
+/// Encrypt the identity files under `new_passphrase` (migrating plaintext
+/// files), change the passphrase, or with `None` store them in plaintext
+/// again. All files are decrypted and staged before any is replaced, off
+/// the async runtime since each takes a key derivation.
+#[tauri::command]
+async fn set_key_passphrase(
+    current_passphrase: Option<String>,
+    new_passphrase: Option<String>,
+    state: State<'_, NetworkState>,
+) -> Result<(), String> {
+    let current = current_passphrase.map(Zeroizing::new);
+    let new = new_passphrase.map(Zeroizing::new);
+    if let Some(ref new) = new {
+        passphrase::validate_passphrase(new)?;
+    }
+    let (key_path, user_id_path) = identity_paths(&state)?;
+    let keyring_path = key_path.with_file_name("identity_keyring.json");
+    let mut paths = vec![key_path, user_id_path];
+    if keyring_path.exists() {
+        paths.push(keyring_path);
+    }
+    let encrypted = new.is_some();
+    tauri::async_runtime::spawn_blocking(move || {
+        passphrase::rewrite_key_files(
+            &paths,
+            current.as_deref().map(String::as_str),
+            new.as_deref().map(String::as_str),
+            KdfParams::default(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    println!("[identity] Key files are now {}", if encrypted { "encrypted" } else { "plaintext" });
+    Ok(())
+}
+
+/// Export the unlocked user identity as a 24-word recovery phrase or a
+/// password-protected backup file, whose key derivation runs off the async
+/// runtime.
+#[tauri::command]
+async fn export_identity(
+    target: backup::ExportTarget,
+    state: State<'_, NetworkState>,
+) -> Result<backup::ExportedIdentity, String> {
+    let identity = {
+        let guard = state.identity.lock().map_err(|e| e.to_string())?;
+        guard.clone().ok_or_else(|| "Identity is locked or not initialized".to_string())?
+    };
+    tauri::async_runtime::spawn_blocking(move || backup::export(&identity, target, KdfParams::default()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Restore the user identity from a recovery phrase or backup file and make
+/// it the current identity, archiving the one it replaces in
+/// `identity_keyring.json`. When the key files are encrypted, `passphrase`
+/// must be theirs; the restored file is encrypted with it too. The key
+/// derivations run off the async runtime. Returns the restored public key.
+#[tauri::command]
+async fn import_identity(
+    source: backup::ImportSource,
+    expected_public_key: Option<String>,
+    passphrase: Option<String>,
+    state: State<'_, NetworkState>,
+) -> Result<String, String> {
+    let passphrase = passphrase.map(Zeroizing::new);
+    let (key_path, user_id_path) = identity_paths(&state)?;
+    let keyring_path = key_path.with_file_name("identity_keyring.json");
+    let unlocked = state.identity.lock().map_err(|e| e.to_string())?.clone();
+    let identity = tauri::async_runtime::spawn_blocking(move || -> Result<UserIdentity, String> {
+        let passphrase = passphrase.as_deref().map(String::as_str);
+        check_key_passphrase(&key_path, &user_id_path, passphrase)?;
+        let identity = backup::import(source, expected_public_key.as_deref())?;
+        let current = match unlocked {
+            Some(current) => Some(current),
+            None if user_id_path.exists() => {
+                Some(UserIdentity::load_or_create(&user_id_path, passphrase).map_err(|e| e.to_string())?)
+            }
+            None => None,
+        };
+        keyring::import(current.as_ref(), &identity, &user_id_path, &keyring_path, passphrase)?;
+        Ok(identity)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let public_key = identity.public_key_b64();
+    *state.identity.lock().map_err(|e| e.to_string())? = Some(identity);
+    println!("[identity] Restored identity (pk: {}...)", &public_key[..8]);
+    Ok(public_key)
+}
+
+/// Replace the user identity with a fresh key. The old key is archived in
+/// `identity_keyring.json`, and a succession record signed by both keys is
+/// published so peers count the new key as the same identity. `passphrase`
+/// is required when the key files are encrypted. The key derivations run
+/// off the async runtime. Returns the new public key.
+#[tauri::command]
+async fn rotate_identity(passphrase: Option<String>, state: State<'_, NetworkState>) -> Result<String, String> {
+    let passphrase = passphrase.map(Zeroizing::new);
+    let current = {
+        let guard = state.identity.lock().map_err(|e| e.to_string())?;
+        guard.clone().ok_or_else(|| "Identity is locked or not initialized".to_string())?
+    };
+    let sender = {
+        let guard = state.sender.lock().map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+    let Some(tx) = sender else {
+        return Err("Node not running".into());
+    };
+    let (key_path, user_id_path) = identity_paths(&state)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    let keyring_path = key_path.with_file_name("identity_keyring.json");
+    let (next, succession) = tauri::async_runtime::spawn_blocking(move || {
+        let passphrase = passphrase.as_deref().map(String::as_str);
+        check_key_passphrase(&key_path, &user_id_path, passphrase)?;
+        keyring::rotate(&current, &user_id_path, &keyring_path, passphrase, now)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let public_key = next.public_key_b64();
+    *state.identity.lock().map_err(|e| e.to_string())? = Some(next);
+    println!("[identity] Rotated to a new key (pk: {}...)", &public_key[..8]);
+
+    // Through the publish queue, so the record is retried until peers are reachable
+    let (ack_tx, ack_rx) = oneshot::channel();
+    tx.send(Command::App(AppCommand::QueuePublish {
+        topic: network::types::GLOBAL_TOPIC.to_string(),
+        message: GossipMessage::Succession(succession).encode()?,
+        max_delay_secs: 0,
+        sender: ack_tx,
+    })).await.map_err(|e| e.to_string())?;
+    ack_rx.await.map_err(|e| e.to_string())??;
+    Ok(public_key)
+}
+
+/// Return the list of currently configured bootstrap peer addresses.
+/// Returns the project relayd defaults if none have been persisted yet.
+#[tauri::command]
+fn get_bootstrap_peers(state: State<'_, NetworkState>) -> Result<Vec<String>, String> {
+    let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+    if let Some(ref path) = *path_guard {
+        let (_, peers) = load_settings(path);
+        if !peers.is_empty() {
+            return Ok(peers.iter().map(|p| p.to_string()).collect());
+        }
+    }
+    Ok(network::node::PeerNode::default_bootstrap_peers()
+        .iter()
+        .map(|p| p.to_string())
+        .collect())
+}
+
+/// Persist a list of bootstrap peer multiaddresses.
+/// Changes take effect on the next application restart.
+#[tauri::command]
+fn set_bootstrap_peers(
+    peers: Vec<String>,
+    state: State<'_, NetworkState>,
+) -> Result<(), String> {
+    let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+    let path = path_guard.clone().ok_or("Settings path not initialised")?;
+    drop(path_guard);
+
+    // Re-read current settings and merge in the new peers
+    let mut existing: serde_json::Value = if path.exists() {
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        serde_json::from_str(&content).unwrap_or(serde_json::json!({}))
+    } else {
+        serde_json::json!({})
+    };
+
+    // Validate and normalise each peer address
+    let validated: Vec<String> = peers
+        .iter()
+        .filter_map(|s| s.parse::<libp2p::Multiaddr>().ok().map(|a| a.to_string()))
+        .collect();
+
+    existing["bootstrap_peers"] = serde_json::Value::Array(
+        validated.iter().map(|s| serde_json::Value::String(s.clone())).collect()
+    );
+
+    let content = serde_json::to_string(&existing).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| e.to_string())?;
+    println!("[settings] Saved {} bootstrap peers to {:?}", validated.len(), path);
+    Ok(())
+}
+
+/// Return the transports the node uses (TCP always, QUIC unless disabled).
+#[tauri::command]
+fn get_transports(state: State<'_, NetworkState>) -> Result<TransportConfig, String> {
+    let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+    Ok(path_guard.as_deref().map(load_transports).unwrap_or_default())
+}
+
+/// Enable or disable the QUIC transport. Persisted to disk; changes take
+/// effect on the next application restart.
+#[tauri::command]
+fn set_transports(transports: TransportConfig, state: State<'_, NetworkState>) -> Result<(), String> {
+    let path = {
+        let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+        path_guard.clone().ok_or("Settings path not initialised")?
+    };
+    let value = serde_json::to_value(transports).map_err(|e| e.to_string())?;
+    write_setting(&path, "transports", value)?;
+    println!("[settings] Transports set to {:?}", transports);
+    Ok(())
+}
+
+/// Return the configured circuit relays.
+#[tauri::command]
+fn get_relays(state: State<'_, NetworkState>) -> Result<Vec<String>, String> {
+    let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+    Ok(path_guard.as_deref().map(load_relays).unwrap_or_default().iter().map(|a| a.to_string()).collect())
+}
+
+/// Persist the circuit relays to reserve a slot on. Each must be a full
+/// multiaddress ending in `/p2p/<peer id>`. Changes take effect on the next
+/// application restart.
+#[tauri::command]
+fn set_relays(relays: Vec<String>, state: State<'_, NetworkState>) -> Result<(), String> {
+    let mut validated = Vec::new();
+    for relay in &relays {
+        let addr: libp2p::Multiaddr = relay.parse().map_err(|e| format!("Invalid relay address {}: {}", relay, e))?;
+        if !matches!(addr.iter().last(), Some(libp2p::multiaddr::Protocol::P2p(_))) {
+            return Err(format!("Relay address {} must end in /p2p/<peer id>", relay));
+        }
+        validated.push(serde_json::Value::String(addr.to_string()));
+    }
+    let path = {
+        let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+        path_guard.clone().ok_or("Settings path not initialised")?
+    };
+    write_setting(&path, "relays", serde_json::Value::Array(validated))?;
+    println!("[settings] Saved {} relays to {:?}", relays.len(), path);
+    Ok(())
+}
+
+/// Return how many days an author's result stays in the network aggregate,
+/// or `None` when results are kept until evicted by the author cap.
+#[tauri::command]
+fn get_retention_days(state: State<'_, NetworkState>) -> Result<Option<u64>, String> {
+    let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+    Ok(path_guard.as_deref().and_then(load_retention_days))
+}
+
+/// Persist the aggregate retention in days (`None` to keep results until
+/// evicted). Changes take effect on the next application restart.
+#[tauri::command]
+fn set_retention_days(days: Option<u64>, state: State<'_, NetworkState>) -> Result<(), String> {
+    AggregatorConfig::default().with_retention_days(days)?;
+    let path = {
+        let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+        path_guard.clone().ok_or("Settings path not initialised")?
+    };
+    write_setting(&path, "retention_days", serde_json::to_value(days).map_err(|e| e.to_string())?)?;
+    println!("[settings] Retention set to {:?} days", days);
+    Ok(())
+}
+
+/// Return the minimum cohort size: network statistics over fewer results
+/// are withheld.
+#[tauri::command]
+fn get_min_cohort_size(state: State<'_, NetworkState>) -> Result<usize, String> {
+    let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+    Ok(path_guard.as_deref().map(load_min_cohort_size).unwrap_or(DEFAULT_MIN_COHORT_SIZE))
+}
+
+/// Persist the minimum cohort size, at least 2. Changes take effect on the
+/// next application restart.
+#[tauri::command]
+fn set_min_cohort_size(size: usize, state: State<'_, NetworkState>) -> Result<(), String> {
+    AggregatorConfig::default().with_min_cohort_size(size)?;
+    let path = {
+        let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
+        path_guard.clone().ok_or("Settings path not initialised")?
+    };
+    write_setting(&path, "min_cohort_size", serde_json::Value::from(size))?;
+    println!("[settings] Minimum cohort size set to {}", size);
+    Ok(())
+}
+
+/// Start the P2P node with the transport key `id_keys`, unless it is
+/// already running. `key_path` locates the other files in the app data dir.
+fn start_node(app_handle: tauri::AppHandle, key_path: PathBuf, id_keys: libp2p::identity::Keypair) {
+    let (cmd_tx, cmd_rx) = mpsc::channel(32);
+    let (event_tx, mut event_rx) = mpsc::channel(32);
+
+    if let Some(state) = app_handle.try_state::<NetworkState>() {
+        if let Ok(mut guard) = state.sender.lock() {
+            if guard.is_some() {
+                return;
+            }
+            *guard = Some(cmd_tx.clone());
+        }
+    }
+
+    let handle_clone = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(stats) = event_rx.recv().await {
+            if let Err(e) = handle_clone.emit("network-stats", stats) {
+                eprintln!("Failed to emit network-stats: {}", e);
+            }
+        }
+    });
+
+    tauri::async_runtime::spawn(async move {
+        // Build cache path for network score persistence
+        let cache_path = match app_handle.path().app_data_dir() {
+            Ok(path) => Some(path.join("network_cache.json")),
+            Err(e) => {
+                eprintln!("Failed to get app_data_dir: {}", e);
+                None
+            },
+        };
+
+        let settings_path = key_path.with_file_name("app_settings.json");
+        let transports = load_transports(&settings_path);
+        let sharing_enabled = match app_handle.try_state::<NetworkState>() {
+            Some(state) => state.sharing_enabled.clone(),
+            None => Arc::new(AtomicBool::new(false)),
+        };
+        let config = NodeConfig {
+            aggregator: load_aggregator_config(&settings_path),
+            transports,
+            relays: load_relays(&settings_path),
+            sharing_enabled,
+            ..NodeConfig::default()
+        };
+        match PeerNode::new(id_keys, cmd_rx, event_tx, cache_path, {
+            // Load bootstrap peers from persisted settings; fall back to defaults if none set.
+            let (_, peers) = load_settings(&settings_path);
+            peers
+        }, config).await {
+            Ok(node) => {
+                println!("P2P Node created successfully.");
+
+                // Listen on TCP and, if enabled, QUIC; each listener starts on
+                // its own so a blocked UDP port does not stop TCP.
+                let mut acks = Vec::new();
+                for listen_addr in transports.listen_addrs(std::net::Ipv4Addr::UNSPECIFIED) {
+                    let (ack_tx, ack_rx) = oneshot::channel();
+                    if let Err(e) = cmd_tx.send(Command::StartListening {
+                        addr: listen_addr.clone(),
+                        sender: ack_tx
+                    }).await {
+                        eprintln!("Failed to send StartListening command: {}", e);
+                    }
+                    acks.push((listen_addr, ack_rx));
+                }
+
+                // The node only answers once it runs, so collect acks alongside it.
+                tauri::async_runtime::spawn(async move {
+                    for (listen_addr, ack_rx) in acks {
+                        match ack_rx.await {
+                            Ok(Ok(_)) => println!("P2P Node listening started on {}", listen_addr),
+                            Ok(Err(e)) => eprintln!("P2P Node failed to listen on {}: {}", listen_addr, e),
+                            Err(e) => eprintln!("P2P Node channel error: {}", e),
+                        }
+                    }
+                });
+
+                node.run().await;
+            }
+            Err(e) => {
+                eprintln!("Failed to create P2P node: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_sql::Builder::new().build())
+        .plugin(tauri_plugin_opener::init())
+        .manage(NetworkState {
+            sender: Mutex::new(None),
+            identity: Mutex::new(None),
+            sharing_enabled: Arc::new(AtomicBool::new(false)),
+            publication_sequence: Mutex::new(PublicationSequence::default()),
+            unlinkable: Mutex::new(None),
+            local_privacy: Mutex::new(None),
+            publish_timing: Mutex::new(None),
+            settings_path: Mutex::new(None),
+            key_path: Mutex::new(None),
+        })
+        .setup(|app| {
+            // Load persisted settings synchronously so get_network_sharing returns
+            // the correct value immediately when the frontend calls it on startup.
+            {
+                let settings_path = match app.path().app_data_dir() {
+                    Ok(path) => {
+                        if let Err(e) = std::fs::create_dir_all(&path) {
+                            eprintln!("Failed to create dir: {}", e);
+                        }
+                        path.join("app_settings.json")
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to get app_data_dir: {}", e);
+                        std::path::PathBuf::from("app_settings.json")
+                    },
+                };
+                let saved_sharing = load_settings(&settings_path);
+                let state = app.state::<NetworkState>();
+                state.sharing_enabled.store(saved_sharing.0, Ordering::Relaxed);
+                if let Ok(mut guard) = state.publication_sequence.lock() {
+                    *guard = load_setting(&settings_path, "publication_sequence").unwrap_or_default();
+                }
+                if let Ok(mut guard) = state.unlinkable.lock() {
+                    *guard = load_setting(&settings_path, "unlinkable_publishing");
+                }
+                if let Ok(mut guard) = state.local_privacy.lock() {
+                    *guard = load_local_privacy(&settings_path);
+                }
+                if let Ok(mut guard) = state.publish_timing.lock() {
+                    *guard = load_publish_timing(&settings_path);
+                }
+                if let Ok(mut guard) = state.settings_path.lock() {
+                    *guard = Some(settings_path);
+                }
+                println!("[setup] Loaded sharing_enabled={} from settings", saved_sharing.0);
+            }
+
+            let app_handle = app.handle().clone();
+
+            tauri::async_runtime::spawn(async move {
+                println!("Initializing P2P Node...");
+
+                // Path to identity key file
+                let key_path = match app_handle.path().app_data_dir() {
+                    Ok(path) => {
+                        if !path.exists() {
+                            if let Err(e) = std::fs::create_dir_all(&path) {
+                                eprintln!("Failed to create dir: {}", e);
+                            }
+                        }
+                        path.join("identity.key")
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to get app_data_dir: {}", e);
+                        std::path::PathBuf::from("identity.key")
+                    }, // Fallback
+                };
+
+                // Update settings_path to the canonical app_data_dir location now that
+                // we have the full key_path resolved; sharing_enabled was already loaded
+                // synchronously in setup above.
+                let settings_path = key_path.with_file_name("app_settings.json");
+                if let Some(state) = app_handle.try_state::<NetworkState>() {
+                    if let Ok(mut guard) = state.settings_path.lock() {
+                        // Only update if not already set (setup already set it)
+                        if guard.is_none() {
+                            *guard = Some(settings_path.clone());
+                        }
+                    }
+                    if let Ok(mut guard) = state.key_path.lock() {
+                        *guard = Some(key_path.clone());
+                    }
+                }
+
+                // Encrypted keys need the passphrase: unlock_identity starts the node
+                let user_id_path = key_path.with_file_name("user_identity.json");
+                if passphrase::is_encrypted(&key_path) || passphrase::is_encrypted(&user_id_path) {
+                    println!("Identity is passphrase-protected; waiting for unlock_identity.");
+                    return;
+                }
+
+                // Load or generate user identity (separate from P2P node ID)
+                let _user_identity = match UserIdentity::load_or_create(&user_id_path, None) {
+                    Ok(id) => {
+                        println!("User identity loaded (pk: {}...)", &id.public_key_b64()[..8]);
+                        if let Some(state) = app_handle.try_state::<NetworkState>() {
+                            if let Ok(mut guard) = state.identity.lock() {
+                                *guard = Some(id.clone());
+                            }
+                        }
+                        id
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to load user identity: {}. Using ephemeral.", e);
+                        UserIdentity::generate()
+                    }
+                };
+
+                let id_keys = match load_or_generate_keypair(&key_path, None) {
+                    Ok(kp) => {
+                        println!("Identity loaded/generated at {:?}", key_path);
+                        kp
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to init identity at {:?}: {}. Using ephemeral key.", key_path, e);
+                        libp2p::identity::Keypair::generate_ed25519()
+                    }
+                };
+
+                start_node(app_handle, key_path, id_keys);
+            });
+
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![get_peer_count, get_banned_peers, unban_peer, get_my_rank, get_score_histograms, get_windowed_stats, publish_answers, set_network_sharing, get_network_sharing, get_unlinkable_publishing, set_unlinkable_publishing, get_local_privacy, set_local_privacy, get_publish_timing, set_publish_timing, get_key_protection, unlock_identity, lock_identity, set_key_passphrase, export_identity, import_identity, rotate_identity, get_bootstrap_peers, set_bootstrap_peers, get_transports, set_transports, get_relays, set_relays, get_retention_days, set_retention_days, get_min_cohort_size, set_min_cohort_size])
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::ExitRequested { code: _, api: _, .. } = event {
+                 // Nothing special needed here unless preventing exit
+            }
+            if let tauri::RunEvent::Exit = event {
+                // Use try_send to avoid blocking the main thread during teardown.
+                // The channel has capacity 32; at shutdown it is effectively empty.
+                if let Some(state) = app_handle.try_state::<NetworkState>() {
+                    if let Ok(guard) = state.sender.lock() {
+                        if let Some(tx) = guard.as_ref() {
+                            if let Err(e) = tx.try_send(Command::Shutdown) {
+                                eprintln!("Warning: Could not signal P2P shutdown: {}", e);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+}
@@ -0,0 +1,213 @@
+use crate::identity::UserIdentity;
+use crate::keystore::{self, EncryptedKeyFile, KdfParams};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use zeroize::{Zeroize, Zeroizing};
+
+const BACKUP_VERSION: u32 = 1;
+
+/// Password-protected identity backup file. The public key is stored in the
+/// clear so a restore can check the decrypted secret really is that identity.
+#[derive(Serialize, Deserialize)]
+pub struct IdentityBackup {
+    version: u32,
+    pub public_key: String,
+    key: EncryptedKeyFile,
+}
+
+impl IdentityBackup {
+    pub fn create(identity: &UserIdentity, password: &str, kdf: KdfParams) -> Result<Self, String> {
+        keystore::passphrase::validate_passphrase(password)?;
+        let json = Zeroizing::new(identity.to_json().map_err(|e| e.to_string())?);
+        Ok(Self {
+            version: BACKUP_VERSION,
+            public_key: identity.public_key_b64(),
+            key: EncryptedKeyFile::seal(&json, password, kdf).map_err(|e| e.to_string())?,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        let backup: Self = serde_json::from_slice(&bytes).map_err(|e| format!("Not an identity backup: {}", e))?;
+        if backup.version != BACKUP_VERSION {
+            return Err(format!("Unsupported backup version {}", backup.version));
+        }
+        Ok(backup)
+    }
+
+    /// Decrypt the identity and check it matches the recorded public key.
+    pub fn restore(&self, password: &str) -> Result<UserIdentity, String> {
+        let json = self.key.open(password).map_err(|e| e.to_string())?;
+        let identity: UserIdentity = serde_json::from_slice(&json).map_err(|e| e.to_string())?;
+        if identity.public_key_b64() != self.public_key {
+            return Err("Backup is corrupt: its key does not match its public key".into());
+        }
+        Ok(identity)
+    }
+}
+
+/// Where `export_identity` puts the identity secret.
+#[derive(Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum ExportTarget {
+    /// Return the 24-word recovery phrase.
+    Mnemonic,
+    /// Write a password-protected backup file.
+    BackupFile { path: String, password: String },
+}
+
+/// Where `import_identity` restores the identity secret from.
+#[derive(Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum ImportSource {
+    Mnemonic { phrase: String },
+    BackupFile { path: String, password: String },
+}
+
+#[derive(Serialize)]
+pub struct ExportedIdentity {
+    pub public_key: String,
+    /// Set for `ExportTarget::Mnemonic` only.
+    pub mnemonic: Option<String>,
+}
+
+impl Drop for ExportedIdentity {
+    fn drop(&mut self) {
+        self.mnemonic.zeroize();
+    }
+}
+
+/// Export `identity` as a recovery phrase or a backup file.
+pub fn export(identity: &UserIdentity, target: ExportTarget, kdf: KdfParams) -> Result<ExportedIdentity, String> {
+    let public_key = identity.public_key_b64();
+    match target {
+        ExportTarget::Mnemonic => Ok(ExportedIdentity {
+            public_key,
+            // Moved out of the `Zeroizing` wrapper, not copied
+            mnemonic: Some(std::mem::take(&mut *identity.to_mnemonic())),
+        }),
+        ExportTarget::BackupFile { path, password } => {
+            let password = Zeroizing::new(password);
+            IdentityBackup::create(identity, &password, kdf)?.write(Path::new(&path))?;
+            Ok(ExportedIdentity { public_key, mnemonic: None })
+        }
+    }
+}
+
+/// Recover an identity from a recovery phrase or backup file. With
+/// `expected_public_key`, anything restoring a different identity fails.
+pub fn import(source: ImportSource, expected_public_key: Option<&str>) -> Result<UserIdentity, String> {
+    let identity = match source {
+        ImportSource::Mnemonic { phrase } => UserIdentity::from_mnemonic(&Zeroizing::new(phrase))?,
+        ImportSource::BackupFile { path, password } => {
+            IdentityBackup::read(Path::new(&path))?.restore(&Zeroizing::new(password))?
+        }
+    };
+    if let Some(expected) = expected_public_key {
+        if identity.public_key_b64() != expected {
+            return Err("Restored identity does not match the expected public key".into());
+        }
+    }
+    Ok(identity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_KDF: KdfParams = KdfParams { m_cost_kib: 64, t_cost: 1, p_cost: 1 };
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!("{}_{}.json", name, nanos))
+    }
+
+    #[test]
+    fn mnemonic_roundtrips() {
+        let identity = UserIdentity::generate();
+        let exported = export(&identity, ExportTarget::Mnemonic, TEST_KDF).unwrap();
+        let phrase = exported.mnemonic.clone().unwrap();
+        assert_eq!(phrase.split_whitespace().count(), 24);
+        let restored = import(ImportSource::Mnemonic { phrase }, Some(&exported.public_key)).unwrap();
+        assert_eq!(restored.public_key_b64(), identity.public_key_b64());
+    }
+
+    #[test]
+    fn mnemonic_ignores_case_and_spacing() {
+        let identity = UserIdentity::generate();
+        let phrase = format!("  {}\n", identity.to_mnemonic().to_uppercase().replace(' ', "   "));
+        let restored = UserIdentity::from_mnemonic(&phrase).unwrap();
+        assert_eq!(restored.public_key_b64(), identity.public_key_b64());
+    }
+
+    #[test]
+    fn mistyped_mnemonic_fails_checksum() {
+        let phrase = UserIdentity::generate().to_mnemonic();
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        // Flipping the last word's lowest bit changes only checksum bits
+        let english = bip39::Language::English;
+        let last = english.find_word(words[23]).unwrap() ^ 1;
+        words[23] = english.word_list()[last as usize];
+        assert!(UserIdentity::from_mnemonic(&words.join(" ")).is_err());
+        assert!(UserIdentity::from_mnemonic("abandon abandon abandon").is_err());
+    }
+
+    #[test]
+    fn backup_file_roundtrips_with_the_password() {
+        let identity = UserIdentity::generate();
+        let path = temp_path("identity_backup");
+        let target = ExportTarget::BackupFile { path: path.display().to_string(), password: "correct horse".into() };
+        export(&identity, target, TEST_KDF).unwrap();
+
+        let wrong = import(
+            ImportSource::BackupFile { path: path.display().to_string(), password: "battery staple".into() },
+            None,
+        );
+        let restored = import(
+            ImportSource::BackupFile { path: path.display().to_string(), password: "correct horse".into() },
+            None,
+        );
+        let _ = std::fs::remove_file(&path);
+
+        assert!(wrong.is_err());
+        assert_eq!(restored.unwrap().public_key_b64(), identity.public_key_b64());
+    }
+
+    #[test]
+    fn backup_with_mismatched_public_key_is_rejected() {
+        let mut backup = IdentityBackup::create(&UserIdentity::generate(), "correct horse", TEST_KDF).unwrap();
+        backup.public_key = UserIdentity::generate().public_key_b64();
+        assert!(backup.restore("correct horse").is_err());
+    }
+
+    #[test]
+    fn import_checks_the_expected_public_key() {
+        let phrase = UserIdentity::generate().to_mnemonic().to_string();
+        let other = UserIdentity::generate().public_key_b64();
+        assert!(import(ImportSource::Mnemonic { phrase }, Some(&other)).is_err());
+    }
+
+    #[test]
+    fn backup_password_must_be_long_enough() {
+        assert!(IdentityBackup::create(&UserIdentity::generate(), "short", TEST_KDF).is_err());
+    }
+
+    #[test]
+    fn export_target_is_tagged_by_format() {
+        let target: ExportTarget = serde_json::from_value(serde_json::json!({ "format": "mnemonic" })).unwrap();
+        assert!(matches!(target, ExportTarget::Mnemonic));
+        let source: ImportSource = serde_json::from_value(serde_json::json!({
+            "format": "backup_file", "path": "/tmp/b.json", "password": "pw"
+        }))
+        .unwrap();
+        assert!(matches!(source, ImportSource::BackupFile { .. }));
+    }
+}
@@ -0,0 +1,23 @@
+//! Headless bootstrap and relay node; see `manifestation-relayd --help`.
+
+use manifestation_app_lib::relayd::{self, RelaydConfig};
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", relayd::USAGE);
+        return;
+    }
+    let config = match RelaydConfig::from_args(args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = relayd::run(config).await {
+        eprintln!("manifestation-relayd: {}", e);
+        std::process::exit(1);
+    }
+}
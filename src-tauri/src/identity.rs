@@ -1,8 +1,7 @@
-use ed25519_dalek::{SigningKey, VerifyingKey, Signer, Signature, Verifier};
-use rand_core::OsRng;
-use std::path::Path;
+use ed25519_dalek::{VerifyingKey, Signature, Verifier};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
 
 /// Represents the user's pseudonymous/anonymous cryptographic identity.
 /// Saved to disk to provide stable identity across sessions.
@@ -13,6 +12,12 @@ pub struct UserIdentity {
     secret_bytes: Vec<u8>,
 }
 
+impl Drop for UserIdentity {
+    fn drop(&mut self) {
+        self.secret_bytes.zeroize();
+    }
+}
+
 mod base64_bytes {
     use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
     use serde::{Deserialize, Deserializer, Serializer};
@@ -28,77 +33,144 @@ mod base64_bytes {
 }
 
 impl UserIdentity {
-    /// Generate a fresh random Ed25519 identity
-    pub fn generate() -> Self {
-        let mut csprng = OsRng;
-        let keypair = SigningKey::generate(&mut csprng);
-        Self {
-            secret_bytes: keypair.to_bytes().to_vec(),
-        }
+    /// Verify a (message, signature, public_key) tuple — purely static, no self needed
+    pub fn verify(message: &[u8], signature_b64: &str, public_key_b64: &str) -> bool {
+        let Ok(pub_bytes) = BASE64.decode(public_key_b64) else { return false; };
+        let Ok(sig_bytes) = BASE64.decode(signature_b64) else { return false; };
+        let Ok(pub_arr) = <[u8; 32]>::try_from(pub_bytes.as_slice()) else { return false; };
+        let Ok(sig_arr) = <[u8; 64]>::try_from(sig_bytes.as_slice()) else { return false; };
+        let Ok(vk)  = VerifyingKey::from_bytes(&pub_arr) else { return false; };
+        let sig = Signature::from_bytes(&sig_arr);
+        vk.verify(message, &sig).is_ok()
     }
+}
 
-    /// Load from disk or create a new identity if the file does not exist
-    pub fn load_or_create<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
-        if path.as_ref().exists() {
-            let file = std::fs::File::open(&path)?;
-            serde_json::from_reader(file)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
-        } else {
-            let identity = Self::generate();
-            if let Some(parent) = path.as_ref().parent() {
-                std::fs::create_dir_all(parent)?;
+/// Holding, storing and signing with a secret key: the desktop app's side.
+/// The relay only verifies.
+#[cfg(any(feature = "gui", test))]
+mod signing {
+    use super::UserIdentity;
+    use crate::keystore;
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+    use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+    use rand_core::OsRng;
+    use sha2::{Digest, Sha256};
+    use std::path::Path;
+    use zeroize::Zeroizing;
+
+    impl UserIdentity {
+        /// Generate a fresh random Ed25519 identity
+        pub fn generate() -> Self {
+            let mut csprng = OsRng;
+            let keypair = SigningKey::generate(&mut csprng);
+            Self {
+                secret_bytes: keypair.to_bytes().to_vec(),
             }
-            let file = std::fs::File::create(&path)?;
-            serde_json::to_writer(file, &identity)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-            // Restrict permissions to owner-only (rw-------) on Unix
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)) {
-                    eprintln!("Failed to set permissions: {}", e);
-                }
+        }
+
+        /// Load from disk or create a new identity if the file does not exist.
+        /// An encrypted file needs `passphrase`; a new file is encrypted with it.
+        pub fn load_or_create<P: AsRef<Path>>(path: P, passphrase: Option<&str>) -> std::io::Result<Self> {
+            let path = path.as_ref();
+            if path.exists() {
+                let bytes = keystore::read_key_file(path, passphrase)?;
+                serde_json::from_slice(&bytes)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            } else {
+                let identity = Self::generate();
+                identity.save(path, passphrase)?;
+                Ok(identity)
             }
-            Ok(identity)
         }
-    }
 
-    fn signing_key(&self) -> SigningKey {
-        let bytes: [u8; 32] = self.secret_bytes.as_slice().try_into()
-            .expect("secret key must be 32 bytes");
-        SigningKey::from_bytes(&bytes)
-    }
+        /// Write this identity to `path`, encrypted under `passphrase` if given.
+        pub fn save(&self, path: &Path, passphrase: Option<&str>) -> std::io::Result<()> {
+            let json = Zeroizing::new(self.to_json()?);
+            keystore::write_key_file(path, &json, passphrase, keystore::KdfParams::default())
+        }
 
-    /// Sign arbitrary bytes; returns a detached signature
-    pub fn sign(&self, message: &[u8]) -> Signature {
-        self.signing_key().sign(message)
-    }
+        /// The identity file's plaintext JSON.
+        pub fn to_json(&self) -> std::io::Result<Vec<u8>> {
+            serde_json::to_vec(self).map_err(std::io::Error::other)
+        }
 
-    /// Ed25519 public key for this identity
-    pub fn verifying_key(&self) -> VerifyingKey {
-        self.signing_key().verifying_key()
-    }
+        /// Replace the identity file at `path` with this identity. It is staged
+        /// next to `path` and read back first, so a write that would not restore
+        /// this exact public key leaves the existing file untouched.
+        pub fn replace_file(&self, path: &Path, passphrase: Option<&str>) -> std::io::Result<()> {
+            let staged = path.with_extension("restore");
+            self.save(&staged, passphrase)?;
+            let restored = Self::load_or_create(&staged, passphrase);
+            if !restored.is_ok_and(|id| id.public_key_b64() == self.public_key_b64()) {
+                let _ = std::fs::remove_file(&staged);
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Restored identity does not match"));
+            }
+            std::fs::rename(&staged, path)
+        }
 
-    /// Base64-encoded public key string for embedding in messages
-    pub fn public_key_b64(&self) -> String {
-        BASE64.encode(self.verifying_key().as_bytes())
-    }
+        /// The secret key as a 24-word BIP39 recovery phrase.
+        pub fn to_mnemonic(&self) -> Zeroizing<String> {
+            let mnemonic = bip39::Mnemonic::from_entropy(&self.secret_bytes)
+                .expect("32 bytes is a valid BIP39 entropy length");
+            Zeroizing::new(mnemonic.to_string())
+        }
 
-    /// Verify a (message, signature, public_key) tuple — purely static, no self needed
-    pub fn verify(message: &[u8], signature_b64: &str, public_key_b64: &str) -> bool {
-        let Ok(pub_bytes) = BASE64.decode(public_key_b64) else { return false; };
-        let Ok(sig_bytes) = BASE64.decode(signature_b64) else { return false; };
-        let Ok(pub_arr) = <[u8; 32]>::try_from(pub_bytes.as_slice()) else { return false; };
-        let Ok(sig_arr) = <[u8; 64]>::try_from(sig_bytes.as_slice()) else { return false; };
-        let Ok(vk)  = VerifyingKey::from_bytes(&pub_arr) else { return false; };
-        let sig = Signature::from_bytes(&sig_arr);
-        vk.verify(message, &sig).is_ok()
+        /// Restore an identity from its recovery phrase. Case and spacing do
+        /// not matter; a mistyped word fails the phrase's checksum.
+        pub fn from_mnemonic(phrase: &str) -> Result<Self, String> {
+            let normalized = Zeroizing::new(phrase.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase());
+            let mnemonic = bip39::Mnemonic::parse_normalized(&normalized)
+                .map_err(|e| format!("Invalid recovery phrase: {}", e))?;
+            let entropy = Zeroizing::new(mnemonic.to_entropy());
+            if entropy.len() != 32 {
+                return Err("Recovery phrase must have 24 words".into());
+            }
+            Ok(Self { secret_bytes: entropy.to_vec() })
+        }
+
+        /// A separate identity derived from this one for `context`. The same
+        /// context always yields the same key, yet without the secret it cannot
+        /// be linked to this identity or to keys for other contexts.
+        pub fn derive(&self, context: &[u8]) -> UserIdentity {
+            let secret = Zeroizing::new(self.keyed_hash(b"manifestation/derived-key/v1", context));
+            Self { secret_bytes: secret.to_vec() }
+        }
+
+        fn keyed_hash(&self, domain: &[u8], context: &[u8]) -> [u8; 32] {
+            let mut hasher = Sha256::new();
+            hasher.update(domain);
+            hasher.update(&self.secret_bytes);
+            hasher.update(context);
+            hasher.finalize().into()
+        }
+
+        fn signing_key(&self) -> SigningKey {
+            let bytes: Zeroizing<[u8; 32]> = Zeroizing::new(self.secret_bytes.as_slice().try_into()
+                .expect("secret key must be 32 bytes"));
+            SigningKey::from_bytes(&bytes)
+        }
+
+        /// Sign arbitrary bytes; returns a detached signature
+        pub fn sign(&self, message: &[u8]) -> Signature {
+            self.signing_key().sign(message)
+        }
+
+        /// Ed25519 public key for this identity
+        pub fn verifying_key(&self) -> VerifyingKey {
+            self.signing_key().verifying_key()
+        }
+
+        /// Base64-encoded public key string for embedding in messages
+        pub fn public_key_b64(&self) -> String {
+            BASE64.encode(self.verifying_key().as_bytes())
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::keystore;
 
     #[test]
     fn test_sign_and_verify() {
@@ -152,10 +224,10 @@ mod tests {
         let sig = id.sign(msg);
         let sig_b64 = BASE64.encode(sig.to_bytes());
         // 31 bytes instead of 32 — should fail
-        let short_key = BASE64.encode(&[0u8; 31]);
+        let short_key = BASE64.encode([0u8; 31]);
         assert!(!UserIdentity::verify(msg, &sig_b64, &short_key));
         // 33 bytes
-        let long_key = BASE64.encode(&[0u8; 33]);
+        let long_key = BASE64.encode([0u8; 33]);
         assert!(!UserIdentity::verify(msg, &sig_b64, &long_key));
     }
 
@@ -194,6 +266,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_derive_is_deterministic_and_unlinkable() {
+        let root = UserIdentity::generate();
+        let a = root.derive(b"epoch-1");
+        assert_eq!(a.public_key_b64(), root.derive(b"epoch-1").public_key_b64());
+        assert_ne!(a.public_key_b64(), root.derive(b"epoch-2").public_key_b64());
+        assert_ne!(a.public_key_b64(), root.public_key_b64());
+        // Another identity derives a different key for the same context
+        assert_ne!(a.public_key_b64(), UserIdentity::generate().derive(b"epoch-1").public_key_b64());
+    }
+
     #[test]
     fn test_load_or_create_persists_identity() {
         let unique = format!("test_identity_{}.json", std::time::SystemTime::now()
@@ -203,11 +286,11 @@ mod tests {
         let path = std::env::temp_dir().join(unique);
 
         // First call: creates new identity
-        let id1 = UserIdentity::load_or_create(&path).expect("load_or_create failed");
+        let id1 = UserIdentity::load_or_create(&path, None).expect("load_or_create failed");
         let pk1 = id1.public_key_b64();
 
         // Second call: loads the same identity
-        let id2 = UserIdentity::load_or_create(&path).expect("load_or_create reload failed");
+        let id2 = UserIdentity::load_or_create(&path, None).expect("load_or_create reload failed");
         let pk2 = id2.public_key_b64();
 
         // Clean up
@@ -215,4 +298,40 @@ mod tests {
 
         assert_eq!(pk1, pk2, "Identity must be stable across calls on the same file");
     }
+
+    #[test]
+    fn test_load_or_create_decrypts_encrypted_file() {
+        let path = std::env::temp_dir().join(format!("test_identity_enc_{}.json", std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)));
+        let id = UserIdentity::generate();
+        let json = serde_json::to_vec(&id).unwrap();
+        let kdf = keystore::KdfParams { m_cost_kib: 64, t_cost: 1, p_cost: 1 };
+        keystore::write_key_file(&path, &json, Some("correct horse"), kdf).unwrap();
+
+        let locked = UserIdentity::load_or_create(&path, None);
+        let unlocked = UserIdentity::load_or_create(&path, Some("correct horse"));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(locked.is_err(), "an encrypted identity must not load without the passphrase");
+        assert_eq!(unlocked.unwrap().public_key_b64(), id.public_key_b64());
+    }
+
+    #[test]
+    fn test_replace_file_swaps_in_verified_identity() {
+        let path = std::env::temp_dir().join(format!("test_identity_replace_{}.json", std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)));
+        UserIdentity::load_or_create(&path, None).unwrap();
+        let restored = UserIdentity::generate();
+        restored.replace_file(&path, None).unwrap();
+        let loaded = UserIdentity::load_or_create(&path, None).unwrap();
+        let staged_left = path.with_extension("restore").exists();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.public_key_b64(), restored.public_key_b64());
+        assert!(!staged_left);
+    }
 }
@@ -0,0 +1,150 @@
+use crate::identity::UserIdentity;
+use crate::keystore::{self, KdfParams};
+use crate::network::succession::SignedSuccession;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// A user identity retired by `rotate`, with the succession that retired
+/// it, or replaced by `import`, with none.
+#[derive(Serialize, Deserialize)]
+pub struct RetiredIdentity {
+    pub identity: UserIdentity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub succession: Option<SignedSuccession>,
+}
+
+/// Archive of retired user identities, stored like the key files (and
+/// encrypted with the same passphrase) so old secrets are not lost.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Keyring {
+    pub retired: Vec<RetiredIdentity>,
+}
+
+impl Keyring {
+    /// Load the keyring at `path`; a missing file is an empty keyring.
+    pub fn load(path: &Path, passphrase: Option<&str>) -> std::io::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let bytes = keystore::read_key_file(path, passphrase)?;
+        serde_json::from_slice(&bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path, passphrase: Option<&str>) -> std::io::Result<()> {
+        let json = Zeroizing::new(serde_json::to_vec(self).map_err(std::io::Error::other)?);
+        keystore::write_key_file(path, &json, passphrase, KdfParams::default())
+    }
+}
+
+/// Replace `current` with a fresh identity. The old one is archived in the
+/// keyring before the identity file is replaced, so a failure in between
+/// never loses it. Returns the new identity and the succession to publish.
+pub fn rotate(
+    current: &UserIdentity,
+    identity_path: &Path,
+    keyring_path: &Path,
+    passphrase: Option<&str>,
+    now: u64,
+) -> Result<(UserIdentity, SignedSuccession), String> {
+    let next = UserIdentity::generate();
+    let succession = SignedSuccession::new(current, &next, now)?;
+    let retired = RetiredIdentity { identity: current.clone(), succession: Some(succession.clone()) };
+    retire(retired, &next, identity_path, keyring_path, passphrase)?;
+    Ok((next, succession))
+}
+
+/// Make `restored` the identity at `identity_path`, archiving `current`
+/// first unless it is the same identity. No succession links the two, so
+/// peers count them as different identities.
+pub fn import(
+    current: Option<&UserIdentity>,
+    restored: &UserIdentity,
+    identity_path: &Path,
+    keyring_path: &Path,
+    passphrase: Option<&str>,
+) -> Result<(), String> {
+    match current {
+        Some(current) if current.public_key_b64() != restored.public_key_b64() => {
+            let retired = RetiredIdentity { identity: current.clone(), succession: None };
+            retire(retired, restored, identity_path, keyring_path, passphrase)
+        }
+        _ => restored.replace_file(identity_path, passphrase).map_err(|e| e.to_string()),
+    }
+}
+
+fn retire(
+    retired: RetiredIdentity,
+    next: &UserIdentity,
+    identity_path: &Path,
+    keyring_path: &Path,
+    passphrase: Option<&str>,
+) -> Result<(), String> {
+    let mut keyring = Keyring::load(keyring_path, passphrase).map_err(|e| e.to_string())?;
+    keyring.retired.push(retired);
+    keyring.save(keyring_path, passphrase).map_err(|e| e.to_string())?;
+    next.replace_file(identity_path, passphrase).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> std::path::PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!("keyring_test_{}", nanos));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rotation_archives_the_old_key_and_replaces_the_file() {
+        let dir = temp_dir();
+        let identity_path = dir.join("user_identity.json");
+        let keyring_path = dir.join("identity_keyring.json");
+        let first = UserIdentity::load_or_create(&identity_path, None).unwrap();
+
+        let (second, succession) = rotate(&first, &identity_path, &keyring_path, None, 1_700_000_000).unwrap();
+        let (third, _) = rotate(&second, &identity_path, &keyring_path, None, 1_700_000_100).unwrap();
+        let loaded = UserIdentity::load_or_create(&identity_path, None).unwrap();
+        let keyring = Keyring::load(&keyring_path, None).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(succession.verify());
+        assert_eq!(succession.succession.old_key, first.public_key_b64());
+        assert_eq!(succession.succession.new_key, second.public_key_b64());
+        assert_eq!(loaded.public_key_b64(), third.public_key_b64());
+        let archived: Vec<String> = keyring.retired.iter().map(|r| r.identity.public_key_b64()).collect();
+        assert_eq!(archived, vec![first.public_key_b64(), second.public_key_b64()]);
+    }
+
+    #[test]
+    fn import_archives_the_replaced_identity_without_a_succession() {
+        let dir = temp_dir();
+        let identity_path = dir.join("user_identity.json");
+        let keyring_path = dir.join("identity_keyring.json");
+        let current = UserIdentity::load_or_create(&identity_path, None).unwrap();
+        let restored = UserIdentity::generate();
+
+        import(Some(&current), &restored, &identity_path, &keyring_path, None).unwrap();
+        // Importing the identity already in use archives nothing more
+        import(Some(&restored), &restored, &identity_path, &keyring_path, None).unwrap();
+        let loaded = UserIdentity::load_or_create(&identity_path, None).unwrap();
+        let keyring = Keyring::load(&keyring_path, None).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(loaded.public_key_b64(), restored.public_key_b64());
+        assert_eq!(keyring.retired.len(), 1);
+        assert_eq!(keyring.retired[0].identity.public_key_b64(), current.public_key_b64());
+        assert!(keyring.retired[0].succession.is_none());
+    }
+
+    #[test]
+    fn missing_keyring_is_empty() {
+        let keyring = Keyring::load(Path::new("/nonexistent/identity_keyring.json"), None).unwrap();
+        assert!(keyring.retired.is_empty());
+    }
+}
@@ -0,0 +1,333 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Format version of encrypted key files: Argon2id key derivation and
+/// XChaCha20-Poly1305 encryption of the original file contents.
+const KEYSTORE_VERSION: u32 = 1;
+
+/// Bound to every ciphertext so it cannot be reused in another format.
+const ASSOCIATED_DATA: &[u8] = b"manifestation/keystore/v1";
+
+/// Refuse to open files asking for more memory than this, so a tampered
+/// file cannot make unlocking exhaust the machine.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Argon2id cost parameters, stored in each file so they can be raised
+/// later without breaking existing files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub m_cost_kib: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    /// RFC 9106's second recommended option: 64 MiB, 3 passes, one lane.
+    fn default() -> Self {
+        Self { m_cost_kib: 64 * 1024, t_cost: 3, p_cost: 1 }
+    }
+}
+
+impl KdfParams {
+    fn derive_key(&self, passphrase: &str, salt: &[u8]) -> std::io::Result<Zeroizing<[u8; 32]>> {
+        if self.m_cost_kib > MAX_MEMORY_KIB {
+            return Err(Error::new(ErrorKind::InvalidData, "Key file asks for too much KDF memory"));
+        }
+        let params = Params::new(self.m_cost_kib, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        Ok(key)
+    }
+}
+
+/// On-disk form of an encrypted key file. The plaintext is the file's
+/// unencrypted contents, so either key file format can be wrapped.
+#[derive(Serialize, Deserialize)]
+pub struct EncryptedKeyFile {
+    version: u32,
+    kdf: KdfParams,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedKeyFile {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice::<Self>(bytes).ok().filter(|f| f.version == KEYSTORE_VERSION)
+    }
+
+    pub fn seal(plaintext: &[u8], passphrase: &str, kdf: KdfParams) -> std::io::Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let key = kdf.derive_key(passphrase, &salt)?;
+        let ciphertext = XChaCha20Poly1305::new(key.as_ref().into())
+            .encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad: ASSOCIATED_DATA })
+            .map_err(|_| Error::other("Encryption failed"))?;
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            kdf,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    pub fn open(&self, passphrase: &str) -> std::io::Result<Zeroizing<Vec<u8>>> {
+        let decode = |s: &str| BASE64.decode(s).map_err(|e| Error::new(ErrorKind::InvalidData, e));
+        let salt = decode(&self.salt)?;
+        let nonce = decode(&self.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "Key file nonce has the wrong length"));
+        }
+        let ciphertext = decode(&self.ciphertext)?;
+        let key = self.kdf.derive_key(passphrase, &salt)?;
+        XChaCha20Poly1305::new(key.as_ref().into())
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: ASSOCIATED_DATA })
+            .map(Zeroizing::new)
+            .map_err(|_| Error::new(ErrorKind::PermissionDenied, "Wrong passphrase"))
+    }
+}
+
+/// Read a key file, decrypting it with `passphrase` if it is encrypted.
+/// Plaintext files are returned as they are, whatever the passphrase.
+pub fn read_key_file(path: &Path, passphrase: Option<&str>) -> std::io::Result<Zeroizing<Vec<u8>>> {
+    let bytes = Zeroizing::new(std::fs::read(path)?);
+    match EncryptedKeyFile::parse(&bytes) {
+        None => Ok(bytes),
+        Some(file) => {
+            let passphrase = passphrase
+                .ok_or_else(|| Error::new(ErrorKind::PermissionDenied, "Identity is locked"))?;
+            file.open(passphrase)
+        }
+    }
+}
+
+/// Write a key file owner-only, encrypted under `passphrase` if one is given.
+/// Goes through a temporary file so an interrupted write leaves the old file.
+pub fn write_key_file(
+    path: &Path,
+    contents: &[u8],
+    passphrase: Option<&str>,
+    kdf: KdfParams,
+) -> std::io::Result<()> {
+    let staged = stage_key_file(path, contents, passphrase, kdf)?;
+    std::fs::rename(staged, path)
+}
+
+/// Write what `write_key_file` would to a temporary file next to `path`,
+/// and return the temporary file's path.
+fn stage_key_file(
+    path: &Path,
+    contents: &[u8],
+    passphrase: Option<&str>,
+    kdf: KdfParams,
+) -> std::io::Result<PathBuf> {
+    let encrypted;
+    let bytes = match passphrase {
+        Some(passphrase) => {
+            encrypted = serde_json::to_vec(&EncryptedKeyFile::seal(contents, passphrase, kdf)?)
+                .map_err(Error::other)?;
+            encrypted.as_slice()
+        }
+        None => contents,
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, bytes)?;
+    // Restrict permissions to owner-only (rw-------) on Unix
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(e) = std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600)) {
+            eprintln!("Failed to set permissions: {}", e);
+        }
+    }
+    Ok(tmp_path)
+}
+
+/// Choosing a passphrase and re-encrypting the key files under it, which
+/// only the desktop app offers. The relay just reads and writes its key.
+#[cfg(any(feature = "gui", test))]
+pub mod passphrase {
+    use super::{read_key_file, stage_key_file, EncryptedKeyFile, KdfParams};
+    use std::path::{Path, PathBuf};
+
+    /// Shortest passphrase accepted when encrypting the key files.
+    pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+    /// Whether the key file at `path` exists and is passphrase-encrypted.
+    pub fn is_encrypted(path: &Path) -> bool {
+        std::fs::read(path).is_ok_and(|bytes| EncryptedKeyFile::parse(&bytes).is_some())
+    }
+
+    /// Re-encrypt the key files at `paths` from `current` to `new` (either may
+    /// be `None` for plaintext). Every file is read and staged before the first
+    /// is renamed into place, so a wrong passphrase or a failed write leaves
+    /// all of them as they were.
+    pub fn rewrite_key_files(
+        paths: &[PathBuf],
+        current: Option<&str>,
+        new: Option<&str>,
+        kdf: KdfParams,
+    ) -> std::io::Result<()> {
+        let contents = paths
+            .iter()
+            .map(|path| read_key_file(path, current))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let mut staged = Vec::with_capacity(paths.len());
+        for (path, bytes) in paths.iter().zip(&contents) {
+            match stage_key_file(path, bytes, new, kdf) {
+                Ok(tmp_path) => staged.push(tmp_path),
+                Err(e) => {
+                    for tmp_path in &staged {
+                        let _ = std::fs::remove_file(tmp_path);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        for (tmp_path, path) in staged.iter().zip(paths) {
+            std::fs::rename(tmp_path, path)?;
+        }
+        Ok(())
+    }
+
+    /// Check a new passphrase before encrypting with it.
+    pub fn validate_passphrase(passphrase: &str) -> Result<(), String> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            return Err(format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LEN));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::passphrase::*;
+
+    /// Cheap parameters so tests do not spend 64 MiB per derivation.
+    const TEST_KDF: KdfParams = KdfParams { m_cost_kib: 64, t_cost: 1, p_cost: 1 };
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!("{}_{}", name, nanos))
+    }
+
+    #[test]
+    fn encrypted_file_roundtrips_with_the_passphrase() {
+        let path = temp_path("keystore_roundtrip");
+        write_key_file(&path, b"secret key bytes", Some("correct horse"), TEST_KDF).unwrap();
+        let on_disk = std::fs::read(&path).unwrap();
+        let read = read_key_file(&path, Some("correct horse")).unwrap();
+        let encrypted = is_encrypted(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(encrypted);
+        assert!(!on_disk.windows(6).any(|w| w == b"secret"), "plaintext must not reach the disk");
+        assert_eq!(read.as_slice(), b"secret key bytes");
+    }
+
+    #[test]
+    fn wrong_or_missing_passphrase_is_refused() {
+        let path = temp_path("keystore_wrong");
+        write_key_file(&path, b"secret", Some("correct horse"), TEST_KDF).unwrap();
+        let wrong = read_key_file(&path, Some("battery staple")).unwrap_err();
+        let missing = read_key_file(&path, None).unwrap_err();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(wrong.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(missing.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn plaintext_files_are_read_as_they_are() {
+        let path = temp_path("keystore_plain");
+        write_key_file(&path, br#"{"secret_bytes":"AAAA"}"#, None, TEST_KDF).unwrap();
+        let read = read_key_file(&path, Some("ignored passphrase")).unwrap();
+        let encrypted = is_encrypted(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(!encrypted);
+        assert_eq!(read.as_slice(), br#"{"secret_bytes":"AAAA"}"#);
+    }
+
+    #[test]
+    fn plaintext_files_migrate_to_encrypted_together() {
+        let dir = temp_path("keystore_migrate");
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = vec![dir.join("identity.key"), dir.join("user_identity.json"), dir.join("identity_keyring.json")];
+        for (i, path) in paths.iter().enumerate() {
+            write_key_file(path, format!("secret {}", i).as_bytes(), None, TEST_KDF).unwrap();
+        }
+
+        rewrite_key_files(&paths, None, Some("correct horse"), TEST_KDF).unwrap();
+        let encrypted = paths.iter().all(|p| is_encrypted(p));
+        let read: Vec<_> = paths.iter().map(|p| read_key_file(p, Some("correct horse")).unwrap()).collect();
+        let leftovers = std::fs::read_dir(&dir).unwrap().count();
+
+        // A wrong current passphrase changes nothing.
+        let refused = rewrite_key_files(&paths, Some("battery staple"), None, TEST_KDF);
+        let still_encrypted = paths.iter().all(|p| is_encrypted(p));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(encrypted);
+        for (i, bytes) in read.iter().enumerate() {
+            assert_eq!(bytes.as_slice(), format!("secret {}", i).as_bytes());
+        }
+        assert_eq!(leftovers, 3, "no staged files are left behind");
+        assert_eq!(refused.unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert!(still_encrypted);
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        let mut file = EncryptedKeyFile::seal(b"secret", "correct horse", TEST_KDF).unwrap();
+        let mut ciphertext = BASE64.decode(&file.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        file.ciphertext = BASE64.encode(ciphertext);
+        assert!(file.open("correct horse").is_err());
+    }
+
+    #[test]
+    fn each_encryption_uses_a_fresh_salt_and_nonce() {
+        let a = EncryptedKeyFile::seal(b"secret", "correct horse", TEST_KDF).unwrap();
+        let b = EncryptedKeyFile::seal(b"secret", "correct horse", TEST_KDF).unwrap();
+        assert_ne!(a.salt, b.salt);
+        assert_ne!(a.nonce, b.nonce);
+        assert_ne!(a.ciphertext, b.ciphertext);
+    }
+
+    #[test]
+    fn excessive_kdf_memory_is_refused() {
+        let mut file = EncryptedKeyFile::seal(b"secret", "correct horse", TEST_KDF).unwrap();
+        file.kdf.m_cost_kib = MAX_MEMORY_KIB + 1;
+        assert_eq!(file.open("correct horse").unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn short_passphrases_are_rejected() {
+        assert!(validate_passphrase("short").is_err());
+        assert!(validate_passphrase("long enough").is_ok());
+    }
+}
@@ -1,447 +1,41 @@
-use std::io::{Read, Write};
 use std::path::Path;
-use std::path::PathBuf;
 
+#[cfg(any(feature = "gui", test))]
+mod backup;
 mod network;
 mod identity;
-
-use network::{Command, PeerNode, ManifestationResult, SignedManifestation};
-use identity::UserIdentity;
-use std::sync::Mutex;
-use tauri::{Emitter, Manager, State};
-use tokio::sync::{mpsc, oneshot};
-
-struct NetworkState {
-    sender: Mutex<Option<mpsc::Sender<Command>>>,
-    identity: Mutex<Option<UserIdentity>>,
-    /// Controls whether `publish_result` forwards data to the P2P network.
-    /// Default: false (explicit opt-in required, per PRD Feature 3.6).
-    sharing_enabled: Mutex<bool>,
-    /// Path to the app settings JSON file for persisting sharing opt-in state.
-    settings_path: Mutex<Option<PathBuf>>,
-}
-
-fn load_or_generate_keypair(path: &Path) -> std::io::Result<libp2p::identity::Keypair> {
+#[cfg(any(feature = "gui", test))]
+mod keyring;
+mod keystore;
+mod questions;
+pub mod relayd;
+#[cfg(any(feature = "gui", test))]
+mod scoring;
+
+/// The Tauri desktop app. Without the `gui` feature only the headless
+/// `manifestation-relayd` builds, with no GUI libraries to link.
+#[cfg(feature = "gui")]
+mod app;
+#[cfg(feature = "gui")]
+pub use app::run;
+
+use keystore::KdfParams;
+use zeroize::Zeroizing;
+
+/// Load the libp2p node key, decrypting it with `passphrase` if it is
+/// encrypted, or generate and save one (encrypted if a passphrase is given).
+fn load_or_generate_keypair(path: &Path, passphrase: Option<&str>) -> std::io::Result<libp2p::identity::Keypair> {
     if path.exists() {
-        let mut bytes = Vec::new();
-        let mut file = std::fs::File::open(path)?;
-        file.read_to_end(&mut bytes)?;
-        
+        let bytes = keystore::read_key_file(path, passphrase)?;
         libp2p::identity::Keypair::from_protobuf_encoding(&bytes)
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
     } else {
         println!("Generating new identity keypair...");
         let keypair = libp2p::identity::Keypair::generate_ed25519();
-        let encoded = keypair.to_protobuf_encoding()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-            
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        
-        let mut file = std::fs::File::create(path)?;
-        file.write_all(&encoded)?;
-        // Restrict permissions to owner-only (rw-------) on Unix
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Err(e) = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)) {
-                eprintln!("Failed to set permissions: {}", e);
-            }
-        }
+        let encoded = Zeroizing::new(keypair.to_protobuf_encoding()
+            .map_err(std::io::Error::other)?);
+        keystore::write_key_file(path, &encoded, passphrase, KdfParams::default())?;
         println!("Saved identity to {:?}", path);
         Ok(keypair)
     }
 }
-
-/// Load sharing opt-in state from the app settings file.
-fn load_settings(path: &Path) -> (bool, Vec<libp2p::Multiaddr>) {
-    let Ok(content) = std::fs::read_to_string(path) else { return (false, vec![]); };
-    let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) else { return (false, vec![]); };
-    let sharing = json.get("sharing_enabled").and_then(|v| v.as_bool()).unwrap_or(false);
-    let peers: Vec<libp2p::Multiaddr> = json
-        .get("bootstrap_peers")
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|s| s.as_str()?.parse().ok())
-                .collect()
-        })
-        .unwrap_or_default();
-    (sharing, peers)
-}
-
-/// Persist sharing opt-in state to the app settings file.
-/// Preserves any existing keys (e.g. bootstrap_peers) by doing a read-merge-write.
-fn save_settings(path: &Path, sharing_enabled: bool) -> Result<(), String> {
-    let mut existing: serde_json::Value = if path.exists() {
-        let content = std::fs::read_to_string(path).unwrap_or_default();
-        serde_json::from_str(&content).unwrap_or(serde_json::json!({}))
-    } else {
-        serde_json::json!({})
-    };
-    existing["sharing_enabled"] = serde_json::Value::Bool(sharing_enabled);
-    let content = serde_json::to_string(&existing).map_err(|e| e.to_string())?;
-    std::fs::write(path, content).map_err(|e| {
-        eprintln!("[settings] Failed to write {:?}: {}", path, e);
-        e.to_string()
-    })
-}
-
-#[tauri::command]
-async fn get_peer_count(state: State<'_, NetworkState>) -> Result<usize, String> {
-    let sender = {
-        let guard = state.sender.lock().map_err(|e| e.to_string())?;
-        guard.clone()
-    };
-
-    if let Some(tx) = sender {
-        let (repl_tx, repl_rx) = oneshot::channel();
-        tx.send(Command::GetPeers { sender: repl_tx })
-            .await
-            .map_err(|e| e.to_string())?;
-            
-        let peers = repl_rx.await.map_err(|e| e.to_string())?;
-        Ok(peers.len())
-    } else {
-        Err("Node not running".into())
-    }
-}
-
-/// Publish a signed manifestation result to the gossipsub network.
-/// The result is signed with the user's persistent Ed25519 identity key.
-/// Returns the IPFS CID of the published payload.
-/// Fails if network sharing has not been enabled via `set_network_sharing`.
-#[tauri::command]
-async fn publish_result(
-    score: f64,
-    category_scores: std::collections::HashMap<String, f64>,
-    state: State<'_, NetworkState>
-) -> Result<String, String> {
-    // Opt-in gate: sharing must be explicitly enabled (PRD Feature 3.6)
-    {
-        let guard = state.sharing_enabled.lock().map_err(|e| e.to_string())?;
-        if !*guard {
-            return Err("Network sharing is disabled. Enable it in Settings to share results anonymously.".into());
-        }
-    }
-    // Build the result
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
-        .as_secs();
-    let result = ManifestationResult { score, timestamp, category_scores };
-
-    // Validate before signing
-    result.validate()?;
-
-    // Get identity
-    let identity = {
-        let guard = state.identity.lock().map_err(|e| e.to_string())?;
-        guard.clone().ok_or_else(|| "Identity not initialized".to_string())?
-    };
-
-    // Sign the result
-    let signed = SignedManifestation::new(result, &identity)?;
-    let cid = signed.payload.get_cid()?;
-    let payload_bytes = serde_json::to_vec(&signed).map_err(|e| e.to_string())?;
-
-    // Publish via gossipsub
-    let sender = {
-        let guard = state.sender.lock().map_err(|e| e.to_string())?;
-        guard.clone()
-    };
-    if let Some(tx) = sender {
-        let (ack_tx, ack_rx) = tokio::sync::oneshot::channel();
-        tx.send(Command::Publish {
-            topic: "manifestation-global".to_string(),
-            message: payload_bytes,
-            sender: ack_tx,
-        }).await.map_err(|e| e.to_string())?;
-        ack_rx.await.map_err(|e| e.to_string())?.map_err(|e| e.to_string())?;
-    } else {
-        return Err("Node not running".into());
-    }
-    Ok(cid)
-}
-
-
-/// Enable or disable anonymous P2P result sharing.
-/// Sharing is **opt-in** and disabled by default (PRD Feature 3.6).
-/// The setting is persisted to disk and restored on next launch.
-#[tauri::command]
-fn set_network_sharing(enabled: bool, state: State<'_, NetworkState>, app: tauri::AppHandle) -> Result<(), String> {
-    {
-        let mut guard = state.sharing_enabled.lock().map_err(|e| e.to_string())?;
-        *guard = enabled;
-    }
-
-    // Resolve path from stored state; fall back to computing it from AppHandle
-    // so a None settings_path never silently skips the write.
-    let path: PathBuf = {
-        let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
-        match path_guard.clone() {
-            Some(p) => p,
-            None => {
-                eprintln!("[settings] settings_path not set, resolving from app_data_dir");
-                let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-                if let Err(e) = std::fs::create_dir_all(&dir) {
-                    eprintln!("Failed to create dir: {}", e);
-                }
-                dir.join("app_settings.json")
-            }
-        }
-    };
-
-    save_settings(&path, enabled)?;
-    println!("[settings] Network sharing {} — persisted to {:?}", if enabled { "enabled" } else { "disabled" }, path);
-    Ok(())
-}
-
-/// Return the current sharing opt-in state.
-#[tauri::command]
-fn get_network_sharing(state: State<'_, NetworkState>) -> Result<bool, String> {
-    let guard = state.sharing_enabled.lock().map_err(|e| e.to_string())?;
-    Ok(*guard)
-}
-
-/// Return the list of currently configured bootstrap peer addresses.
-/// Returns the hardcoded defaults if none have been persisted yet.
-#[tauri::command]
-fn get_bootstrap_peers(state: State<'_, NetworkState>) -> Result<Vec<String>, String> {
-    let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
-    if let Some(ref path) = *path_guard {
-        let (_, peers) = load_settings(path);
-        if !peers.is_empty() {
-            return Ok(peers.iter().map(|p| p.to_string()).collect());
-        }
-    }
-    Ok(network::node::PeerNode::default_bootstrap_peers()
-        .iter()
-        .map(|p| p.to_string())
-        .collect())
-}
-
-/// Persist a list of bootstrap peer multiaddresses.
-/// Changes take effect on the next application restart.
-#[tauri::command]
-fn set_bootstrap_peers(
-    peers: Vec<String>,
-    state: State<'_, NetworkState>,
-) -> Result<(), String> {
-    let path_guard = state.settings_path.lock().map_err(|e| e.to_string())?;
-    let path = path_guard.clone().ok_or("Settings path not initialised")?;
-    drop(path_guard);
-
-    // Re-read current settings and merge in the new peers
-    let mut existing: serde_json::Value = if path.exists() {
-        let content = std::fs::read_to_string(&path).unwrap_or_default();
-        serde_json::from_str(&content).unwrap_or(serde_json::json!({}))
-    } else {
-        serde_json::json!({})
-    };
-
-    // Validate and normalise each peer address
-    let validated: Vec<String> = peers
-        .iter()
-        .filter_map(|s| s.parse::<libp2p::Multiaddr>().ok().map(|a| a.to_string()))
-        .collect();
-
-    existing["bootstrap_peers"] = serde_json::Value::Array(
-        validated.iter().map(|s| serde_json::Value::String(s.clone())).collect()
-    );
-
-    let content = serde_json::to_string(&existing).map_err(|e| e.to_string())?;
-    std::fs::write(&path, content).map_err(|e| e.to_string())?;
-    println!("[settings] Saved {} bootstrap peers to {:?}", validated.len(), path);
-    Ok(())
-}
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_updater::Builder::new().build())
-        .plugin(tauri_plugin_process::init())
-        .plugin(tauri_plugin_sql::Builder::new().build())
-        .plugin(tauri_plugin_opener::init())
-        .manage(NetworkState {
-            sender: Mutex::new(None),
-            identity: Mutex::new(None),
-            sharing_enabled: Mutex::new(false),
-            settings_path: Mutex::new(None),
-        })
-        .setup(|app| {
-            // Load persisted settings synchronously so get_network_sharing returns
-            // the correct value immediately when the frontend calls it on startup.
-            {
-                let settings_path = match app.path().app_data_dir() {
-                    Ok(path) => {
-                        if let Err(e) = std::fs::create_dir_all(&path) {
-                            eprintln!("Failed to create dir: {}", e);
-                        }
-                        path.join("app_settings.json")
-                    },
-                    Err(e) => {
-                        eprintln!("Failed to get app_data_dir: {}", e);
-                        std::path::PathBuf::from("app_settings.json")
-                    },
-                };
-                let saved_sharing = load_settings(&settings_path);
-                let state = app.state::<NetworkState>();
-                if let Ok(mut guard) = state.sharing_enabled.lock() {
-                    *guard = saved_sharing.0;
-                }
-                if let Ok(mut guard) = state.settings_path.lock() {
-                    *guard = Some(settings_path);
-                }
-                println!("[setup] Loaded sharing_enabled={} from settings", saved_sharing.0);
-            }
-
-            let app_handle = app.handle().clone();
-
-            tauri::async_runtime::spawn(async move {
-                println!("Initializing P2P Node...");
-                let (cmd_tx, cmd_rx) = mpsc::channel(32);
-                let (event_tx, mut event_rx) = mpsc::channel(32);
-
-                if let Some(state) = app_handle.try_state::<NetworkState>() {
-                    if let Ok(mut guard) = state.sender.lock() {
-                        *guard = Some(cmd_tx.clone());
-                    }
-                }
-
-                let handle_clone = app_handle.clone();
-                tauri::async_runtime::spawn(async move {
-                    while let Some(stats) = event_rx.recv().await {
-                        if let Err(e) = handle_clone.emit("network-stats", stats) {
-                            eprintln!("Failed to emit network-stats: {}", e);
-                        }
-                    }
-                });
-
-
-                // Path to identity key file
-                let key_path = match app_handle.path().app_data_dir() {
-                    Ok(path) => {
-                        if !path.exists() {
-                            if let Err(e) = std::fs::create_dir_all(&path) {
-                                eprintln!("Failed to create dir: {}", e);
-                            }
-                        }
-                        path.join("identity.key")
-                    },
-                    Err(e) => {
-                        eprintln!("Failed to get app_data_dir: {}", e);
-                        std::path::PathBuf::from("identity.key")
-                    }, // Fallback
-                };
-
-                // Update settings_path to the canonical app_data_dir location now that
-                // we have the full key_path resolved; sharing_enabled was already loaded
-                // synchronously in setup above.
-                let settings_path = key_path.with_file_name("app_settings.json");
-                if let Some(state) = app_handle.try_state::<NetworkState>() {
-                    if let Ok(mut guard) = state.settings_path.lock() {
-                        // Only update if not already set (setup already set it)
-                        if guard.is_none() {
-                            *guard = Some(settings_path.clone());
-                        }
-                    }
-                }
-
-                // Load or generate user identity (separate from P2P node ID)
-                let user_id_path = key_path.with_file_name("user_identity.json");
-                let _user_identity = match UserIdentity::load_or_create(&user_id_path) {
-                    Ok(id) => {
-                        println!("User identity loaded (pk: {}...)", &id.public_key_b64()[..8]);
-                        if let Some(state) = app_handle.try_state::<NetworkState>() {
-                            if let Ok(mut guard) = state.identity.lock() {
-                                *guard = Some(id.clone());
-                            }
-                        }
-                        id
-                    },
-                    Err(e) => {
-                        eprintln!("Failed to load user identity: {}. Using ephemeral.", e);
-                        UserIdentity::generate()
-                    }
-                };
-
-                let id_keys = match load_or_generate_keypair(&key_path) {
-                    Ok(kp) => {
-                        println!("Identity loaded/generated at {:?}", key_path);
-                        kp
-                    },
-                    Err(e) => {
-                        eprintln!("Failed to init identity at {:?}: {}. Using ephemeral key.", key_path, e);
-                        libp2p::identity::Keypair::generate_ed25519()
-                    }
-                };
-
-                // Build cache path for network score persistence
-                let cache_path = match app_handle.path().app_data_dir() {
-                    Ok(path) => Some(path.join("network_cache.json")),
-                    Err(e) => {
-                        eprintln!("Failed to get app_data_dir: {}", e);
-                        None
-                    },
-                };
-
-                match PeerNode::new(id_keys, cmd_rx, event_tx, cache_path, {
-                    // Load bootstrap peers from persisted settings; fall back to defaults if none set.
-                    let (_, peers) = load_settings(&settings_path);
-                    peers
-                }).await {
-                    Ok(node) => {
-                        println!("P2P Node created successfully.");
-                        
-                        let (ack_tx, ack_rx) = oneshot::channel();
-                        let listen_addr = "/ip4/0.0.0.0/tcp/0".parse().unwrap();
-                        
-                        if let Err(e) = cmd_tx.send(Command::StartListening { 
-                            addr: listen_addr, 
-                            sender: ack_tx 
-                        }).await {
-                            eprintln!("Failed to send StartListening command: {}", e);
-                        }
-
-                        match ack_rx.await {
-                            Ok(Ok(_)) => println!("P2P Node listening started"),
-                            Ok(Err(e)) => eprintln!("P2P Node failed to listen: {}", e),
-                            Err(e) => eprintln!("P2P Node channel error: {}", e),
-                        }
-
-                        node.run().await;
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to create P2P node: {}", e);
-                    }
-                }
-            });
-
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![get_peer_count, publish_result, set_network_sharing, get_network_sharing, get_bootstrap_peers, set_bootstrap_peers])
-        .build(tauri::generate_context!())
-        .expect("error while running tauri application")
-        .run(|app_handle, event| {
-            if let tauri::RunEvent::ExitRequested { code: _, api: _, .. } = event {
-                 // Nothing special needed here unless preventing exit
-            }
-            if let tauri::RunEvent::Exit = event {
-                // Use try_send to avoid blocking the main thread during teardown.
-                // The channel has capacity 32; at shutdown it is effectively empty.
-                if let Some(state) = app_handle.try_state::<NetworkState>() {
-                    if let Ok(guard) = state.sender.lock() {
-                        if let Some(tx) = guard.as_ref() {
-                            if let Err(e) = tx.try_send(Command::Shutdown) {
-                                eprintln!("Warning: Could not signal P2P shutdown: {}", e);
-                            }
-                        }
-                    }
-                }
-            }
-        });
-}
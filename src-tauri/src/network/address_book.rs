@@ -0,0 +1,226 @@
+use libp2p::{Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Peers not seen for this long are forgotten.
+pub const MAX_PEER_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Most peers kept; the least recently seen are dropped first.
+pub const MAX_PEERS: usize = 1000;
+
+/// Most addresses kept per peer; the most recently reported come first.
+const MAX_ADDRESSES_PER_PEER: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownPeer {
+    pub peer_id: String,
+    pub addresses: Vec<Multiaddr>,
+    /// Unix timestamp (seconds) of the last connection to the peer.
+    pub last_seen: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct AddressBookFile {
+    peers: Vec<KnownPeer>,
+}
+
+/// Peers on our DHT and where to reach them, persisted so a restart can
+/// reconnect without bootstrap peers or mDNS.
+#[derive(Debug, Default)]
+pub struct AddressBook {
+    peers: HashMap<PeerId, KnownPeer>,
+    path: Option<PathBuf>,
+}
+
+impl AddressBook {
+    /// Load the address book persisted at `path`, if any, without entries
+    /// older than `MAX_PEER_AGE_SECS`.
+    pub fn load(path: Option<PathBuf>, now: u64) -> Self {
+        let peers = path.as_deref()
+            .and_then(|p| std::fs::File::open(p).ok())
+            .and_then(|file| serde_json::from_reader::<_, AddressBookFile>(file).ok())
+            .map(|f| f.peers)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|peer| Some((peer.peer_id.parse().ok()?, peer)))
+            .collect();
+        let mut book = Self { peers, path };
+        book.expire(now);
+        book
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Record addresses a peer reported, most preferred first.
+    pub fn observe(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>, now: u64) {
+        let entry = self.peers.entry(peer_id).or_insert_with(|| KnownPeer {
+            peer_id: peer_id.to_string(),
+            addresses: vec![],
+            last_seen: now,
+        });
+        entry.addresses.retain(|a| !addresses.contains(a));
+        entry.addresses.splice(0..0, addresses);
+        entry.addresses.truncate(MAX_ADDRESSES_PER_PEER);
+        entry.last_seen = entry.last_seen.max(now);
+    }
+
+    /// Record a Kademlia routing table entry. Unknown addresses go after the
+    /// known ones and a known peer keeps its last-seen time, so entries that
+    /// sit in the table without ever connecting still age out.
+    pub fn merge(&mut self, peer_id: PeerId, addresses: impl IntoIterator<Item = Multiaddr>, now: u64) {
+        let entry = self.peers.entry(peer_id).or_insert_with(|| KnownPeer {
+            peer_id: peer_id.to_string(),
+            addresses: vec![],
+            last_seen: now,
+        });
+        for address in addresses {
+            if !entry.addresses.contains(&address) {
+                entry.addresses.push(address);
+            }
+        }
+        entry.addresses.truncate(MAX_ADDRESSES_PER_PEER);
+    }
+
+    /// Mark a known peer as seen at `now`.
+    pub fn seen(&mut self, peer_id: &PeerId, now: u64) {
+        if let Some(entry) = self.peers.get_mut(peer_id) {
+            entry.last_seen = entry.last_seen.max(now);
+        }
+    }
+
+    pub fn remove(&mut self, peer_id: &PeerId) {
+        self.peers.remove(peer_id);
+    }
+
+    /// Known peers, most recently seen first.
+    pub fn peers(&self) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        let mut peers: Vec<_> = self.peers.iter().collect();
+        peers.sort_by_key(|(_, peer)| std::cmp::Reverse(peer.last_seen));
+        peers.into_iter().map(|(id, peer)| (*id, peer.addresses.clone())).collect()
+    }
+
+    /// Drop peers past `MAX_PEER_AGE_SECS`, then the least recently seen
+    /// beyond `MAX_PEERS`.
+    fn expire(&mut self, now: u64) {
+        self.peers.retain(|_, peer| now.saturating_sub(peer.last_seen) <= MAX_PEER_AGE_SECS);
+        if self.peers.len() > MAX_PEERS {
+            let mut by_age: Vec<(u64, PeerId)> = self.peers.iter().map(|(id, p)| (p.last_seen, *id)).collect();
+            by_age.sort();
+            for (_, peer_id) in by_age.iter().take(self.peers.len() - MAX_PEERS) {
+                self.peers.remove(peer_id);
+            }
+        }
+    }
+
+    /// Expire old entries and write the rest to disk.
+    pub fn save(&mut self, now: u64) {
+        self.expire(now);
+        let Some(path) = self.path.as_deref() else { return };
+        if let Err(e) = write_book(path, self.peers.values().cloned().collect()) {
+            eprintln!("Failed to save address book: {}", e);
+        }
+    }
+}
+
+fn write_book(path: &Path, peers: Vec<KnownPeer>) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    serde_json::to_writer(file, &AddressBookFile { peers }).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn addr(port: u16) -> Multiaddr {
+        format!("/ip4/10.0.0.1/tcp/{}", port).parse().unwrap()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!("{}_{}.json", name, nanos))
+    }
+
+    #[test]
+    fn new_addresses_go_first_without_duplicates() {
+        let peer = PeerId::random();
+        let mut book = AddressBook::default();
+        book.observe(peer, vec![addr(1), addr(2)], NOW);
+        book.observe(peer, vec![addr(3), addr(1)], NOW + 1);
+        assert_eq!(book.peers(), vec![(peer, vec![addr(3), addr(1), addr(2)])]);
+    }
+
+    #[test]
+    fn routing_table_entries_add_addresses_without_refreshing_known_peers() {
+        let (known, unknown) = (PeerId::random(), PeerId::random());
+        let mut book = AddressBook::default();
+        book.observe(known, vec![addr(1)], NOW);
+        book.merge(known, vec![addr(2), addr(1)], NOW + 60);
+        book.merge(unknown, vec![addr(3)], NOW + 60);
+        assert_eq!(book.peers(), vec![(unknown, vec![addr(3)]), (known, vec![addr(1), addr(2)])]);
+    }
+
+    #[test]
+    fn addresses_per_peer_are_capped() {
+        let peer = PeerId::random();
+        let mut book = AddressBook::default();
+        book.observe(peer, (0..20).map(addr).collect(), NOW);
+        assert_eq!(book.peers()[0].1.len(), MAX_ADDRESSES_PER_PEER);
+    }
+
+    #[test]
+    fn peers_are_listed_most_recently_seen_first() {
+        let (old, recent) = (PeerId::random(), PeerId::random());
+        let mut book = AddressBook::default();
+        book.observe(old, vec![addr(1)], NOW);
+        book.observe(recent, vec![addr(2)], NOW);
+        book.seen(&recent, NOW + 60);
+        let order: Vec<PeerId> = book.peers().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(order, vec![recent, old]);
+    }
+
+    #[test]
+    fn roundtrip_drops_peers_past_the_maximum_age() {
+        let path = temp_path("address_book");
+        let (stale, fresh) = (PeerId::random(), PeerId::random());
+        let mut book = AddressBook::load(Some(path.clone()), NOW);
+        book.observe(stale, vec![addr(1)], NOW);
+        book.observe(fresh, vec![addr(2)], NOW + MAX_PEER_AGE_SECS);
+        book.save(NOW);
+
+        let reloaded = AddressBook::load(Some(path.clone()), NOW + MAX_PEER_AGE_SECS + 1);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(reloaded.peers(), vec![(fresh, vec![addr(2)])]);
+    }
+
+    #[test]
+    fn least_recently_seen_peers_go_beyond_capacity() {
+        let mut book = AddressBook::default();
+        let newest = PeerId::random();
+        for i in 0..MAX_PEERS as u64 {
+            book.observe(PeerId::random(), vec![addr(1)], NOW + i);
+        }
+        book.observe(newest, vec![addr(1)], NOW + MAX_PEERS as u64);
+        book.save(NOW + MAX_PEERS as u64);
+        assert_eq!(book.len(), MAX_PEERS);
+        assert_eq!(book.peers()[0].0, newest);
+    }
+
+    #[test]
+    fn missing_or_corrupt_file_is_empty() {
+        assert_eq!(AddressBook::load(Some(PathBuf::from("/nonexistent/peers.json")), NOW).len(), 0);
+        let path = temp_path("address_book_corrupt");
+        std::fs::write(&path, b"not json").unwrap();
+        let book = AddressBook::load(Some(path.clone()), NOW);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(book.len(), 0);
+    }
+}
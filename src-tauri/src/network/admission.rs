@@ -0,0 +1,260 @@
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::num::NonZeroUsize;
+
+/// Limits applied to correctly signed, valid results before they are aggregated.
+#[derive(Debug, Clone)]
+pub struct AdmissionConfig {
+    /// Results an author may publish in a burst.
+    pub bucket_capacity: f64,
+    /// Seconds to earn back one result once the burst is spent.
+    pub refill_interval_secs: f64,
+    /// Results older than this (by their own timestamp) are dropped as stale.
+    pub max_age_secs: u64,
+    /// Authors tracked for rate limiting; least recently seen are forgotten first.
+    pub max_tracked_authors: usize,
+    /// Upper bound on (author, timestamp, sequence) entries kept for replay detection.
+    pub replay_capacity: usize,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            // One result per completed assessment: a handful per hour is generous.
+            bucket_capacity: 5.0,
+            refill_interval_secs: 15.0 * 60.0,
+            // Fresh results gossip within seconds; allow for delayed delivery
+            // and clock drift, but not for results re-broadcast days later.
+            max_age_secs: 48 * 60 * 60,
+            max_tracked_authors: 10_000,
+            replay_capacity: 100_000,
+        }
+    }
+}
+
+/// Why a result was not admitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    TooOld,
+    Replayed,
+    RateLimited,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::TooOld => write!(f, "result is older than the maximum message age"),
+            Rejection::Replayed => write!(f, "result from this author and timestamp was already seen"),
+            Rejection::RateLimited => write!(f, "author exceeded the publish rate limit"),
+        }
+    }
+}
+
+/// Cumulative counts of received results dropped, by reason.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RejectionStats {
+    pub oversized: u64,
+    pub malformed: u64,
+    pub invalid_signature: u64,
+    pub invalid_payload: u64,
+    pub too_old: u64,
+    pub replayed: u64,
+    pub rate_limited: u64,
+}
+
+impl RejectionStats {
+    pub fn record(&mut self, rejection: Rejection) {
+        match rejection {
+            Rejection::TooOld => self.too_old += 1,
+            Rejection::Replayed => self.replayed += 1,
+            Rejection::RateLimited => self.rate_limited += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    updated_at: u64,
+}
+
+/// Per-author token buckets plus a bounded replay window keyed by
+/// (timestamp, sequence, author). Both are bounded so a flood of fresh keys cannot
+/// grow memory without limit.
+pub struct Admission {
+    config: AdmissionConfig,
+    buckets: LruCache<String, TokenBucket>,
+    /// Ordered by timestamp so stale entries are pruned from the front.
+    seen: BTreeSet<(u64, u32, String)>,
+}
+
+impl Admission {
+    pub fn new(config: AdmissionConfig) -> Self {
+        let authors = NonZeroUsize::new(config.max_tracked_authors.max(1)).unwrap();
+        Self {
+            buckets: LruCache::new(authors),
+            seen: BTreeSet::new(),
+            config,
+        }
+    }
+
+    /// Decide whether a result from `author` stamped `timestamp` and
+    /// `sequence` is admitted at time `now` (Unix seconds). Admitting consumes
+    /// a token and records the (author, timestamp, sequence) entry; rejected
+    /// results change nothing but the replay window's pruning.
+    pub fn check(&mut self, author: &str, timestamp: u64, sequence: u32, now: u64) -> Result<(), Rejection> {
+        let oldest_allowed = now.saturating_sub(self.config.max_age_secs);
+        if timestamp < oldest_allowed {
+            return Err(Rejection::TooOld);
+        }
+
+        self.prune_seen(oldest_allowed);
+        let key = (timestamp, sequence, author.to_string());
+        if self.seen.contains(&key) {
+            return Err(Rejection::Replayed);
+        }
+
+        let capacity = self.config.bucket_capacity;
+        let refill = self.config.refill_interval_secs;
+        let bucket = self.buckets.get_or_insert_mut(author.to_string(), || TokenBucket {
+            tokens: capacity,
+            updated_at: now,
+        });
+        let elapsed = now.saturating_sub(bucket.updated_at) as f64;
+        bucket.tokens = (bucket.tokens + elapsed / refill).min(capacity);
+        bucket.updated_at = now;
+        if bucket.tokens < 1.0 {
+            return Err(Rejection::RateLimited);
+        }
+        bucket.tokens -= 1.0;
+
+        self.seen.insert(key);
+        while self.seen.len() > self.config.replay_capacity {
+            self.seen.pop_first();
+        }
+        Ok(())
+    }
+
+    /// Entries older than the maximum age can go: `check` rejects those
+    /// timestamps as too old before consulting the window.
+    fn prune_seen(&mut self, oldest_allowed: u64) {
+        while let Some((ts, _, _)) = self.seen.first() {
+            if *ts >= oldest_allowed {
+                break;
+            }
+            self.seen.pop_first();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn admission() -> Admission {
+        Admission::new(AdmissionConfig::default())
+    }
+
+    #[test]
+    fn admits_fresh_result() {
+        assert_eq!(admission().check("alice", NOW, 0, NOW), Ok(()));
+    }
+
+    #[test]
+    fn rejects_result_older_than_max_age() {
+        let mut a = admission();
+        let max_age = AdmissionConfig::default().max_age_secs;
+        assert_eq!(a.check("alice", NOW - max_age - 1, 0, NOW), Err(Rejection::TooOld));
+        assert_eq!(a.check("alice", NOW - max_age, 0, NOW), Ok(()));
+    }
+
+    #[test]
+    fn rejects_replay_of_same_author_and_timestamp() {
+        let mut a = admission();
+        assert_eq!(a.check("alice", NOW - 10, 0, NOW), Ok(()));
+        assert_eq!(a.check("alice", NOW - 10, 0, NOW + 5), Err(Rejection::Replayed));
+        // Same timestamp from a different author is not a replay
+        assert_eq!(a.check("bob", NOW - 10, 0, NOW + 5), Ok(()));
+        // Nor is the next result under the same rounded timestamp
+        assert_eq!(a.check("alice", NOW - 10, 1, NOW + 5), Ok(()));
+        assert_eq!(a.check("alice", NOW - 10, 1, NOW + 6), Err(Rejection::Replayed));
+    }
+
+    #[test]
+    fn rate_limits_bursts_per_author() {
+        let mut a = admission();
+        let burst = AdmissionConfig::default().bucket_capacity as u64;
+        for i in 0..burst {
+            assert_eq!(a.check("alice", NOW - i, 0, NOW), Ok(()));
+        }
+        assert_eq!(a.check("alice", NOW - burst, 0, NOW), Err(Rejection::RateLimited));
+        // Another author has their own bucket
+        assert_eq!(a.check("bob", NOW, 0, NOW), Ok(()));
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        let config = AdmissionConfig::default();
+        let refill = config.refill_interval_secs as u64;
+        let mut a = Admission::new(config.clone());
+        for i in 0..config.bucket_capacity as u64 {
+            a.check("alice", NOW - i, 0, NOW).unwrap();
+        }
+        assert_eq!(a.check("alice", NOW + 1, 0, NOW + refill - 1), Err(Rejection::RateLimited));
+        assert_eq!(a.check("alice", NOW + 2, 0, NOW + refill), Ok(()));
+    }
+
+    #[test]
+    fn rate_limited_result_is_not_recorded_as_seen() {
+        let config = AdmissionConfig { bucket_capacity: 1.0, ..Default::default() };
+        let refill = config.refill_interval_secs as u64;
+        let mut a = Admission::new(config);
+        a.check("alice", NOW, 0, NOW).unwrap();
+        assert_eq!(a.check("alice", NOW + 1, 0, NOW + 1), Err(Rejection::RateLimited));
+        // Once a token is back, the same result is admitted rather than flagged as replay
+        assert_eq!(a.check("alice", NOW + 1, 0, NOW + 1 + refill), Ok(()));
+    }
+
+    #[test]
+    fn replay_window_is_bounded() {
+        let config = AdmissionConfig { replay_capacity: 3, ..Default::default() };
+        let mut a = Admission::new(config);
+        for (i, author) in ["a", "b", "c", "d"].iter().enumerate() {
+            a.check(author, NOW + i as u64, 0, NOW + 10).unwrap();
+        }
+        assert_eq!(a.seen.len(), 3);
+    }
+
+    #[test]
+    fn replay_window_prunes_expired_entries() {
+        let config = AdmissionConfig::default();
+        let max_age = config.max_age_secs;
+        let mut a = Admission::new(config);
+        a.check("alice", NOW, 0, NOW).unwrap();
+        a.check("bob", NOW + max_age + 1, 0, NOW + max_age + 1).unwrap();
+        assert_eq!(a.seen.len(), 1);
+    }
+
+    #[test]
+    fn tracked_authors_are_bounded() {
+        let config = AdmissionConfig { max_tracked_authors: 2, ..Default::default() };
+        let mut a = Admission::new(config);
+        for author in ["a", "b", "c"] {
+            a.check(author, NOW, 0, NOW).unwrap();
+        }
+        assert_eq!(a.buckets.len(), 2);
+    }
+
+    #[test]
+    fn rejection_stats_count_each_reason() {
+        let mut stats = RejectionStats::default();
+        stats.record(Rejection::TooOld);
+        stats.record(Rejection::Replayed);
+        stats.record(Rejection::Replayed);
+        stats.record(Rejection::RateLimited);
+        assert_eq!((stats.too_old, stats.replayed, stats.rate_limited), (1, 2, 1));
+    }
+}
@@ -0,0 +1,852 @@
+use super::cohort::{check_cohort, Withheld, DEFAULT_MIN_COHORT_SIZE, LOWEST_MIN_COHORT_SIZE};
+use super::histogram::Histogram;
+use super::privacy::{counted_category, counted_total, MeanAccumulator, MeanEstimate};
+use super::quantiles::{ScoreDistribution, MAX_SORTED_SCORES};
+use super::succession::{Succession, SuccessionChains, SuccessionConflict};
+use super::types::{ManifestationResult, MAX_SCORES_CAPACITY};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Shortest retention that can be configured. Results are admitted until
+/// they are 48 hours old, so a shorter period would evict results as soon
+/// as they were accepted.
+pub const MIN_RETENTION_DAYS: u64 = 2;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// How long and how many per-author results the aggregator keeps.
+#[derive(Debug, Clone)]
+pub struct AggregatorConfig {
+    /// Maximum number of authors tracked; the stalest author is evicted first.
+    pub max_authors: usize,
+    /// Drop an author's result once its timestamp is older than this.
+    /// `None` keeps results until evicted by `max_authors`.
+    pub retention_secs: Option<u64>,
+    /// k-anonymity threshold: any statistic (total or per category, in any
+    /// time window) over fewer results than this is withheld.
+    pub min_cohort_size: usize,
+}
+
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        Self {
+            max_authors: MAX_SCORES_CAPACITY,
+            retention_secs: None,
+            min_cohort_size: DEFAULT_MIN_COHORT_SIZE,
+        }
+    }
+}
+
+impl AggregatorConfig {
+    /// Keep each author's result for `days` after its timestamp, as the app
+    /// settings and the relayd config give it; `None` keeps results until
+    /// evicted by `max_authors`.
+    pub fn with_retention_days(self, days: Option<u64>) -> Result<Self, String> {
+        if let Some(days) = days {
+            if days < MIN_RETENTION_DAYS {
+                return Err(format!("Retention must be at least {} days", MIN_RETENTION_DAYS));
+            }
+        }
+        Ok(Self { retention_secs: days.map(|d| d.saturating_mul(DAY_SECS)), ..self })
+    }
+
+    /// Withhold statistics over fewer than `size` results, as the app
+    /// settings and the relayd config give it.
+    pub fn with_min_cohort_size(self, size: usize) -> Result<Self, String> {
+        if size < LOWEST_MIN_COHORT_SIZE {
+            return Err(format!("Minimum cohort size must be at least {}", LOWEST_MIN_COHORT_SIZE));
+        }
+        Ok(Self { min_cohort_size: size, ..self })
+    }
+}
+
+/// Mean of the total and each category over exact and noised results alike.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeanEstimates {
+    pub total: Option<MeanEstimate>,
+    pub withheld: Option<Withheld>,
+    pub categories: HashMap<String, MeanEstimate>,
+    pub withheld_categories: HashMap<String, Withheld>,
+}
+
+/// Network aggregate with one vote per identity: only the latest result of
+/// each author public key counts, so re-publishing cannot skew statistics.
+/// Keys linked by signed successions count as one identity, filed under the
+/// newest key of the chain.
+///
+/// Score distributions and histograms are updated as votes come and go, so
+/// statistics are read without re-sorting the whole aggregate. They hold
+/// exact results only: results carrying local differential-privacy noise
+/// count towards the mean estimates alone, which stay unbiased under noise
+/// where quantiles would not.
+#[derive(Debug)]
+pub struct Aggregator {
+    config: AggregatorConfig,
+    latest: HashMap<String, ManifestationResult>,
+    /// (timestamp, author) for every entry in `latest`, oldest first.
+    by_age: BTreeSet<(u64, String)>,
+    totals: ScoreDistribution,
+    categories: HashMap<String, ScoreDistribution>,
+    total_histogram: Histogram,
+    category_histograms: HashMap<String, Histogram>,
+    total_estimate: MeanAccumulator,
+    category_estimates: HashMap<String, MeanAccumulator>,
+    successions: SuccessionChains,
+}
+
+impl Default for Aggregator {
+    fn default() -> Self {
+        Self::new(AggregatorConfig::default())
+    }
+}
+
+impl Aggregator {
+    pub fn new(config: AggregatorConfig) -> Self {
+        debug_assert!(
+            config.max_authors <= MAX_SORTED_SCORES,
+            "score distributions update in O(n); revisit them before raising the author cap"
+        );
+        Self {
+            successions: SuccessionChains::new(config.max_authors),
+            config,
+            latest: HashMap::new(),
+            by_age: BTreeSet::new(),
+            totals: ScoreDistribution::default(),
+            categories: HashMap::new(),
+            total_histogram: Histogram::for_totals(),
+            category_histograms: HashMap::new(),
+            total_estimate: MeanAccumulator::default(),
+            category_estimates: HashMap::new(),
+        }
+    }
+
+    /// Rebuild from a persisted per-author snapshot and the successions
+    /// known when it was taken, applying the current limits.
+    pub fn from_snapshot(
+        config: AggregatorConfig,
+        snapshot: HashMap<String, ManifestationResult>,
+        successions: Vec<Succession>,
+        now: u64,
+    ) -> Self {
+        let mut aggregator = Self::new(config);
+        // Votes are restored below, so keep every chain until they are.
+        for succession in successions {
+            let _ = aggregator.successions.record(succession, |_| true);
+        }
+        for (author, result) in snapshot {
+            aggregator.insert(&author, result, now);
+        }
+        aggregator
+    }
+
+    /// Record `result` as `author`'s current vote. Returns `false` (and
+    /// changes nothing) if the author already has a result at least as new
+    /// (by timestamp, then sequence),
+    /// if the result falls outside the retention period, or if `author` is a
+    /// retired key and the result is newer than its retirement.
+    pub fn insert(&mut self, author: &str, result: ManifestationResult, now: u64) -> bool {
+        if let Some(cutoff) = self.retention_cutoff(now) {
+            if result.timestamp < cutoff {
+                return false;
+            }
+        }
+        if self.successions.retired_at(author).is_some_and(|retired_at| result.timestamp > retired_at) {
+            return false;
+        }
+        let head = self.successions.head(author);
+        let author = head.as_str();
+        if let Some(existing) = self.latest.get(author) {
+            if (existing.timestamp, existing.sequence) >= (result.timestamp, result.sequence) {
+                return false;
+            }
+            self.by_age.remove(&(existing.timestamp, author.to_string()));
+        }
+        self.by_age.insert((result.timestamp, author.to_string()));
+        self.count(&result);
+        if let Some(replaced) = self.latest.insert(author.to_string(), result) {
+            self.uncount(&replaced);
+        }
+
+        self.prune(now);
+        true
+    }
+
+    /// Record that `succession.new_key` replaces `succession.old_key` (the
+    /// signatures must already be verified). The old key's vote moves to the
+    /// chain's newest key, unless that key already has a newer result.
+    pub fn apply_succession(&mut self, succession: Succession, now: u64) -> Result<(), SuccessionConflict> {
+        let old_key = succession.old_key.clone();
+        let new_key = succession.new_key.clone();
+        // The extended chain's vote still sits under the old key here.
+        let latest = &self.latest;
+        self.successions.record(succession, |head| head == new_key || latest.contains_key(head))?;
+        if let Some(result) = self.latest.remove(&old_key) {
+            self.by_age.remove(&(result.timestamp, old_key.clone()));
+            self.uncount(&result);
+            self.insert(&old_key, result, now);
+        }
+        Ok(())
+    }
+
+    /// Successions known to the aggregate, oldest first, for persistence.
+    pub fn successions(&self) -> Vec<Succession> {
+        self.successions.snapshot()
+    }
+
+    pub fn successions_len(&self) -> usize {
+        self.successions.len()
+    }
+
+    /// Drop results past the retention period, then the stalest authors
+    /// beyond `max_authors`.
+    pub fn prune(&mut self, now: u64) {
+        let cutoff = self.retention_cutoff(now).unwrap_or(0);
+        while let Some((ts, author)) = self.by_age.first().cloned() {
+            if ts >= cutoff && self.by_age.len() <= self.config.max_authors {
+                break;
+            }
+            self.by_age.pop_first();
+            if let Some(evicted) = self.latest.remove(&author) {
+                self.uncount(&evicted);
+            }
+        }
+    }
+
+    fn count(&mut self, result: &ManifestationResult) {
+        let (total, variance) = counted_total(result);
+        self.total_estimate.add(total, variance);
+        for (category, &score) in &result.category_scores {
+            let (score, variance) = counted_category(result, score);
+            self.category_estimates.entry(category.clone()).or_default().add(score, variance);
+        }
+        if result.privacy.is_some() {
+            return;
+        }
+
+        self.totals.insert(result.score);
+        self.total_histogram.add(result.score);
+        for (category, &score) in &result.category_scores {
+            self.categories.entry(category.clone()).or_default().insert(score);
+            self.category_histograms
+                .entry(category.clone())
+                .or_insert_with(Histogram::for_category)
+                .add(score);
+        }
+    }
+
+    fn uncount(&mut self, result: &ManifestationResult) {
+        let (total, variance) = counted_total(result);
+        self.total_estimate.remove(total, variance);
+        for (category, &score) in &result.category_scores {
+            if let Some(estimate) = self.category_estimates.get_mut(category) {
+                let (score, variance) = counted_category(result, score);
+                estimate.remove(score, variance);
+                if estimate.is_empty() {
+                    self.category_estimates.remove(category);
+                }
+            }
+        }
+        if result.privacy.is_some() {
+            return;
+        }
+
+        self.totals.remove(result.score);
+        self.total_histogram.remove(result.score);
+        for (category, &score) in &result.category_scores {
+            if let Some(distribution) = self.categories.get_mut(category) {
+                distribution.remove(score);
+                if distribution.is_empty() {
+                    self.categories.remove(category);
+                    self.category_histograms.remove(category);
+                    continue;
+                }
+            }
+            if let Some(histogram) = self.category_histograms.get_mut(category) {
+                histogram.remove(score);
+            }
+        }
+    }
+
+    fn retention_cutoff(&self, now: u64) -> Option<u64> {
+        self.config.retention_secs.map(|secs| now.saturating_sub(secs))
+    }
+
+    /// Number of authors currently contributing a vote.
+    pub fn len(&self) -> usize {
+        self.latest.len()
+    }
+
+    /// `Ok` if statistics over `sample_size` results may be reported.
+    pub fn check_cohort(&self, sample_size: usize) -> Result<(), Withheld> {
+        check_cohort(sample_size, self.config.min_cohort_size)
+    }
+
+    /// Distribution of total scores, one per author.
+    pub fn totals(&self) -> &ScoreDistribution {
+        &self.totals
+    }
+
+    /// Unbiased means over every result, noised or not, withholding those
+    /// over too few results.
+    pub fn estimates(&self) -> MeanEstimates {
+        let total = self.total_estimate.estimate();
+        let (total, withheld) = match self.check_cohort(total.map_or(0, |e| e.sample_size)) {
+            Ok(()) => (total, None),
+            Err(withheld) => (None, Some(withheld)),
+        };
+        let mut estimates = MeanEstimates {
+            total,
+            withheld,
+            categories: HashMap::new(),
+            withheld_categories: HashMap::new(),
+        };
+        for (category, accumulator) in &self.category_estimates {
+            let Some(estimate) = accumulator.estimate() else { continue };
+            match self.check_cohort(estimate.sample_size) {
+                Ok(()) => {
+                    estimates.categories.insert(category.clone(), estimate);
+                }
+                Err(withheld) => {
+                    estimates.withheld_categories.insert(category.clone(), withheld);
+                }
+            }
+        }
+        estimates
+    }
+
+    /// Number of aggregated results that carry privacy noise.
+    pub fn noisy_len(&self) -> usize {
+        self.total_estimate.estimate().map_or(0, |e| e.noisy_samples)
+    }
+
+    /// Distribution of each category's scores over the authors that rated it.
+    pub fn categories(&self) -> &HashMap<String, ScoreDistribution> {
+        &self.categories
+    }
+
+    /// Copy of the per-author view, for persisting.
+    pub fn snapshot(&self) -> HashMap<String, ManifestationResult> {
+        self.latest.clone()
+    }
+}
+
+/// What the desktop app asks of the aggregate beyond the stats updates.
+#[cfg(any(feature = "gui", test))]
+mod queries {
+    use super::Aggregator;
+    use crate::network::quantiles::ScoreDistribution;
+    use crate::network::types::ManifestationResult;
+    use crate::network::views::{ScoreHistograms, ScoreRank};
+    use crate::network::window::{windowed_stats, WindowQuery, WindowedStats};
+    use std::collections::HashMap;
+
+    impl Aggregator {
+        /// Percentile rank of a (typically local, unpublished) result against the
+        /// aggregate, withholding the overall rank and each category's rank
+        /// separately when too few results back them.
+        pub fn rank(&self, score: f64, category_scores: &HashMap<String, f64>) -> ScoreRank {
+            let mut rank = ScoreRank::default();
+            match self.check_cohort(self.totals.len()) {
+                Ok(()) => rank.overall = self.totals.percentile_rank(score),
+                Err(withheld) => rank.withheld = Some(withheld),
+            }
+            for (category, &value) in category_scores {
+                let distribution = self.categories.get(category);
+                // An empty cohort is always withheld, so `None` never reaches `Ok`.
+                match self.check_cohort(distribution.map_or(0, ScoreDistribution::len)) {
+                    Ok(()) => {
+                        if let Some(category_rank) = distribution.and_then(|d| d.percentile_rank(value)) {
+                            rank.categories.insert(category.clone(), category_rank);
+                        }
+                    }
+                    Err(withheld) => {
+                        rank.withheld_categories.insert(category.clone(), withheld);
+                    }
+                }
+            }
+            rank
+        }
+
+        /// Histograms of the total score and each category, withholding those
+        /// built from too few results.
+        pub fn histograms(&self) -> ScoreHistograms {
+            let (total, withheld) = match self.check_cohort(self.totals.len()) {
+                Ok(()) => (Some(self.total_histogram.clone()), None),
+                Err(withheld) => (None, Some(withheld)),
+            };
+            let mut histograms = ScoreHistograms {
+                total,
+                withheld,
+                categories: HashMap::new(),
+                withheld_categories: HashMap::new(),
+            };
+            for (category, distribution) in &self.categories {
+                match self.check_cohort(distribution.len()) {
+                    Ok(()) => {
+                        if let Some(histogram) = self.category_histograms.get(category) {
+                            histograms.categories.insert(category.clone(), histogram.clone());
+                        }
+                    }
+                    Err(withheld) => {
+                        histograms.withheld_categories.insert(category.clone(), withheld);
+                    }
+                }
+            }
+            histograms
+        }
+
+        /// Each author's latest result with a timestamp of at least `cutoff`.
+        pub fn results_since(&self, cutoff: u64) -> impl Iterator<Item = &ManifestationResult> {
+            self.by_age
+                .range((cutoff, String::new())..)
+                .filter_map(|(_, author)| self.latest.get(author))
+        }
+
+        /// Statistics over the exact results inside `query.window`, optionally
+        /// decayed by age.
+        pub fn windowed(&self, query: WindowQuery, now: u64) -> WindowedStats {
+            let exact = self.results_since(query.window.cutoff(now)).filter(|r| r.privacy.is_none());
+            windowed_stats(exact, query, now, self.config.min_cohort_size)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::window::WindowQuery;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn result(score: f64, timestamp: u64) -> ManifestationResult {
+        ManifestationResult {
+            score,
+            timestamp,
+            category_scores: HashMap::from([("14".to_string(), score / 1000.0)]),
+            question_set: crate::questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+        }
+    }
+
+    fn scores(a: &Aggregator) -> Vec<f64> {
+        let mut v: Vec<f64> = a.latest.values().map(|r| r.score).collect();
+        v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        v
+    }
+
+    #[test]
+    fn repeated_publishing_counts_once() {
+        let mut a = Aggregator::default();
+        a.config.max_authors = 10;
+        for i in 0..500 {
+            a.insert("flooder", result(9000.0, NOW + i), NOW + i);
+        }
+        a.insert("alice", result(1000.0, NOW), NOW);
+        assert_eq!(a.len(), 2);
+        assert_eq!(scores(&a), vec![1000.0, 9000.0]);
+    }
+
+    #[test]
+    fn unlinkable_results_vote_under_their_derived_key() {
+        let mut a = Aggregator::default();
+        for i in 0..DEFAULT_MIN_COHORT_SIZE as u64 {
+            let derived = ManifestationResult { unlinkable: true, ..result(9000.0, NOW + i) };
+            assert!(a.insert(&format!("derived-{}", i), derived, NOW + i));
+        }
+        assert_eq!(a.len(), DEFAULT_MIN_COHORT_SIZE);
+        assert!(a.check_cohort(a.totals().len()).is_ok());
+    }
+
+    #[test]
+    fn newer_result_replaces_older() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        assert!(a.insert("alice", result(1000.0, NOW), NOW));
+        assert!(a.insert("alice", result(2000.0, NOW + 60), NOW + 60));
+        assert_eq!(scores(&a), vec![2000.0]);
+    }
+
+    #[test]
+    fn second_publication_within_one_rounding_unit_replaces_the_first() {
+        use crate::network::admission::{Admission, AdmissionConfig};
+        use crate::network::publish_timing::{PublicationSequence, TimestampPrecision};
+        let mut admission = Admission::new(AdmissionConfig::default());
+        let mut sequence = PublicationSequence::default();
+        let mut a = Aggregator::default();
+        for (published_at, score) in [(NOW + 60, 1000.0), (NOW + 120, 2000.0)] {
+            let timestamp = TimestampPrecision::Hour.coarsen(published_at);
+            let r = ManifestationResult { sequence: sequence.claim(timestamp), ..result(score, timestamp) };
+            assert_eq!(admission.check("alice", r.timestamp, r.sequence, published_at), Ok(()));
+            assert!(a.insert("alice", r, published_at));
+        }
+        assert_eq!(scores(&a), vec![2000.0]);
+    }
+
+    #[test]
+    fn older_or_equal_result_is_ignored() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        a.insert("alice", result(2000.0, NOW), NOW);
+        assert!(!a.insert("alice", result(1000.0, NOW - 60), NOW));
+        assert!(!a.insert("alice", result(3000.0, NOW), NOW));
+        assert_eq!(scores(&a), vec![2000.0]);
+    }
+
+    #[test]
+    fn evicts_stalest_author_beyond_capacity() {
+        let config = AggregatorConfig { max_authors: 2, retention_secs: None, min_cohort_size: 1 };
+        let mut a = Aggregator::new(config);
+        a.insert("old", result(1.0, NOW), NOW);
+        a.insert("mid", result(2.0, NOW + 1), NOW + 1);
+        a.insert("new", result(3.0, NOW + 2), NOW + 2);
+        assert_eq!(scores(&a), vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn retention_drops_expired_results() {
+        let config = AggregatorConfig { max_authors: 10, retention_secs: Some(3600), min_cohort_size: 1 };
+        let mut a = Aggregator::new(config);
+        a.insert("alice", result(1.0, NOW), NOW);
+        assert!(!a.insert("bob", result(2.0, NOW - 7200), NOW), "already expired");
+        a.prune(NOW + 3601);
+        assert_eq!(a.len(), 0);
+    }
+
+    #[test]
+    fn retention_is_configured_in_days() {
+        let config = AggregatorConfig::default().with_retention_days(Some(3)).unwrap();
+        assert_eq!(config.retention_secs, Some(3 * 24 * 60 * 60));
+        assert_eq!(AggregatorConfig::default().with_retention_days(None).unwrap().retention_secs, None);
+        assert!(AggregatorConfig::default().with_retention_days(Some(MIN_RETENTION_DAYS - 1)).is_err());
+    }
+
+    #[test]
+    fn configured_cohort_size_withholds_below_it() {
+        let config = AggregatorConfig::default().with_min_cohort_size(8).unwrap();
+        let mut a = Aggregator::new(config);
+        for i in 0..7u64 {
+            a.insert(&format!("author-{i}"), result(5000.0, NOW + i), NOW + i);
+        }
+        assert_eq!(a.check_cohort(a.totals().len()).unwrap_err().min_cohort_size, 8);
+        a.insert("author-7", result(5000.0, NOW + 7), NOW + 7);
+        assert!(a.check_cohort(a.totals().len()).is_ok());
+
+        assert!(AggregatorConfig::default().with_min_cohort_size(LOWEST_MIN_COHORT_SIZE).is_ok());
+        assert!(AggregatorConfig::default().with_min_cohort_size(1).is_err());
+        assert!(AggregatorConfig::default().with_min_cohort_size(0).is_err());
+    }
+
+    #[test]
+    fn distributions_follow_replacement_and_eviction() {
+        let config = AggregatorConfig { max_authors: 2, retention_secs: None, min_cohort_size: 1 };
+        let mut a = Aggregator::new(config);
+        a.insert("alice", result(1000.0, NOW), NOW);
+        a.insert("bob", result(3000.0, NOW + 1), NOW + 1);
+        a.insert("alice", result(2000.0, NOW + 2), NOW + 2);
+        assert_eq!(a.totals().len(), 2);
+        assert_eq!(a.totals().mean(), Some(2500.0));
+
+        a.insert("carol", result(5000.0, NOW + 3), NOW + 3); // evicts bob
+        assert_eq!(a.totals().quantile(0.0), Some(2000.0));
+        assert_eq!(a.totals().quantile(1.0), Some(5000.0));
+        assert_eq!(a.categories()["14"].len(), 2);
+    }
+
+    #[test]
+    fn distributions_match_results_after_churn() {
+        let config = AggregatorConfig { max_authors: 50, retention_secs: Some(500), min_cohort_size: 1 };
+        let mut a = Aggregator::new(config);
+        for i in 0..1_000u64 {
+            let author = format!("author-{}", i % 80);
+            a.insert(&author, result((i * 37 % 10_000) as f64, NOW + i), NOW + i);
+        }
+        let mut expected: Vec<f64> = a.latest.values().map(|r| r.score).collect();
+        expected.sort_by(|a, b| a.total_cmp(b));
+        assert_eq!(a.totals().len(), expected.len());
+        assert_eq!(a.totals().quantile(0.5), Some(expected[expected.len() / 2]));
+        assert_eq!(a.categories()["14"].len(), expected.len());
+    }
+
+    #[test]
+    fn empty_categories_are_dropped() {
+        let config = AggregatorConfig { max_authors: 10, retention_secs: Some(60), min_cohort_size: 1 };
+        let mut a = Aggregator::new(config);
+        a.insert("alice", result(1000.0, NOW), NOW);
+        a.prune(NOW + 61);
+        assert!(a.categories().is_empty());
+        assert!(a.totals().is_empty());
+    }
+
+    #[test]
+    fn rank_places_score_overall_and_per_category() {
+        let mut a = Aggregator::new(AggregatorConfig { min_cohort_size: 1, ..Default::default() });
+        for (i, score) in [1000.0, 2000.0, 3000.0, 4000.0].into_iter().enumerate() {
+            a.insert(&format!("author-{i}"), result(score, NOW), NOW);
+        }
+        let query = HashMap::from([("14".to_string(), 3.5), ("2".to_string(), 9.0)]);
+        let rank = a.rank(3500.0, &query);
+        assert_eq!(rank.overall.unwrap().percentile, 75.0);
+        assert_eq!(rank.overall.unwrap().sample_size, 4);
+        assert_eq!(rank.categories["14"].percentile, 75.0);
+        assert!(!rank.categories.contains_key("2"), "no aggregate data for category 2");
+        assert_eq!(rank.withheld_categories["2"].sample_size, 0);
+    }
+
+    #[test]
+    fn rank_against_empty_aggregate_has_no_overall() {
+        let a = Aggregator::new(AggregatorConfig::default());
+        let rank = a.rank(5000.0, &HashMap::from([("14".to_string(), 5.0)]));
+        assert_eq!(rank.overall, None);
+        assert!(rank.categories.is_empty());
+        assert!(rank.withheld.is_some());
+    }
+
+    #[test]
+    fn rank_is_withheld_below_min_cohort_size() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        for i in 0..DEFAULT_MIN_COHORT_SIZE - 1 {
+            a.insert(&format!("author-{i}"), result(1000.0 * i as f64, NOW), NOW);
+        }
+        let query = HashMap::from([("14".to_string(), 2.0)]);
+        let rank = a.rank(2000.0, &query);
+        assert_eq!(rank.overall, None);
+        assert_eq!(rank.withheld.unwrap().sample_size, DEFAULT_MIN_COHORT_SIZE - 1);
+        assert!(rank.categories.is_empty());
+        assert!(rank.withheld_categories.contains_key("14"));
+
+        a.insert("last", result(9000.0, NOW), NOW);
+        let rank = a.rank(2000.0, &query);
+        assert_eq!(rank.overall.unwrap().sample_size, DEFAULT_MIN_COHORT_SIZE);
+        assert_eq!(rank.withheld, None);
+        assert!(rank.categories.contains_key("14"));
+    }
+
+    #[test]
+    fn histograms_follow_replacement_and_eviction() {
+        let config = AggregatorConfig { max_authors: 2, retention_secs: None, min_cohort_size: 1 };
+        let mut a = Aggregator::new(config);
+        a.insert("alice", result(1050.0, NOW), NOW);
+        a.insert("bob", result(2500.0, NOW + 1), NOW + 1);
+        a.insert("alice", result(9990.0, NOW + 2), NOW + 2);
+
+        let h = a.histograms();
+        let total = h.total.unwrap();
+        assert_eq!(total.counts.iter().sum::<u64>(), 2);
+        assert_eq!(total.counts[10], 0, "replaced result is no longer counted");
+        assert_eq!((total.counts[25], total.counts[99]), (1, 1));
+        assert_eq!(h.categories["14"].counts.iter().sum::<u64>(), 2);
+
+        a.insert("carol", result(4000.0, NOW + 3), NOW + 3); // evicts bob
+        let total = a.histograms().total.unwrap();
+        assert_eq!(total.counts[25], 0);
+        assert_eq!(total.counts.iter().sum::<u64>(), 2);
+    }
+
+    #[test]
+    fn histograms_drop_emptied_categories() {
+        let config = AggregatorConfig { max_authors: 10, retention_secs: Some(60), min_cohort_size: 1 };
+        let mut a = Aggregator::new(config);
+        a.insert("alice", result(1000.0, NOW), NOW);
+        a.prune(NOW + 61);
+        let h = a.histograms();
+        assert!(h.categories.is_empty() && h.withheld_categories.is_empty());
+        assert_eq!(h.total, None);
+        assert_eq!(h.withheld.unwrap().sample_size, 0);
+    }
+
+    #[test]
+    fn histograms_are_withheld_per_series() {
+        let mut a = Aggregator::new(AggregatorConfig { min_cohort_size: 2, ..Default::default() });
+        a.insert("alice", result(1000.0, NOW), NOW);
+        let mut bob = result(2000.0, NOW);
+        bob.category_scores.insert("2".to_string(), 7.0);
+        a.insert("bob", bob, NOW);
+
+        let h = a.histograms();
+        assert!(h.total.is_some() && h.withheld.is_none());
+        assert!(h.categories.contains_key("14"));
+        assert!(!h.categories.contains_key("2"));
+        assert_eq!(h.withheld_categories["2"].sample_size, 1);
+    }
+
+    #[test]
+    fn windowed_stats_only_include_results_inside_the_window() {
+        use crate::network::window::TimeWindow;
+        let day = 24 * 60 * 60;
+        let mut a = Aggregator::new(AggregatorConfig { min_cohort_size: 1, ..Default::default() });
+        a.insert("recent", result(8000.0, NOW - day), NOW);
+        a.insert("month", result(4000.0, NOW - 20 * day), NOW);
+        a.insert("old", result(1000.0, NOW - 200 * day), NOW);
+
+        let sizes: Vec<usize> = [TimeWindow::Last7Days, TimeWindow::Last30Days, TimeWindow::Last90Days, TimeWindow::AllTime]
+            .into_iter()
+            .map(|window| {
+                let stats = a.windowed(WindowQuery { window, half_life_days: None }, NOW);
+                stats.total.map_or(0, |t| t.sample_size)
+            })
+            .collect();
+        assert_eq!(sizes, vec![1, 2, 2, 3]);
+
+        let week = a.windowed(WindowQuery { window: TimeWindow::Last7Days, half_life_days: None }, NOW);
+        assert_eq!(week.total.unwrap().avg, 8000.0);
+    }
+
+    #[test]
+    fn results_since_follows_replacement() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        a.insert("alice", result(1000.0, NOW - 100), NOW);
+        a.insert("alice", result(2000.0, NOW), NOW);
+        let scores: Vec<f64> = a.results_since(NOW - 50).map(|r| r.score).collect();
+        assert_eq!(scores, vec![2000.0]);
+        assert_eq!(a.results_since(0).count(), 1);
+    }
+
+    #[test]
+    fn noised_results_only_count_towards_estimates() {
+        use crate::network::privacy::{LocalPrivacy, NoiseMechanism};
+        let privacy = LocalPrivacy { mechanism: NoiseMechanism::Laplace, epsilon: 1.0 };
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(1);
+        let mut a = Aggregator::new(AggregatorConfig { min_cohort_size: 1, ..Default::default() });
+        a.insert("exact", result(4000.0, NOW), NOW);
+        let mut noisy = result(6000.0, NOW);
+        privacy.perturb(&mut noisy, &mut rng);
+        a.insert("noisy", noisy, NOW);
+
+        assert_eq!(a.len(), 2);
+        assert_eq!(a.noisy_len(), 1);
+        assert_eq!(a.totals().len(), 1, "quantiles see exact results only");
+        assert_eq!(a.histograms().total.unwrap().counts.iter().sum::<u64>(), 1);
+        let week = WindowQuery { window: crate::network::window::TimeWindow::AllTime, half_life_days: None };
+        assert_eq!(a.windowed(week, NOW).total.unwrap().sample_size, 1);
+
+        let total = a.estimates().total.unwrap();
+        assert_eq!((total.sample_size, total.noisy_samples), (2, 1));
+        assert!(total.noise_standard_error > 0.0);
+        let category = &a.estimates().categories["14"];
+        assert_eq!((category.sample_size, category.noisy_samples), (2, 1));
+
+        a.insert("noisy", result(5000.0, NOW + 1), NOW + 1); // replaced by an exact result
+        assert_eq!(a.noisy_len(), 0);
+        let total = a.estimates().total.unwrap();
+        assert!((total.mean - 4500.0).abs() < 1e-9);
+        assert_eq!(total.noise_standard_error, 0.0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "author cap")]
+    fn author_cap_beyond_sorted_distribution_limit_is_flagged() {
+        Aggregator::new(AggregatorConfig { max_authors: MAX_SORTED_SCORES + 1, ..Default::default() });
+    }
+
+    #[test]
+    fn estimates_are_unbiased_over_mixed_results() {
+        use crate::network::privacy::{LocalPrivacy, NoiseMechanism};
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(9);
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        let mut true_sum = 0.0;
+        let n = 10_000;
+        for i in 0..n {
+            let mut r = result((i % 101) as f64 * 100.0, NOW);
+            true_sum += r.score;
+            let mechanism = match i % 3 {
+                0 => None,
+                1 => Some(NoiseMechanism::Laplace),
+                _ => Some(NoiseMechanism::RandomizedResponse),
+            };
+            if let Some(mechanism) = mechanism {
+                LocalPrivacy { mechanism, epsilon: 2.0 }.perturb(&mut r, &mut rng);
+            }
+            a.insert(&format!("author-{i}"), r, NOW);
+        }
+        let total = a.estimates().total.unwrap();
+        assert_eq!(total.sample_size, n);
+        let error = (total.mean - true_sum / n as f64).abs();
+        assert!(error <= 4.0 * total.noise_standard_error, "error {} vs se {}", error, total.noise_standard_error);
+    }
+
+    #[test]
+    fn estimates_respect_min_cohort_size() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        a.insert("alice", result(1000.0, NOW), NOW);
+        let estimates = a.estimates();
+        assert_eq!(estimates.total, None);
+        assert_eq!(estimates.withheld.unwrap().sample_size, 1);
+        assert!(estimates.withheld_categories.contains_key("14"));
+    }
+
+    #[test]
+    fn snapshot_roundtrip_keeps_latest_per_author() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        a.insert("alice", result(1000.0, NOW), NOW);
+        a.insert("bob", result(2000.0, NOW), NOW);
+        let restored = Aggregator::from_snapshot(AggregatorConfig::default(), a.snapshot(), vec![], NOW);
+        assert_eq!(scores(&restored), vec![1000.0, 2000.0]);
+    }
+
+    fn succession(old: &str, new: &str, timestamp: u64) -> Succession {
+        Succession { old_key: old.into(), new_key: new.into(), timestamp }
+    }
+
+    #[test]
+    fn succession_moves_the_old_vote_to_the_new_key() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        a.insert("old", result(1000.0, NOW), NOW);
+        a.apply_succession(succession("old", "new", NOW + 10), NOW + 10).unwrap();
+        assert_eq!(a.snapshot().keys().collect::<Vec<_>>(), vec!["new"]);
+        // The new key's next result replaces it: still one vote
+        a.insert("new", result(3000.0, NOW + 20), NOW + 20);
+        assert_eq!(scores(&a), vec![3000.0]);
+    }
+
+    #[test]
+    fn chain_counts_as_one_identity() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        a.apply_succession(succession("a", "b", NOW), NOW).unwrap();
+        a.apply_succession(succession("b", "c", NOW + 10), NOW + 10).unwrap();
+        a.insert("c", result(2000.0, NOW + 20), NOW + 20);
+        // A late result from an ancestor, made before it was retired, is older: ignored
+        assert!(!a.insert("a", result(1000.0, NOW - 5), NOW + 30));
+        assert_eq!(a.len(), 1);
+    }
+
+    #[test]
+    fn retired_key_cannot_vote_after_its_retirement() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        a.apply_succession(succession("old", "new", NOW), NOW).unwrap();
+        assert!(!a.insert("old", result(1000.0, NOW + 1), NOW + 1));
+        assert_eq!(a.len(), 0);
+    }
+
+    #[test]
+    fn newer_successor_result_is_kept_over_the_moved_vote() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        a.insert("new", result(3000.0, NOW + 5), NOW + 5);
+        a.insert("old", result(1000.0, NOW), NOW);
+        a.apply_succession(succession("old", "new", NOW + 1), NOW + 10).unwrap();
+        assert_eq!(scores(&a), vec![3000.0]);
+        assert_eq!(a.len(), 1);
+    }
+
+    #[test]
+    fn snapshot_roundtrip_keeps_successions() {
+        let mut a = Aggregator::new(AggregatorConfig::default());
+        a.apply_succession(succession("old", "new", NOW), NOW).unwrap();
+        let mut restored = Aggregator::from_snapshot(AggregatorConfig::default(), a.snapshot(), a.successions(), NOW);
+        assert!(!restored.insert("old", result(1000.0, NOW + 1), NOW + 1));
+        assert_eq!(restored.successions_len(), 1);
+    }
+
+    #[test]
+    fn retired_key_with_a_live_vote_stays_retired_at_capacity() {
+        let config = AggregatorConfig { max_authors: 2, retention_secs: None, min_cohort_size: 1 };
+        let mut a = Aggregator::new(config);
+        a.insert("alice-old", result(1000.0, NOW), NOW);
+        a.apply_succession(succession("alice-old", "alice-new", NOW + 1), NOW + 1).unwrap();
+        for i in 0..3 {
+            a.apply_succession(succession(&format!("x{i}"), &format!("y{i}"), NOW + 2 + i), NOW + 2).unwrap();
+        }
+        assert!(!a.insert("alice-old", result(9000.0, NOW + 10), NOW + 10), "retired key cannot vote again");
+        assert_eq!(scores(&a), vec![1000.0]);
+    }
+}
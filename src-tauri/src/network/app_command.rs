@@ -0,0 +1,51 @@
+use super::types::BannedPeer;
+use super::views::{ScoreHistograms, ScoreRank};
+use super::window::{WindowQuery, WindowedStats};
+use std::error::Error;
+use tokio::sync::oneshot;
+
+/// Requests to `PeerNode` that only the desktop app sends, wrapped in
+/// `Command::App`.
+pub enum AppCommand {
+    Publish {
+        topic: String,
+        message: Vec<u8>,
+        sender: oneshot::Sender<Result<(), Box<dyn Error + Send>>>,
+    },
+    GetPeers {
+        sender: oneshot::Sender<Vec<String>>,
+    },
+    GetBannedPeers {
+        sender: oneshot::Sender<Vec<BannedPeer>>,
+    },
+    /// Lift a ban early. Replies `false` if the peer was not banned.
+    UnbanPeer {
+        peer_id: libp2p::PeerId,
+        sender: oneshot::Sender<bool>,
+    },
+    GetHistograms {
+        sender: oneshot::Sender<ScoreHistograms>,
+    },
+    GetWindowedStats {
+        query: WindowQuery,
+        sender: oneshot::Sender<WindowedStats>,
+    },
+    /// Pad `message` and hold it in the publish queue for a random delay of
+    /// up to `max_delay_secs`. Replies with the release time.
+    QueuePublish {
+        topic: String,
+        message: Vec<u8>,
+        max_delay_secs: u64,
+        sender: oneshot::Sender<Result<u64, String>>,
+    },
+    /// Drop every queued publication unsent. Replies with how many were dropped.
+    ClearPublishQueue {
+        sender: oneshot::Sender<usize>,
+    },
+    /// Rank a local result against the aggregate. Read-only: nothing is published.
+    RankScore {
+        score: f64,
+        category_scores: std::collections::HashMap<String, f64>,
+        sender: oneshot::Sender<ScoreRank>,
+    },
+}
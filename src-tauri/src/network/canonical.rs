@@ -0,0 +1,90 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// Serialise `value` to canonical JSON: compact, with object keys sorted
+/// lexicographically at every depth.
+///
+/// `ManifestationResult::category_scores` is a `HashMap`, whose iteration
+/// order differs between processes, so plain `serde_json::to_vec` is not
+/// stable enough to sign or hash. Every signature and CID is computed over
+/// this encoding instead.
+pub fn to_canonical_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    let mut out = Vec::new();
+    write_canonical(&value, &mut out)?;
+    Ok(out)
+}
+
+fn write_canonical(value: &Value, out: &mut Vec<u8>) -> Result<(), String> {
+    match value {
+        Value::Object(map) => {
+            // Sort explicitly rather than relying on serde_json's default
+            // BTreeMap, which a `preserve_order` feature elsewhere in the
+            // dependency graph would silently turn off.
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push(b'{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, key).map_err(|e| e.to_string())?;
+                out.push(b':');
+                write_canonical(item, out)?;
+            }
+            out.push(b'}');
+        }
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_canonical(item, out)?;
+            }
+            out.push(b']');
+        }
+        scalar => serde_json::to_writer(&mut *out, scalar).map_err(|e| e.to_string())?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn sorts_object_keys() {
+        let map = HashMap::from([("b", 2), ("a", 1), ("c", 3)]);
+        let bytes = to_canonical_vec(&map).unwrap();
+        assert_eq!(bytes, br#"{"a":1,"b":2,"c":3}"#);
+    }
+
+    #[test]
+    fn sorts_nested_keys_and_keeps_array_order() {
+        let value = serde_json::json!({
+            "z": [{"y": 1, "x": 2}, 3],
+            "a": {"d": true, "c": null},
+        });
+        let bytes = to_canonical_vec(&value).unwrap();
+        assert_eq!(bytes, br#"{"a":{"c":null,"d":true},"z":[{"x":2,"y":1},3]}"#);
+    }
+
+    #[test]
+    fn escapes_keys_like_serde_json() {
+        let map = HashMap::from([("quote\"key", 1)]);
+        let bytes = to_canonical_vec(&map).unwrap();
+        assert_eq!(bytes, br#"{"quote\"key":1}"#);
+    }
+
+    #[test]
+    fn insertion_order_does_not_change_output() {
+        let keys: Vec<String> = (0..40).map(|i| format!("category_{i}")).collect();
+        let forward: HashMap<String, f64> =
+            keys.iter().enumerate().map(|(i, k)| (k.clone(), i as f64 / 4.0)).collect();
+        let reverse: HashMap<String, f64> =
+            keys.iter().enumerate().rev().map(|(i, k)| (k.clone(), i as f64 / 4.0)).collect();
+        assert_eq!(to_canonical_vec(&forward).unwrap(), to_canonical_vec(&reverse).unwrap());
+    }
+}
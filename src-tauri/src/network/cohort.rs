@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+/// Statistics over fewer results than this are withheld. With two peers,
+/// an average is simply the other person's score; five makes any single
+/// contribution hard to single out.
+pub const DEFAULT_MIN_COHORT_SIZE: usize = 5;
+
+/// Lowest threshold that can be configured: a statistic over one result
+/// is that person's score.
+pub const LOWEST_MIN_COHORT_SIZE: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WithheldReason {
+    /// Fewer results than the minimum cohort size.
+    InsufficientData,
+}
+
+/// Why a statistic is missing, reported in its place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Withheld {
+    pub reason: WithheldReason,
+    pub sample_size: usize,
+    pub min_cohort_size: usize,
+}
+
+/// `Ok` if `sample_size` results are enough to publish statistics over.
+/// An empty cohort is also withheld.
+pub fn check_cohort(sample_size: usize, min_cohort_size: usize) -> Result<(), Withheld> {
+    if sample_size > 0 && sample_size >= min_cohort_size {
+        Ok(())
+    } else {
+        Err(Withheld { reason: WithheldReason::InsufficientData, sample_size, min_cohort_size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cohort_at_threshold_is_allowed() {
+        assert!(check_cohort(5, 5).is_ok());
+        assert!(check_cohort(6, 5).is_ok());
+    }
+
+    #[test]
+    fn cohort_below_threshold_is_withheld() {
+        let withheld = check_cohort(4, 5).unwrap_err();
+        assert_eq!(withheld.reason, WithheldReason::InsufficientData);
+        assert_eq!((withheld.sample_size, withheld.min_cohort_size), (4, 5));
+    }
+
+    #[test]
+    fn empty_cohort_is_withheld_even_without_threshold() {
+        assert!(check_cohort(0, 0).is_err());
+        assert!(check_cohort(1, 0).is_ok());
+    }
+
+    #[test]
+    fn reason_serialises_as_snake_case() {
+        let json = serde_json::to_value(check_cohort(1, 5).unwrap_err()).unwrap();
+        assert_eq!(json["reason"], "insufficient_data");
+    }
+}
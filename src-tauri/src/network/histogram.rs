@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+/// Total scores: 0–10,000 in 100-point bins.
+pub const TOTAL_BINS: usize = 100;
+pub const TOTAL_RANGE: (f64, f64) = (0.0, 10_000.0);
+
+/// Category ratings: 0–10 in 1-point bins.
+pub const CATEGORY_BINS: usize = 10;
+pub const CATEGORY_RANGE: (f64, f64) = (0.0, 10.0);
+
+/// Fixed-bucket histogram. Bin `i` covers `[min + i·w, min + (i+1)·w)`;
+/// the last bin also includes the maximum, and out-of-range values are
+/// clamped into the first or last bin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    pub min: f64,
+    pub bin_width: f64,
+    pub counts: Vec<u64>,
+}
+
+impl Histogram {
+    pub fn new((min, max): (f64, f64), bins: usize) -> Self {
+        let bins = bins.max(1);
+        Self { min, bin_width: (max - min) / bins as f64, counts: vec![0; bins] }
+    }
+
+    pub fn for_totals() -> Self {
+        Self::new(TOTAL_RANGE, TOTAL_BINS)
+    }
+
+    pub fn for_category() -> Self {
+        Self::new(CATEGORY_RANGE, CATEGORY_BINS)
+    }
+
+    /// Index of the bin `value` falls into.
+    pub fn bin(&self, value: f64) -> usize {
+        let offset = ((value - self.min) / self.bin_width).floor();
+        // `as usize` saturates: negatives (and NaN) land in bin 0
+        (offset as usize).min(self.counts.len() - 1)
+    }
+
+    pub fn add(&mut self, value: f64) {
+        let bin = self.bin(value);
+        self.counts[bin] += 1;
+    }
+
+    pub fn remove(&mut self, value: f64) {
+        let bin = self.bin(value);
+        self.counts[bin] = self.counts[bin].saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn totals_histogram_has_hundred_point_bins() {
+        let h = Histogram::for_totals();
+        assert_eq!(h.counts.len(), 100);
+        assert_eq!(h.bin_width, 100.0);
+        assert_eq!(h.bin(0.0), 0);
+        assert_eq!(h.bin(99.99), 0);
+        assert_eq!(h.bin(100.0), 1);
+        assert_eq!(h.bin(9_950.0), 99);
+    }
+
+    #[test]
+    fn maximum_falls_in_last_bin() {
+        assert_eq!(Histogram::for_totals().bin(10_000.0), 99);
+        assert_eq!(Histogram::for_category().bin(10.0), 9);
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let h = Histogram::for_category();
+        assert_eq!(h.bin(-1.0), 0);
+        assert_eq!(h.bin(42.0), 9);
+    }
+
+    #[test]
+    fn add_and_remove_update_counts() {
+        let mut h = Histogram::for_category();
+        h.add(7.5);
+        h.add(7.0);
+        h.add(2.0);
+        assert_eq!(h.counts[7], 2);
+        assert_eq!(h.counts.iter().sum::<u64>(), 3);
+        h.remove(7.9);
+        assert_eq!(h.counts[7], 1);
+        h.remove(5.0); // empty bin stays at zero
+        assert_eq!(h.counts[5], 0);
+        assert_eq!(h.counts.iter().sum::<u64>(), 2);
+    }
+}
@@ -1,39 +1,113 @@
 use super::types::{
-    AppBehaviour, CategoryStats, Command, NetworkScoresCache,
-    NetworkStatUpdate, SignedManifestation, MAX_SCORES_CAPACITY,
+    AppBehaviour, BannedPeer, BannedPeersCache, CategoryStats, Command, NatStatus, NetworkScoresCache,
+    NetworkStatUpdate, NodeConfig, SignedManifestation, GLOBAL_TOPIC, KAD_PROTOCOL,
 };
-use super::utils::calculate_percentile;
+use super::admission::{Admission, AdmissionConfig, RejectionStats};
+use super::aggregate::Aggregator;
+use super::protocol::{DecodeError, GossipMessage};
+use super::address_book::AddressBook;
+use super::publish_queue::PublishQueue;
+use super::succession::SignedSuccession;
+use super::utils::unix_now;
 use libp2p::{
-    gossipsub, identify, kad, mdns, noise, ping, tcp, yamux, core,
-    Multiaddr, Swarm, Transport,
+    allow_block_list, autonat, dcutr, gossipsub, identify, kad, mdns, noise, ping, quic, relay, tcp, yamux, core,
+    Multiaddr, PeerId, Swarm, Transport,
 };
-use libp2p::swarm::SwarmEvent;
+use libp2p::core::muxing::StreamMuxerBox;
+use libp2p::multiaddr::Protocol;
+use libp2p::core::transport::ListenerId;
+use libp2p::swarm::{behaviour::toggle::Toggle, dial_opts::{DialOpts, PeerCondition}, SwarmEvent};
 use libp2p::futures::StreamExt;
 use lru::LruCache;
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::num::NonZeroUsize;
+use std::num::{NonZeroU8, NonZeroUsize};
 use std::path::PathBuf;
-use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+use std::sync::{Arc, atomic::{AtomicBool, AtomicU64, Ordering}};
 use std::time::Duration;
 use tokio::sync::mpsc;
 
+/// Peers whose gossipsub score drops below this are disconnected and banned.
+/// Set below the graylist threshold so a peer is first ignored, then banned.
+pub const BAN_SCORE_THRESHOLD: f64 = -100.0;
+
+/// How long a ban lasts. Bans are by peer id; a misbehaving peer that comes
+/// back is banned again after a few more rejected messages.
+pub const BAN_DURATION_SECS: u64 = 24 * 60 * 60;
+
+/// How often connected peers' scores are checked against `BAN_SCORE_THRESHOLD`
+/// and expired bans are lifted.
+const SCORE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often the publish queue is checked for publications due for release.
+const PUBLISH_QUEUE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often relays without a live reservation are asked for one again.
+const RELAY_RESERVATION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the Kademlia routing table is refreshed with a bootstrap query.
+const KAD_BOOTSTRAP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Multiaddresses of the project's relayd instances, separated by whitespace
+/// or commas. Release builds set `MANIFESTATION_BOOTSTRAP_PEERS` at compile
+/// time; other builds have none and rely on mDNS or configured peers.
+const DEFAULT_BOOTSTRAP_PEERS: &str = match option_env!("MANIFESTATION_BOOTSTRAP_PEERS") {
+    Some(peers) => peers,
+    None => "",
+};
+
+/// How often the address book is written to disk between startup and shutdown.
+const ADDRESS_BOOK_SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Most address-book peers dialled on startup; Kademlia still learns the rest.
+const ADDRESS_BOOK_DIALS: usize = 20;
+
 pub struct PeerNode {
     swarm: Swarm<AppBehaviour>,
     command_receiver: mpsc::Receiver<Command>,
     event_sender: mpsc::Sender<NetworkStatUpdate>,
-    total_manifestations: usize,
-    received_scores: VecDeque<f64>,
-    received_category_scores: std::collections::HashMap<String, VecDeque<f64>>,
+    /// Latest result per author; all network statistics are computed over it.
+    aggregator: Aggregator,
     /// Cumulative inbound application bytes (gossipsub message payloads).
     bytes_in: Arc<AtomicU64>,
     /// Cumulative outbound application bytes (published gossipsub payloads).
     bytes_out: Arc<AtomicU64>,
     seen_messages: LruCache<gossipsub::MessageId, ()>,
+    /// Messages from a newer protocol version or of an unknown kind.
+    unsupported_messages: u64,
+    /// Valid results scored against a different question set; neither forwarded nor aggregated.
+    other_question_set_results: u64,
+    /// Valid results signed by unlinkable derived keys.
+    unlinkable_results: u64,
+    /// Per-author rate limits, maximum age and replay window.
+    admission: Admission,
+    /// The same for key successions, kept apart so that a succession and a
+    /// result signed by one key in the same second do not collide as replays.
+    succession_admission: Admission,
+    /// Received results dropped, by reason.
+    rejections: RejectionStats,
     /// Optional path for persisting the score window across restarts.
     cache_path: Option<PathBuf>,
+    /// Banned peers and their ban records; persisted next to the score cache.
+    banned_peers: HashMap<PeerId, BannedPeer>,
+    ban_list_path: Option<PathBuf>,
     /// Bootstrap peer addresses to dial on startup (empty = use defaults).
     bootstrap_peers: Vec<Multiaddr>,
+    /// Own publications held back for a random delay; persisted next to the score cache.
+    publish_queue: PublishQueue,
+    /// Configured relays, by peer id.
+    relays: Vec<(PeerId, Multiaddr)>,
+    /// Relay circuit listeners, open while the relay keeps our reservation.
+    relay_listeners: HashMap<PeerId, ListenerId>,
+    /// Relays that accepted a reservation on their current listener.
+    relay_reservations: HashSet<PeerId>,
+    nat_status: NatStatus,
+    /// Peers in the Kademlia routing table, refreshed on Kademlia events.
+    routing_table_size: usize,
+    /// Peers on our DHT seen recently, reloaded on startup.
+    address_book: AddressBook,
+    /// Shared with the app's sharing setting; see `NodeConfig::sharing_enabled`.
+    sharing_enabled: Arc<AtomicBool>,
 }
 
 impl PeerNode {
@@ -43,16 +117,31 @@ impl PeerNode {
         event_sender: mpsc::Sender<NetworkStatUpdate>,
         cache_path: Option<PathBuf>,
         bootstrap_peers: Vec<Multiaddr>,
+        config: NodeConfig,
     ) -> Result<Self, Box<dyn Error + Send + Sync>> {
-        let transport = libp2p::tcp::tokio::Transport::new(tcp::Config::default())
+        let tcp_transport = libp2p::tcp::tokio::Transport::new(tcp::Config::default())
             .upgrade(core::upgrade::Version::V1)
             .authenticate(noise::Config::new(&keypair).unwrap())
-            .multiplex(yamux::Config::default());
+            .multiplex(yamux::Config::default())
+            .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)));
+        // QUIC brings its own encryption (TLS 1.3) and stream multiplexing.
+        let transport = if config.transports.quic {
+            tcp_transport
+                .or_transport(
+                    quic::tokio::Transport::new(quic::Config::new(&keypair))
+                        .map(|(peer_id, conn), _| (peer_id, StreamMuxerBox::new(conn))),
+                )
+                .map(|either, _| either.into_inner())
+                .boxed()
+        } else {
+            tcp_transport.boxed()
+        };
 
         let mut swarm = libp2p::SwarmBuilder::with_existing_identity(keypair.clone())
             .with_tokio()
             .with_other_transport(|_key| transport)?
-            .with_behaviour(|key| {
+            .with_relay_client(noise::Config::new, yamux::Config::default)?
+            .with_behaviour(|key, relay_client| {
                 let message_id_fn = |message: &gossipsub::Message| {
                     use sha2::{Sha256, Digest};
                     let mut hasher = Sha256::new();
@@ -65,21 +154,34 @@ impl PeerNode {
                 };
                 let gossipsub_config = gossipsub::ConfigBuilder::default()
                     .heartbeat_interval(Duration::from_secs(10))
-                    .validation_mode(gossipsub::ValidationMode::Permissive)
+                    // Anonymous mode rejects any message that carries an author,
+                    // sequence number or transport signature.
+                    .validation_mode(gossipsub::ValidationMode::Anonymous)
+                    // Hold received messages until handle_gossip_message reports
+                    // a verdict, so invalid ones are never forwarded to the mesh.
+                    .validate_messages()
                     .message_id_fn(message_id_fn)
                     .duplicate_cache_time(Duration::from_secs(60))
                     .build()
-                    .map_err(|msg| std::io::Error::new(std::io::ErrorKind::Other, msg))?;
+                    .map_err(std::io::Error::other)?;
 
                 // Use Anonymous authenticity so the P2P node identity is NOT leaked.
                 // Application-level signatures in SignedManifestation provide authentication.
-                let gossipsub = gossipsub::Behaviour::new(
+                let mut gossipsub = gossipsub::Behaviour::new(
                     gossipsub::MessageAuthenticity::Anonymous,
                     gossipsub_config,
                 )?;
+                let (score_params, score_thresholds) =
+                    peer_score_config(gossipsub::IdentTopic::new(GLOBAL_TOPIC).hash());
+                gossipsub
+                    .with_peer_score(score_params, score_thresholds)
+                    .map_err(std::io::Error::other)?;
 
                 let store = kad::store::MemoryStore::new(key.public().to_peer_id());
-                let kademlia = kad::Behaviour::new(key.public().to_peer_id(), store);
+                let mut kad_config = kad::Config::new(KAD_PROTOCOL);
+                // Bootstrapping is driven by `run` on KAD_BOOTSTRAP_INTERVAL instead.
+                kad_config.set_periodic_bootstrap_interval(None);
+                let kademlia = kad::Behaviour::with_config(key.public().to_peer_id(), store, kad_config);
 
                 let identify = identify::Behaviour::new(identify::Config::new(
                     "manifestation/1.0.0".to_string(),
@@ -90,40 +192,113 @@ impl PeerNode {
                     ping::Config::new().with_interval(Duration::from_secs(30))
                 );
 
-                let mdns = mdns::tokio::Behaviour::new(
-                    mdns::Config::default(),
-                    key.public().to_peer_id(),
-                )?;
+                let mdns = Toggle::from(if config.mdns {
+                    Some(mdns::tokio::Behaviour::new(mdns::Config::default(), key.public().to_peer_id())?)
+                } else {
+                    None
+                });
+
+                let blocked_peers = allow_block_list::Behaviour::default();
 
-                Ok(AppBehaviour { gossipsub, kademlia, identify, ping, mdns })
+                let autonat = autonat::Behaviour::new(key.public().to_peer_id(), autonat::Config::default());
+                let dcutr = dcutr::Behaviour::new(key.public().to_peer_id());
+                let relay_server = Toggle::from(config.relay_server.then(|| {
+                    relay::Behaviour::new(key.public().to_peer_id(), relay::Config::default())
+                }));
+
+                Ok(AppBehaviour {
+                    gossipsub, kademlia, identify, ping, mdns, blocked_peers, autonat, relay_client, dcutr,
+                    relay_server,
+                })
             })?
-            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            // Try a peer's addresses one at a time, in order, so QUIC placed
+            // first is dialled first and TCP is only a fallback rather than a
+            // concurrent race.
+            .with_swarm_config(|c| c
+                .with_idle_connection_timeout(Duration::from_secs(60))
+                .with_dial_concurrency_factor(NonZeroU8::new(1).unwrap()))
             .build();
 
-        let topic = gossipsub::IdentTopic::new("manifestation-global");
+        let topic = gossipsub::IdentTopic::new(GLOBAL_TOPIC);
         swarm.behaviour_mut().gossipsub.subscribe(&topic)?;
 
+        if config.relay_server {
+            // Answer DHT queries even before AutoNAT confirms an external
+            // address. Other nodes switch to server mode once it does.
+            swarm.behaviour_mut().kademlia.set_mode(Some(kad::Mode::Server));
+        }
+        for addr in &config.external_addresses {
+            swarm.add_external_address(addr.clone());
+        }
+
         let bytes_in  = Arc::new(AtomicU64::new(0));
         let bytes_out = Arc::new(AtomicU64::new(0));
 
-        let cache = cache_path.as_ref()
+        let mut cache = cache_path.as_ref()
             .and_then(|p| Self::load_cache(p))
             .unwrap_or_default();
+        // Results cached before a questionnaire update no longer compare.
+        cache.latest_by_author.retain(|_, result| result.uses_local_question_set());
+
+        let ban_list_path = cache_path.as_ref().map(|p| p.with_file_name("banned_peers.json"));
+        let publish_queue = PublishQueue::load(cache_path.as_ref().map(|p| p.with_file_name("publish_queue.json")));
+        let mut address_book = AddressBook::load(
+            cache_path.as_ref().map(|p| p.with_file_name("peer_addresses.json")),
+            unix_now(),
+        );
+        let mut banned_peers = HashMap::new();
+        let now = unix_now();
+        for record in ban_list_path.as_ref()
+            .and_then(|p| Self::load_ban_list(p))
+            .unwrap_or_default()
+            .peers
+        {
+            if ban_expired(&record, now) {
+                continue;
+            }
+            let Ok(peer_id) = record.peer_id.parse::<PeerId>() else { continue };
+            swarm.behaviour_mut().blocked_peers.block_peer(peer_id);
+            swarm.behaviour_mut().gossipsub.blacklist_peer(&peer_id);
+            address_book.remove(&peer_id);
+            banned_peers.insert(peer_id, record);
+        }
+
+        let mut relays = Vec::new();
+        for addr in config.relays {
+            let Some(peer_id) = peer_id_of(&addr) else {
+                println!("Ignoring relay without a /p2p/ peer id: {}", addr);
+                continue;
+            };
+            swarm.behaviour_mut().autonat.add_server(peer_id, Some(addr.clone()));
+            relays.push((peer_id, addr));
+        }
 
         Ok(Self {
             swarm,
             command_receiver,
             event_sender,
-            total_manifestations: cache.scores.len(),
+            aggregator: Aggregator::from_snapshot(config.aggregator, cache.latest_by_author, cache.successions, unix_now()),
             seen_messages: LruCache::new(NonZeroUsize::new(10000).unwrap()),
-            received_scores: VecDeque::from(cache.scores),
-            received_category_scores: cache.category_scores.into_iter()
-                .map(|(k, v)| (k, VecDeque::from(v)))
-                .collect(),
+            unsupported_messages: 0,
+            other_question_set_results: 0,
+            unlinkable_results: 0,
+            admission: Admission::new(AdmissionConfig::default()),
+            succession_admission: Admission::new(AdmissionConfig::default()),
+            rejections: RejectionStats::default(),
             bytes_in,
             bytes_out,
             cache_path,
+            banned_peers,
+            ban_list_path,
             bootstrap_peers,
+            publish_queue,
+            relays,
+            relay_listeners: HashMap::new(),
+            relay_reservations: HashSet::new(),
+            nat_status: NatStatus::Unknown,
+            routing_table_size: 0,
+            address_book,
+            sharing_enabled: config.sharing_enabled,
         })
     }
 
@@ -133,19 +308,50 @@ impl PeerNode {
         } else {
             self.bootstrap_peers.clone()
         };
-        for addr in peers_to_dial {
-            if let Err(e) = self.swarm.dial(addr.clone()) {
-                println!("Bootstrap dial skipped for {}: {}", addr, e);
+        // Bootstrap peers join the routing table once Identify shows they
+        // speak KAD_PROTOCOL, like any other peer.
+        for (peer_id, addrs) in group_dial_addresses(peers_to_dial) {
+            let opts = match peer_id {
+                Some(peer_id) => DialOpts::peer_id(peer_id).addresses(addrs.clone()).build(),
+                None => DialOpts::unknown_peer_id().address(addrs[0].clone()).build(),
+            };
+            if let Err(e) = self.swarm.dial(opts) {
+                println!("Bootstrap dial skipped for {:?}: {}", addrs, e);
             } else {
-                println!("Dialing bootstrap peer: {}", addr);
+                println!("Dialing bootstrap peer: {:?}", addrs);
             }
         }
+        self.dial_known_peers();
+
+        let mut score_check = tokio::time::interval(SCORE_CHECK_INTERVAL);
+        let mut publish_check = tokio::time::interval(PUBLISH_QUEUE_INTERVAL);
+        let mut relay_check = tokio::time::interval(RELAY_RESERVATION_INTERVAL);
+        // The first tick fires at once, so this also bootstraps on startup.
+        let mut kad_bootstrap = tokio::time::interval(KAD_BOOTSTRAP_INTERVAL);
+        let mut address_book_save = tokio::time::interval(ADDRESS_BOOK_SAVE_INTERVAL);
+        address_book_save.tick().await;
 
         loop {
             tokio::select! {
                 event = self.swarm.select_next_some() => {
                     self.handle_swarm_event(event).await;
                 }
+                _ = score_check.tick() => {
+                    self.lift_expired_bans();
+                    self.enforce_peer_scores();
+                }
+                _ = publish_check.tick() => {
+                    self.release_due_publications();
+                }
+                _ = relay_check.tick() => {
+                    self.reserve_relay_slots();
+                }
+                _ = kad_bootstrap.tick() => {
+                    self.bootstrap_kademlia();
+                }
+                _ = address_book_save.tick() => {
+                    self.save_address_book();
+                }
                 command = self.command_receiver.recv() => {
                     if !self.handle_command(command).await {
                         break;
@@ -160,6 +366,49 @@ impl PeerNode {
             SwarmEvent::NewListenAddr { address, .. } => {
                 println!("Listening on {:?}", address);
             }
+            SwarmEvent::ListenerClosed { listener_id, reason, .. } => {
+                let closed = self.relay_listeners.iter()
+                    .find(|(_, id)| **id == listener_id)
+                    .map(|(relay, _)| *relay);
+                if let Some(relay) = closed {
+                    println!("Relay reservation on {} ended: {:?}", relay, reason);
+                    self.relay_listeners.remove(&relay);
+                    self.relay_reservations.remove(&relay);
+                    let stats = self.get_stats();
+                    if let Err(e) = self.event_sender.send(stats).await {
+                        eprintln!("Failed to send stats: {}", e);
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(super::types::AppBehaviourEvent::RelayClient(
+                relay::client::Event::ReservationReqAccepted { relay_peer_id, renewal, .. }
+            )) => {
+                if !renewal {
+                    println!("Relay {} accepted our reservation", relay_peer_id);
+                }
+                if self.relay_reservations.insert(relay_peer_id) {
+                    let stats = self.get_stats();
+                    if let Err(e) = self.event_sender.send(stats).await {
+                        eprintln!("Failed to send stats: {}", e);
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(super::types::AppBehaviourEvent::Autonat(
+                autonat::Event::StatusChanged { new, .. }
+            )) => {
+                println!("NAT status: {:?}", new);
+                self.nat_status = NatStatus::from(&new);
+                let stats = self.get_stats();
+                if let Err(e) = self.event_sender.send(stats).await {
+                    eprintln!("Failed to send stats: {}", e);
+                }
+            }
+            SwarmEvent::Behaviour(super::types::AppBehaviourEvent::Dcutr(
+                dcutr::Event { remote_peer_id, result }
+            )) => match result {
+                Ok(_) => println!("Hole punch to {} succeeded; connection is now direct", remote_peer_id),
+                Err(e) => println!("Hole punch to {} failed, staying relayed: {}", remote_peer_id, e),
+            },
             SwarmEvent::Behaviour(super::types::AppBehaviourEvent::Mdns(
                 mdns::Event::Discovered(list)
             )) => {
@@ -188,14 +437,82 @@ impl PeerNode {
             }
             SwarmEvent::Behaviour(super::types::AppBehaviourEvent::Gossipsub(
                 gossipsub::Event::Message {
-                    propagation_source: _peer_id,
+                    propagation_source,
                     message_id: id,
                     message,
                 }
             )) => {
-                self.handle_gossip_message(id, message).await;
+                // With `validate_messages()` gossipsub holds the message until we
+                // report a verdict: Accept forwards it, Reject also penalises the
+                // sender, Ignore drops it silently.
+                let acceptance = self.handle_gossip_message(id.clone(), message).await;
+                let rejected = matches!(acceptance, gossipsub::MessageAcceptance::Reject);
+                self.swarm.behaviour_mut().gossipsub.report_message_validation_result(
+                    &id,
+                    &propagation_source,
+                    acceptance,
+                );
+                if rejected {
+                    self.enforce_peer_scores();
+                }
+            }
+            SwarmEvent::Behaviour(super::types::AppBehaviourEvent::Identify(
+                identify::Event::Received { peer_id, info, .. }
+            // Only peers on our DHT belong in the routing table.
+            )) if info.protocols.contains(&KAD_PROTOCOL) => {
+                // Kademlia reports a peer's addresses for a dial in the order
+                // they entered its routing table, ahead of other behaviours,
+                // and dials try them in turn; adding QUIC ones first makes
+                // them the first tried.
+                let mut addrs = info.listen_addrs;
+                prefer_quic(&mut addrs);
+                for addr in &addrs {
+                    self.swarm.behaviour_mut().kademlia.add_address(&peer_id, addr.clone());
+                }
+                let known = self.address_book.len();
+                self.address_book.observe(peer_id, addrs, unix_now());
+                if self.address_book.len() != known {
+                    let stats = self.get_stats();
+                    if let Err(e) = self.event_sender.send(stats).await {
+                        eprintln!("Failed to send stats: {}", e);
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(super::types::AppBehaviourEvent::Kademlia(event)) => {
+                if let kad::Event::OutboundQueryProgressed {
+                    result: kad::QueryResult::Bootstrap(result), step, ..
+                } = &event {
+                    match result {
+                        Ok(ok) if ok.num_remaining == 0 => println!("Kademlia bootstrap finished"),
+                        Ok(_) => {}
+                        Err(e) => println!("Kademlia bootstrap step {} failed: {:?}", step.count, e),
+                    }
+                }
+                let size = self.swarm.behaviour_mut().kademlia.kbuckets().map(|b| b.num_entries()).sum();
+                if self.routing_table_size == 0 && size > 0 {
+                    // The startup bootstrap ran before any peer was known.
+                    self.bootstrap_kademlia();
+                }
+                if size != self.routing_table_size {
+                    self.routing_table_size = size;
+                    let stats = self.get_stats();
+                    if let Err(e) = self.event_sender.send(stats).await {
+                        eprintln!("Failed to send stats: {}", e);
+                    }
+                }
+            }
+            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                self.address_book.seen(&peer_id, unix_now());
+                if self.relays.iter().any(|(relay, _)| *relay == peer_id) {
+                    self.reserve_relay_slots();
+                }
+                let stats = self.get_stats();
+                if let Err(e) = self.event_sender.send(stats).await {
+                    eprintln!("Failed to send stats: {}", e);
+                }
             }
-            SwarmEvent::ConnectionEstablished { .. } | SwarmEvent::ConnectionClosed { .. } => {
+            SwarmEvent::ConnectionClosed { peer_id, .. } => {
+                self.address_book.seen(&peer_id, unix_now());
                 let stats = self.get_stats();
                 if let Err(e) = self.event_sender.send(stats).await {
                     eprintln!("Failed to send stats: {}", e);
@@ -209,57 +526,104 @@ impl PeerNode {
         &mut self,
         id: gossipsub::MessageId,
         message: gossipsub::Message,
-    ) {
+    ) -> gossipsub::MessageAcceptance {
         // Deduplication: skip if we've already processed this message
         if self.seen_messages.contains(&id) {
             println!("Duplicate message received: {:?}, skipping", id);
-            return;
+            return gossipsub::MessageAcceptance::Ignore;
         }
         self.seen_messages.put(id.clone(), ());
 
         self.bytes_in.fetch_add(message.data.len() as u64, Ordering::Relaxed);
 
-        match serde_json::from_slice::<SignedManifestation>(&message.data) {
-            Ok(signed) => {
-                // 1. Cryptographic signature check (app-level)
-                if !signed.verify() {
-                    println!("SECURITY: Invalid signature, dropping.");
-                    return;
-                }
-                // 2. Domain validation (range / privacy checks)
-                if let Err(e) = signed.payload.validate() {
-                    println!("Validation failed for received message: {}", e);
-                } else {
-                    println!("Received valid signed result from author key: {}", &signed.public_key[..8]);
-                    self.total_manifestations += 1;
-
-                    // Sliding window: evict oldest entry when at capacity
-                    if self.received_scores.len() >= MAX_SCORES_CAPACITY {
-                        self.received_scores.pop_front();
+        match GossipMessage::decode(&message.data) {
+            Ok(GossipMessage::Result(signed)) => self.handle_result(signed).await,
+            Ok(GossipMessage::Succession(signed)) => self.handle_succession(signed).await,
+            Err(e) => {
+                match e {
+                    DecodeError::UnsupportedVersion(_) | DecodeError::UnknownKind(_) => {
+                        // Sent by a newer build; not an error on either side.
+                        self.unsupported_messages += 1;
+                        println!("Ignoring gossip message: {}", e);
                     }
-                    self.received_scores.push_back(signed.payload.score);
-
-                    for (category, score) in &signed.payload.category_scores {
-                        let cat_scores = self.received_category_scores
-                            .entry(category.clone())
-                            .or_default();
-                        if cat_scores.len() >= MAX_SCORES_CAPACITY {
-                            cat_scores.pop_front();
-                        }
-                        cat_scores.push_back(*score);
+                    DecodeError::TooLarge(size) => {
+                        self.rejections.oversized += 1;
+                        eprintln!("Received oversized message ({} bytes)", size);
                     }
-
-                    let stats = self.get_stats();
-                    if let Err(e) = self.event_sender.send(stats).await {
-                        eprintln!("Failed to send stats: {}", e);
+                    DecodeError::Malformed(ref reason) => {
+                        self.rejections.malformed += 1;
+                        eprintln!("Received unparseable message: {} ({})",
+                            String::from_utf8_lossy(&message.data), reason);
                     }
                 }
+                decode_error_acceptance(&e)
             }
-            Err(e) => {
-                eprintln!("Received unparseable message: {} ({})",
-                    String::from_utf8_lossy(&message.data), e);
+        }
+    }
+
+    async fn handle_result(&mut self, signed: SignedManifestation) -> gossipsub::MessageAcceptance { // cargo-mutants: skip
+        if let Err(e) = check_signed_result(&signed) {
+            println!("Rejecting received result: {}", e);
+            match e {
+                InvalidResult::Signature => self.rejections.invalid_signature += 1,
+                InvalidResult::Payload(_) => self.rejections.invalid_payload += 1,
             }
+            return gossipsub::MessageAcceptance::Reject;
+        }
+        // Another questionnaire version: its category keys were only checked
+        // for shape, not against our registry, so the result is not forwarded.
+        if !signed.payload.uses_local_question_set() {
+            self.other_question_set_results += 1;
+            return gossipsub::MessageAcceptance::Ignore;
+        }
+        // Admission: well-formed but unwanted (flooding, stale, replayed).
+        // Ignored rather than rejected — the forwarding peer is not the author.
+        let now = unix_now();
+        if let Err(rejection) = self.admission.check(&signed.public_key, signed.payload.timestamp, signed.payload.sequence, now) {
+            println!("Dropping result from {}: {}", &signed.public_key[..8], rejection);
+            self.rejections.record(rejection);
+            return gossipsub::MessageAcceptance::Ignore;
+        }
+        println!("Received valid signed result from author key: {}", &signed.public_key[..8]);
+
+        if signed.payload.unlinkable {
+            self.unlinkable_results += 1;
         }
+
+        // One vote per identity: replaces this author's previous result, if older.
+        self.aggregator.insert(&signed.public_key, signed.payload, now);
+
+        let stats = self.get_stats();
+        if let Err(e) = self.event_sender.send(stats).await {
+            eprintln!("Failed to send stats: {}", e);
+        }
+        gossipsub::MessageAcceptance::Accept
+    }
+
+    async fn handle_succession(&mut self, signed: SignedSuccession) -> gossipsub::MessageAcceptance { // cargo-mutants: skip
+        if !signed.verify() {
+            println!("Rejecting key succession with an invalid signature");
+            self.rejections.invalid_signature += 1;
+            return gossipsub::MessageAcceptance::Reject;
+        }
+        let succession = signed.succession;
+        if let Err(rejection) = self.succession_admission.check(&succession.old_key, succession.timestamp, 0, unix_now()) {
+            println!("Dropping key succession from {}: {}", &succession.old_key[..8], rejection);
+            self.rejections.record(rejection);
+            return gossipsub::MessageAcceptance::Ignore;
+        }
+        let old_key = succession.old_key.clone();
+        if let Err(conflict) = self.aggregator.apply_succession(succession, unix_now()) {
+            println!("Ignoring key succession from {}: {}", &old_key[..8], conflict);
+            return gossipsub::MessageAcceptance::Ignore;
+        }
+        println!("Key {} rotated to a new key", &old_key[..8]);
+
+        let stats = self.get_stats();
+        if let Err(e) = self.event_sender.send(stats).await {
+            eprintln!("Failed to send stats: {}", e);
+        }
+        gossipsub::MessageAcceptance::Accept
     }
 
     /// Handle a command from the frontend; returns `false` to signal shutdown.
@@ -274,22 +638,9 @@ impl PeerNode {
                 }
                 true
             }
-            Some(Command::Publish { topic, message, sender }) => {
-                self.bytes_out.fetch_add(message.len() as u64, Ordering::Relaxed);
-                let topic = gossipsub::IdentTopic::new(topic);
-                if let Err(e) = match self.swarm.behaviour_mut().gossipsub.publish(topic, message) {
-                    Ok(_)  => sender.send(Ok(())),
-                    Err(e) => sender.send(Err(Box::new(e))),
-                } {
-                    eprintln!("Failed to send Publish response: {:?}", e);
-                }
-                true
-            }
-            Some(Command::GetPeers { sender }) => {
-                let peers = self.swarm.connected_peers().map(|p| p.to_string()).collect();
-                if let Err(e) = sender.send(peers) {
-                    eprintln!("Failed to send peers: {:?}", e);
-                }
+            #[cfg(feature = "gui")]
+            Some(Command::App(command)) => {
+                self.handle_app_command(command);
                 true
             }
             Some(Command::Shutdown) => {
@@ -297,59 +648,236 @@ impl PeerNode {
                 if let Some(ref path) = self.cache_path.clone() {
                     self.save_cache(path);
                 }
+                self.save_address_book();
                 false
             }
             None => false,
         }
     }
 
+    /// Hand every address-book peer to Kademlia and dial the most recently
+    /// seen, so a restart reconnects without bootstrap peers or mDNS.
+    fn dial_known_peers(&mut self) { // cargo-mutants: skip
+        for (index, (peer_id, addrs)) in self.address_book.peers().into_iter().enumerate() {
+            for addr in &addrs {
+                self.swarm.behaviour_mut().kademlia.add_address(&peer_id, addr.clone());
+            }
+            if index >= ADDRESS_BOOK_DIALS || addrs.is_empty() {
+                continue;
+            }
+            let opts = DialOpts::peer_id(peer_id)
+                .condition(PeerCondition::DisconnectedAndNotDialing)
+                .addresses(addrs)
+                .build();
+            if let Err(e) = self.swarm.dial(opts) {
+                println!("Dial of known peer {} skipped: {}", peer_id, e);
+            }
+        }
+    }
+
+    /// Snapshot the Kademlia routing table into the address book, refresh
+    /// connected peers' last-seen times and write it.
+    fn save_address_book(&mut self) { // cargo-mutants: skip
+        let now = unix_now();
+        for bucket in self.swarm.behaviour_mut().kademlia.kbuckets() {
+            for entry in bucket.iter() {
+                let peer_id = *entry.node.key.preimage();
+                self.address_book.merge(peer_id, entry.node.value.iter().cloned(), now);
+            }
+        }
+        for peer_id in self.swarm.connected_peers() {
+            self.address_book.seen(peer_id, now);
+        }
+        self.address_book.save(now);
+    }
+
+    /// Refresh the routing table with a Kademlia bootstrap query (a lookup
+    /// of our own id, then of a random id per bucket).
+    fn bootstrap_kademlia(&mut self) { // cargo-mutants: skip
+        if let Err(e) = self.swarm.behaviour_mut().kademlia.bootstrap() {
+            println!("Kademlia bootstrap skipped: {:?}", e);
+        }
+    }
+
+    /// Listen through every configured relay that has no circuit listener,
+    /// which asks the relay for a reservation. DCUtR then upgrades circuits
+    /// peers open through it to direct connections where the NATs allow.
+    ///
+    /// Relays are dialled first and only listened through once connected:
+    /// a reservation racing a second dial to the same relay (say, because it
+    /// is also a bootstrap peer) is lost when that connection wins.
+    fn reserve_relay_slots(&mut self) { // cargo-mutants: skip
+        for (peer_id, addr) in &self.relays {
+            if self.relay_listeners.contains_key(peer_id) {
+                continue;
+            }
+            if !self.swarm.is_connected(peer_id) {
+                let opts = DialOpts::peer_id(*peer_id)
+                    .condition(PeerCondition::DisconnectedAndNotDialing)
+                    .addresses(vec![addr.clone()])
+                    .build();
+                if let Err(e) = self.swarm.dial(opts) {
+                    println!("Relay dial skipped for {}: {}", addr, e);
+                }
+                continue;
+            }
+            match self.swarm.listen_on(addr.clone().with(Protocol::P2pCircuit)) {
+                Ok(listener_id) => {
+                    self.relay_listeners.insert(*peer_id, listener_id);
+                }
+                Err(e) => println!("Relay reservation on {} failed: {}", addr, e),
+            }
+        }
+    }
+
     fn get_stats(&self) -> NetworkStatUpdate { // cargo-mutants: skip
         let peers: Vec<String> = self.swarm.connected_peers().map(|p| p.to_string()).collect();
 
-        let scores_vec: Vec<f64> = self.received_scores.iter().cloned().collect();
-        let (avg_score, percentile_90) = if scores_vec.is_empty() {
-            (None, None)
-        } else {
-            let sum: f64 = scores_vec.iter().sum();
-            let avg = sum / scores_vec.len() as f64;
-            let p90 = calculate_percentile(&scores_vec, 0.9);
-            (Some(avg), p90)
+        // Below the minimum cohort size an average would expose individual scores.
+        let totals = self.aggregator.totals();
+        let (totals, withheld) = match self.aggregator.check_cohort(totals.len()) {
+            Ok(()) => (Some(totals), None),
+            Err(withheld) => (None, Some(withheld)),
         };
-
-        let mut category_stats = std::collections::HashMap::new();
-        for (category, scores) in &self.received_category_scores {
-            if scores.is_empty() { continue; }
-            let scores_slice: Vec<f64> = scores.iter().cloned().collect();
-            let sum: f64 = scores_slice.iter().sum();
-            let avg = sum / scores_slice.len() as f64;
-            let p90 = calculate_percentile(&scores_slice, 0.9).unwrap_or(0.0);
-            category_stats.insert(category.clone(), CategoryStats { avg, p90 });
+        let mut category_stats = HashMap::new();
+        let mut withheld_categories = HashMap::new();
+        for (category, distribution) in self.aggregator.categories() {
+            if let Err(withheld) = self.aggregator.check_cohort(distribution.len()) {
+                withheld_categories.insert(category.clone(), withheld);
+            } else if let (Some(quantiles), Some(avg)) = (distribution.quantiles(), distribution.mean()) {
+                category_stats.insert(category.clone(), CategoryStats { avg, p90: quantiles.p90, quantiles });
+            }
         }
 
         NetworkStatUpdate {
             peer_count: peers.len(),
             connected_peers: peers,
-            total_manifestations: self.total_manifestations,
-            avg_score,
-            percentile_90,
+            total_manifestations: self.aggregator.len(),
+            avg_score: totals.and_then(|t| t.mean()),
+            percentile_90: totals.and_then(|t| t.quantile(0.9)),
+            score_quantiles: totals.and_then(|t| t.quantiles()),
+            withheld,
             category_stats,
+            withheld_categories,
+            noisy_results: self.aggregator.noisy_len(),
+            estimates: self.aggregator.estimates(),
+            queued_publications: self.publish_queue.len(),
+            known_successions: self.aggregator.successions_len(),
+            nat_status: self.nat_status,
+            relay_reservations: self.relay_reservations.len(),
+            routing_table_size: self.routing_table_size,
+            known_peers: self.address_book.len(),
             bandwidth_in:  self.bytes_in.load(Ordering::Relaxed),
             bandwidth_out: self.bytes_out.load(Ordering::Relaxed),
+            unsupported_messages: self.unsupported_messages,
+            question_set: crate::questions::question_set_hash().to_string(),
+            other_question_set_results: self.other_question_set_results,
+            unlinkable_results: self.unlinkable_results,
+            rejections: self.rejections.clone(),
+        }
+    }
+
+    /// Publish queued results whose delay has passed. Each leaves the queue
+    /// only once published or rejected for good; those that cannot go out for
+    /// lack of peers stay queued and are retried on the next tick.
+    /// Once sharing is turned off nothing is released and the queue is dropped,
+    /// including entries queued just before the switch or restored from disk.
+    fn release_due_publications(&mut self) { // cargo-mutants: skip
+        if !self.sharing_enabled.load(Ordering::Relaxed) {
+            if self.publish_queue.clear() > 0 {
+                println!("Sharing is off; dropped queued publications");
+            }
+            return;
+        }
+        for publication in self.publish_queue.due(unix_now()) {
+            let topic = gossipsub::IdentTopic::new(publication.topic.clone());
+            let message = publication.message.clone().into_bytes();
+            let size = message.len() as u64;
+            match self.swarm.behaviour_mut().gossipsub.publish(topic, message) {
+                Ok(_) => {
+                    self.publish_queue.remove(&publication);
+                    self.bytes_out.fetch_add(size, Ordering::Relaxed);
+                    println!("Released queued publication ({} bytes)", size);
+                }
+                Err(e) if should_retry_publish(&e) => {}
+                Err(e) => {
+                    self.publish_queue.remove(&publication);
+                    eprintln!("Dropping queued publication: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Ban every connected peer whose gossipsub score is below `BAN_SCORE_THRESHOLD`.
+    fn enforce_peer_scores(&mut self) { // cargo-mutants: skip
+        let gossipsub = &self.swarm.behaviour().gossipsub;
+        let offenders: Vec<(PeerId, f64)> = self.swarm.connected_peers()
+            .filter_map(|peer| gossipsub.peer_score(peer).map(|score| (*peer, score)))
+            .filter(|(_, score)| should_ban(*score))
+            .collect();
+        if offenders.is_empty() {
+            return;
+        }
+        for (peer_id, score) in offenders {
+            self.ban_peer(peer_id, score);
+        }
+        if let Some(ref path) = self.ban_list_path.clone() {
+            self.save_ban_list(path);
         }
     }
 
-    /// Returns well-known bootstrap peer addresses for WAN DHT peer discovery.
-    /// These complement mDNS (LAN-only) for internet-scale peer connectivity.
-    /// Used as fallback when no custom bootstrap peers are configured.
+    /// Lift bans older than `BAN_DURATION_SECS`.
+    fn lift_expired_bans(&mut self) { // cargo-mutants: skip
+        let now = unix_now();
+        let expired: Vec<PeerId> = self.banned_peers.iter()
+            .filter(|(_, record)| ban_expired(record, now))
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+        for peer_id in expired {
+            println!("Ban on peer {} expired", peer_id);
+            self.unban_peer(&peer_id);
+        }
+        if let Some(ref path) = self.ban_list_path.clone() {
+            self.save_ban_list(path);
+        }
+    }
+
+    /// Allow a banned peer to connect and gossip again. Returns `false` if
+    /// it was not banned.
+    fn unban_peer(&mut self, peer_id: &PeerId) -> bool { // cargo-mutants: skip
+        if self.banned_peers.remove(peer_id).is_none() {
+            return false;
+        }
+        let behaviour = self.swarm.behaviour_mut();
+        behaviour.gossipsub.remove_blacklisted_peer(peer_id);
+        behaviour.blocked_peers.unblock_peer(*peer_id);
+        true
+    }
+
+    fn ban_peer(&mut self, peer_id: PeerId, score: f64) { // cargo-mutants: skip
+        println!("Banning peer {} (score {:.1})", peer_id, score);
+        let behaviour = self.swarm.behaviour_mut();
+        behaviour.gossipsub.blacklist_peer(&peer_id);
+        behaviour.gossipsub.remove_explicit_peer(&peer_id);
+        behaviour.kademlia.remove_peer(&peer_id);
+        // Closes any open connections and refuses new ones.
+        behaviour.blocked_peers.block_peer(peer_id);
+        self.address_book.remove(&peer_id);
+        self.banned_peers.insert(peer_id, BannedPeer {
+            peer_id: peer_id.to_string(),
+            banned_at: unix_now(),
+            score,
+        });
+    }
+
+    /// The project's `manifestation-relayd` instances, used when no custom
+    /// bootstrap peers are configured. These complement mDNS (LAN-only) for
+    /// internet-scale peer connectivity.
     pub fn default_bootstrap_peers() -> Vec<Multiaddr> {
-        [
-            "/ip4/104.131.131.82/tcp/4001/p2p/QmaCpDMGvV2BGHeYERUEnRQAwe3N8SzbUtfsmvsqQLuvuJ",
-            "/ip4/104.236.179.241/tcp/4001/p2p/QmSoLPppuBtQSGwKDZT2M73ULpjvfd3aZ6ha4oFGL1KrGM",
-            "/ip4/128.199.219.111/tcp/4001/p2p/QmSoLSafTMBsPKadTEgaXctDQVcqN88CNLHXMkTNwMKPnu",
-        ]
-        .iter()
-        .filter_map(|a| a.parse().ok())
-        .collect()
+        parse_bootstrap_list(DEFAULT_BOOTSTRAP_PEERS)
     }
 
     fn load_cache(path: &std::path::Path) -> Option<NetworkScoresCache> {
@@ -357,19 +885,36 @@ impl PeerNode {
         serde_json::from_reader(file).ok()
     }
 
+    fn load_ban_list(path: &std::path::Path) -> Option<BannedPeersCache> {
+        let file = std::fs::File::open(path).ok()?;
+        serde_json::from_reader(file).ok()
+    }
+
+    fn save_ban_list(&self, path: &std::path::Path) { // cargo-mutants: skip
+        let ban_list = BannedPeersCache {
+            peers: self.banned_peers.values().cloned().collect(),
+        };
+        match std::fs::File::create(path) {
+            Ok(file) => {
+                if let Err(e) = serde_json::to_writer(file, &ban_list) {
+                    eprintln!("Failed to save ban list: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to create ban list file: {}", e),
+        }
+    }
+
     fn save_cache(&self, path: &std::path::Path) { // cargo-mutants: skip
         let cache = NetworkScoresCache {
-            scores: self.received_scores.iter().cloned().collect(),
-            category_scores: self.received_category_scores.iter()
-                .map(|(k, v)| (k.clone(), v.iter().cloned().collect()))
-                .collect(),
+            latest_by_author: self.aggregator.snapshot(),
+            successions: self.aggregator.successions(),
         };
         match std::fs::File::create(path) {
             Ok(file) => {
                 if let Err(e) = serde_json::to_writer(file, &cache) {
                     eprintln!("Failed to save network cache: {}", e);
                 } else {
-                    println!("Network score cache saved ({} scores)", cache.scores.len());
+                    println!("Network score cache saved ({} authors)", cache.latest_by_author.len());
                 }
             }
             Err(e) => eprintln!("Failed to create network cache file: {}", e),
@@ -377,17 +922,270 @@ impl PeerNode {
     }
 }
 
+/// Gossipsub peer-score parameters for the results topic.
+///
+/// Results are rare (one per completed assessment), so the mesh delivery-rate
+/// penalties (P3/P3b) are disabled: on a quiet network they would punish
+/// honest peers. The only negative signal is P4, invalid deliveries, which
+/// costs `weight × count²` and decays over about an hour. Positive
+/// contributions are capped low enough that three rejected messages sink any
+/// peer below `BAN_SCORE_THRESHOLD`.
+///
+/// IP colocation (P6) is off: peers behind one NAT, CGNAT or relay share an
+/// address, and relayed peers all appear with the relay's. Behaviour (P7)
+/// and slow-peer penalties are off as well, so bans follow invalid messages only.
+fn peer_score_config(
+    topic: gossipsub::TopicHash,
+) -> (gossipsub::PeerScoreParams, gossipsub::PeerScoreThresholds) {
+    let topic_params = gossipsub::TopicScoreParams {
+        topic_weight: 1.0,
+        // P1: up to +36 for an hour in the mesh
+        time_in_mesh_weight: 0.01,
+        time_in_mesh_quantum: Duration::from_secs(1),
+        time_in_mesh_cap: 3600.0,
+        // P2: up to +20 for first deliveries of valid messages
+        first_message_deliveries_weight: 1.0,
+        first_message_deliveries_cap: 20.0,
+        // P3/P3b: disabled, see above
+        mesh_message_deliveries_weight: 0.0,
+        mesh_failure_penalty_weight: 0.0,
+        // P4: invalid (rejected) messages
+        invalid_message_deliveries_weight: -20.0,
+        invalid_message_deliveries_decay: gossipsub::score_parameter_decay(Duration::from_secs(3600)),
+        ..Default::default()
+    };
+    let mut params = gossipsub::PeerScoreParams {
+        ip_colocation_factor_weight: 0.0,
+        behaviour_penalty_weight: 0.0,
+        slow_peer_weight: 0.0,
+        ..Default::default()
+    };
+    params.topics.insert(topic, topic_params);
+
+    let thresholds = gossipsub::PeerScoreThresholds {
+        gossip_threshold: -10.0,
+        publish_threshold: -50.0,
+        graylist_threshold: -80.0,
+        ..Default::default()
+    };
+    (params, thresholds)
+}
+
+fn should_ban(score: f64) -> bool {
+    score < BAN_SCORE_THRESHOLD
+}
+
+fn ban_expired(record: &BannedPeer, now: u64) -> bool {
+    now.saturating_sub(record.banned_at) >= BAN_DURATION_SECS
+}
+
+/// Gossipsub verdict for a message that could not be decoded.
+/// Garbage is rejected (and penalises the sender); messages from newer
+/// protocol versions are merely not forwarded, since we cannot vouch for them.
+fn decode_error_acceptance(error: &DecodeError) -> gossipsub::MessageAcceptance {
+    match error {
+        DecodeError::Malformed(_) | DecodeError::TooLarge(_) => gossipsub::MessageAcceptance::Reject,
+        DecodeError::UnsupportedVersion(_) | DecodeError::UnknownKind(_) => {
+            gossipsub::MessageAcceptance::Ignore
+        }
+    }
+}
+
+/// Whether a queued publication that failed to publish is worth retrying:
+/// only when no peer could take it right now.
+fn should_retry_publish(error: &gossipsub::PublishError) -> bool {
+    matches!(
+        error,
+        gossipsub::PublishError::NoPeersSubscribedToTopic | gossipsub::PublishError::AllQueuesFull(_)
+    )
+}
+
+/// Why a received result failed `check_signed_result`.
+#[derive(Debug, Clone, PartialEq)]
+enum InvalidResult {
+    Signature,
+    Payload(String),
+}
+
+impl std::fmt::Display for InvalidResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidResult::Signature => write!(f, "invalid signature"),
+            InvalidResult::Payload(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn is_quic(addr: &Multiaddr) -> bool {
+    addr.iter().any(|p| matches!(p, Protocol::QuicV1))
+}
+
+/// The peer id an address ends in, if any.
+fn peer_id_of(addr: &Multiaddr) -> Option<PeerId> {
+    match addr.iter().last() {
+        Some(Protocol::P2p(peer_id)) => Some(peer_id),
+        _ => None,
+    }
+}
+
+/// Move QUIC addresses to the front, keeping the order otherwise.
+fn prefer_quic(addrs: &mut [Multiaddr]) {
+    addrs.sort_by_key(|a| !is_quic(a));
+}
+
+/// Parse a whitespace- or comma-separated list of multiaddresses, skipping
+/// any that do not parse.
+fn parse_bootstrap_list(list: &str) -> Vec<Multiaddr> {
+    list.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .filter_map(|a| match a.parse() {
+            Ok(addr) => Some(addr),
+            Err(e) => {
+                println!("Ignoring default bootstrap peer {}: {}", a, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Group bootstrap addresses by the peer they name, QUIC first, so a peer
+/// listed with both a TCP and a QUIC address is dialled once, preferring
+/// QUIC. Addresses without a `/p2p/` peer id are each dialled on their own.
+fn group_dial_addresses(addrs: Vec<Multiaddr>) -> Vec<(Option<PeerId>, Vec<Multiaddr>)> {
+    let mut groups: Vec<(Option<PeerId>, Vec<Multiaddr>)> = Vec::new();
+    for addr in addrs {
+        let peer_id = peer_id_of(&addr);
+        match groups.iter_mut().find(|(id, _)| peer_id.is_some() && *id == peer_id) {
+            Some((_, group)) => group.push(addr),
+            None => groups.push((peer_id, vec![addr])),
+        }
+    }
+    for (_, group) in &mut groups {
+        prefer_quic(group);
+    }
+    groups
+}
+
+/// Signature and domain checks a received result must pass before it is
+/// forwarded or aggregated.
+fn check_signed_result(signed: &SignedManifestation) -> Result<(), InvalidResult> {
+    // 1. Cryptographic signature check (app-level)
+    if !signed.verify() {
+        return Err(InvalidResult::Signature);
+    }
+    // 2. Domain validation (range / privacy checks)
+    signed.payload.validate().map_err(InvalidResult::Payload)
+}
+
+/// Handling of the requests only the desktop app makes.
+#[cfg(feature = "gui")]
+mod app_commands {
+    use super::PeerNode;
+    use crate::network::app_command::AppCommand;
+    use crate::network::publish_queue::QueuedPublication;
+    use crate::network::publish_timing::pad;
+    use crate::network::types::MAX_MESSAGE_SIZE;
+    use crate::network::utils::unix_now;
+    use libp2p::gossipsub;
+    use rand::Rng;
+    use std::sync::atomic::Ordering;
+
+    impl PeerNode {
+        pub(super) fn handle_app_command(&mut self, command: AppCommand) { // cargo-mutants: skip
+            match command {
+                AppCommand::Publish { topic, message, sender } => {
+                    self.bytes_out.fetch_add(message.len() as u64, Ordering::Relaxed);
+                    let topic = gossipsub::IdentTopic::new(topic);
+                    if let Err(e) = match self.swarm.behaviour_mut().gossipsub.publish(topic, message) {
+                        Ok(_)  => sender.send(Ok(())),
+                        Err(e) => sender.send(Err(Box::new(e))),
+                    } {
+                        eprintln!("Failed to send Publish response: {:?}", e);
+                    }
+                }
+                AppCommand::QueuePublish { topic, message, max_delay_secs, sender } => {
+                    let reply = String::from_utf8(pad(message, MAX_MESSAGE_SIZE))
+                        .map_err(|e| e.to_string())
+                        .map(|message| {
+                            let now = unix_now();
+                            let release_at = now + rand::thread_rng().gen_range(0..=max_delay_secs);
+                            self.publish_queue.push(QueuedPublication { topic, message, queued_at: now, release_at });
+                            release_at
+                        });
+                    if let Err(e) = sender.send(reply) {
+                        eprintln!("Failed to send QueuePublish response: {:?}", e);
+                    }
+                }
+                AppCommand::ClearPublishQueue { sender } => {
+                    let dropped = self.publish_queue.clear();
+                    if dropped > 0 {
+                        println!("Dropped {} queued publication(s)", dropped);
+                    }
+                    if let Err(e) = sender.send(dropped) {
+                        eprintln!("Failed to send ClearPublishQueue response: {:?}", e);
+                    }
+                }
+                AppCommand::GetPeers { sender } => {
+                    let peers = self.swarm.connected_peers().map(|p| p.to_string()).collect();
+                    if let Err(e) = sender.send(peers) {
+                        eprintln!("Failed to send peers: {:?}", e);
+                    }
+                }
+                AppCommand::GetBannedPeers { sender } => {
+                    let banned = self.banned_peers.values().cloned().collect();
+                    if let Err(e) = sender.send(banned) {
+                        eprintln!("Failed to send banned peers: {:?}", e);
+                    }
+                }
+                AppCommand::UnbanPeer { peer_id, sender } => {
+                    let unbanned = self.unban_peer(&peer_id);
+                    if unbanned {
+                        println!("Unbanned peer {}", peer_id);
+                        if let Some(ref path) = self.ban_list_path.clone() {
+                            self.save_ban_list(path);
+                        }
+                    }
+                    if let Err(e) = sender.send(unbanned) {
+                        eprintln!("Failed to send UnbanPeer response: {:?}", e);
+                    }
+                }
+                AppCommand::GetHistograms { sender } => {
+                    if let Err(e) = sender.send(self.aggregator.histograms()) {
+                        eprintln!("Failed to send histograms: {:?}", e);
+                    }
+                }
+                AppCommand::GetWindowedStats { query, sender } => {
+                    if let Err(e) = sender.send(self.aggregator.windowed(query, unix_now())) {
+                        eprintln!("Failed to send windowed stats: {:?}", e);
+                    }
+                }
+                AppCommand::RankScore { score, category_scores, sender } => {
+                    let rank = self.aggregator.rank(score, &category_scores);
+                    if let Err(e) = sender.send(rank) {
+                        eprintln!("Failed to send score rank: {:?}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     // ── default_bootstrap_peers ─────────────────────────────────────────────
-    // Kills mutant: `replace default_bootstrap_peers -> Vec<Multiaddr> with vec![]`
+    // Kills mutant: `replace parse_bootstrap_list -> Vec<Multiaddr> with vec![]`
 
     #[test]
-    fn default_bootstrap_peers_is_non_empty() {
-        let peers = PeerNode::default_bootstrap_peers();
-        assert!(!peers.is_empty(), "must have at least one bootstrap peer");
+    fn bootstrap_list_splits_on_commas_and_whitespace() {
+        let list = format!(
+            "/ip4/203.0.113.7/tcp/4001/p2p/{},\n  /ip4/203.0.113.7/udp/4001/quic-v1 not-an-address",
+            PeerId::random(),
+        );
+        let peers = parse_bootstrap_list(&list);
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[1].to_string(), "/ip4/203.0.113.7/udp/4001/quic-v1");
     }
 
     // ── load_cache ──────────────────────────────────────────────────────────
@@ -403,16 +1201,467 @@ mod tests {
     fn load_cache_valid_json_returns_correct_data() {
         let dir = std::env::temp_dir();
         let path = dir.join("manifestation_node_cache_test.json");
+        let result = super::super::types::ManifestationResult {
+            score: 2.5,
+            timestamp: 1_700_000_000,
+            category_scores: std::collections::HashMap::from([("14".to_string(), 7.0)]),
+            question_set: crate::questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+        };
         let cache = super::super::types::NetworkScoresCache {
-            scores: vec![1.5, 2.5, 3.5],
-            category_scores: std::collections::HashMap::new(),
+            latest_by_author: std::collections::HashMap::from([("author-key".to_string(), result.clone())]),
+            successions: vec![],
         };
         let json = serde_json::to_vec(&cache).expect("serialise cache");
         std::fs::write(&path, json).expect("write temp cache");
 
         let loaded = PeerNode::load_cache(&path).expect("load_cache must return Some");
-        assert_eq!(loaded.scores, vec![1.5, 2.5, 3.5], "scores must match written data");
+        assert_eq!(loaded.latest_by_author.len(), 1);
+        assert_eq!(loaded.latest_by_author["author-key"], result, "result must match written data");
 
         let _ = std::fs::remove_file(&path);
     }
+
+    #[test]
+    fn load_cache_legacy_score_window_yields_empty_snapshot() {
+        // Caches written before per-author aggregation hold anonymous score
+        // lists that cannot be attributed; they load as an empty snapshot.
+        let path = std::env::temp_dir().join("manifestation_node_legacy_cache_test.json");
+        std::fs::write(&path, br#"{"scores":[1.5,2.5],"category_scores":{}}"#).unwrap();
+
+        let loaded = PeerNode::load_cache(&path).expect("legacy cache must still parse");
+        assert!(loaded.latest_by_author.is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    // ── Gossip validation verdicts ──────────────────────────────────────────
+
+    fn signed_result(score: f64) -> SignedManifestation {
+        let payload = super::super::types::ManifestationResult {
+            score,
+            timestamp: 1_700_000_000,
+            category_scores: std::collections::HashMap::from([("14".to_string(), 5.0)]),
+            question_set: crate::questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+        };
+        SignedManifestation::new(payload, &crate::identity::UserIdentity::generate()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn result_and_succession_from_one_key_in_the_same_second_are_both_admitted() {
+        let (_commands, command_rx) = mpsc::channel(8);
+        let (events, _event_rx) = mpsc::channel(64);
+        let config = NodeConfig { mdns: false, ..NodeConfig::default() };
+        let keys = libp2p::identity::Keypair::generate_ed25519();
+        let mut node = PeerNode::new(keys, command_rx, events, None, vec![], config).await.unwrap();
+
+        let old = crate::identity::UserIdentity::generate();
+        let now = unix_now();
+        let mut payload = signed_result(5000.0).payload;
+        payload.timestamp = now;
+        let signed = SignedManifestation::new(payload, &old).unwrap();
+        let succession = SignedSuccession::new(&old, &crate::identity::UserIdentity::generate(), now).unwrap();
+
+        assert!(matches!(node.handle_result(signed).await, gossipsub::MessageAcceptance::Accept));
+        assert!(matches!(node.handle_succession(succession).await, gossipsub::MessageAcceptance::Accept));
+        assert_eq!(node.rejections.replayed, 0);
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected() {
+        let e = DecodeError::Malformed("bad".into());
+        assert!(matches!(decode_error_acceptance(&e), gossipsub::MessageAcceptance::Reject));
+    }
+
+    #[test]
+    fn oversized_messages_are_rejected() {
+        let e = DecodeError::TooLarge(1 << 20);
+        assert!(matches!(decode_error_acceptance(&e), gossipsub::MessageAcceptance::Reject));
+    }
+
+    #[test]
+    fn newer_protocol_messages_are_ignored() {
+        assert!(matches!(
+            decode_error_acceptance(&DecodeError::UnsupportedVersion(9)),
+            gossipsub::MessageAcceptance::Ignore
+        ));
+        assert!(matches!(
+            decode_error_acceptance(&DecodeError::UnknownKind("future".into())),
+            gossipsub::MessageAcceptance::Ignore
+        ));
+    }
+
+    #[test]
+    fn publish_is_retried_only_when_no_peer_can_take_it() {
+        assert!(should_retry_publish(&gossipsub::PublishError::NoPeersSubscribedToTopic));
+        assert!(should_retry_publish(&gossipsub::PublishError::AllQueuesFull(3)));
+        assert!(!should_retry_publish(&gossipsub::PublishError::Duplicate));
+        assert!(!should_retry_publish(&gossipsub::PublishError::MessageTooLarge));
+    }
+
+    #[test]
+    fn check_signed_result_accepts_valid_result() {
+        assert!(check_signed_result(&signed_result(5000.0)).is_ok());
+    }
+
+    #[test]
+    fn check_signed_result_rejects_bad_signature() {
+        let mut signed = signed_result(5000.0);
+        signed.payload.score = 9000.0;
+        assert_eq!(check_signed_result(&signed), Err(InvalidResult::Signature));
+    }
+
+    #[test]
+    fn check_signed_result_rejects_invalid_payload() {
+        // Correctly signed, but out of range
+        assert!(matches!(
+            check_signed_result(&signed_result(20_000.0)),
+            Err(InvalidResult::Payload(_))
+        ));
+    }
+
+    // ── Peer scoring / bans ─────────────────────────────────────────────────
+
+    #[test]
+    fn peer_score_config_is_valid() {
+        let topic = gossipsub::IdentTopic::new(GLOBAL_TOPIC).hash();
+        let (params, thresholds) = peer_score_config(topic.clone());
+        assert!(params.validate().is_ok());
+        assert!(thresholds.validate().is_ok());
+        assert!(params.topics.contains_key(&topic));
+    }
+
+    #[test]
+    fn ban_threshold_is_below_graylist() {
+        let topic = gossipsub::IdentTopic::new(GLOBAL_TOPIC).hash();
+        let (_, thresholds) = peer_score_config(topic);
+        assert!(BAN_SCORE_THRESHOLD < thresholds.graylist_threshold);
+    }
+
+    #[test]
+    fn three_invalid_messages_exceed_max_positive_score() {
+        let topic = gossipsub::IdentTopic::new(GLOBAL_TOPIC).hash();
+        let (params, _) = peer_score_config(topic.clone());
+        let t = &params.topics[&topic];
+        let max_positive = t.time_in_mesh_weight * t.time_in_mesh_cap
+            + t.first_message_deliveries_weight * t.first_message_deliveries_cap;
+        let penalty = t.invalid_message_deliveries_weight * 3.0_f64.powi(2);
+        assert!(should_ban((max_positive + penalty) * t.topic_weight));
+    }
+
+    #[test]
+    fn only_invalid_deliveries_lower_the_score() {
+        let topic = gossipsub::IdentTopic::new(GLOBAL_TOPIC).hash();
+        let (params, _) = peer_score_config(topic.clone());
+        // Many peers sharing one NAT or relay address must not add up to a ban
+        assert_eq!(params.ip_colocation_factor_weight, 0.0);
+        assert_eq!(params.behaviour_penalty_weight, 0.0);
+        assert_eq!(params.slow_peer_weight, 0.0);
+        let t = &params.topics[&topic];
+        assert_eq!((t.mesh_message_deliveries_weight, t.mesh_failure_penalty_weight), (0.0, 0.0));
+        assert!(t.invalid_message_deliveries_weight < 0.0);
+    }
+
+    #[test]
+    fn bans_expire_after_ban_duration() {
+        let record = BannedPeer { peer_id: PeerId::random().to_string(), banned_at: 1_700_000_000, score: -150.0 };
+        assert!(!ban_expired(&record, record.banned_at + BAN_DURATION_SECS - 1));
+        assert!(ban_expired(&record, record.banned_at + BAN_DURATION_SECS));
+    }
+
+    #[test]
+    fn should_ban_boundary() {
+        assert!(!should_ban(BAN_SCORE_THRESHOLD));
+        assert!(should_ban(BAN_SCORE_THRESHOLD - 0.1));
+        assert!(!should_ban(0.0));
+    }
+
+    #[test]
+    fn load_ban_list_roundtrip() {
+        let path = std::env::temp_dir().join("manifestation_ban_list_test.json");
+        let record = BannedPeer {
+            peer_id: PeerId::random().to_string(),
+            banned_at: 1_700_000_000,
+            score: -150.0,
+        };
+        let json = serde_json::to_vec(&BannedPeersCache { peers: vec![record.clone()] }).unwrap();
+        std::fs::write(&path, json).unwrap();
+
+        let loaded = PeerNode::load_ban_list(&path).expect("ban list must load");
+        assert_eq!(loaded.peers, vec![record]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_ban_list_missing_file_returns_none() {
+        let path = std::path::Path::new("/nonexistent/no_such_ban_list.json");
+        assert!(PeerNode::load_ban_list(path).is_none());
+    }
+
+    // ── QUIC ────────────────────────────────────────────────────────────────
+
+    #[test]
+    fn bootstrap_addresses_are_grouped_per_peer_quic_first() {
+        let peer = PeerId::random();
+        let tcp: Multiaddr = format!("/ip4/10.0.0.1/tcp/4001/p2p/{}", peer).parse().unwrap();
+        let quic: Multiaddr = format!("/ip4/10.0.0.1/udp/4001/quic-v1/p2p/{}", peer).parse().unwrap();
+        let anonymous: Multiaddr = "/ip4/10.0.0.2/tcp/4001".parse().unwrap();
+
+        let groups = group_dial_addresses(vec![tcp.clone(), anonymous.clone(), quic.clone()]);
+        assert_eq!(groups, vec![(Some(peer), vec![quic, tcp]), (None, vec![anonymous])]);
+    }
+
+    #[test]
+    fn prefer_quic_keeps_relative_order() {
+        let mut addrs: Vec<Multiaddr> = ["/ip4/1.1.1.1/tcp/1", "/ip4/2.2.2.2/udp/2/quic-v1", "/ip4/3.3.3.3/tcp/3"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        prefer_quic(&mut addrs);
+        let order: Vec<String> = addrs.iter().map(|a| a.to_string()).collect();
+        assert_eq!(order, ["/ip4/2.2.2.2/udp/2/quic-v1", "/ip4/1.1.1.1/tcp/1", "/ip4/3.3.3.3/tcp/3"]);
+    }
+
+    /// Start a node that dials `bootstrap`; returns its peer id, command
+    /// sender (dropping it stops the node) and stats receiver.
+    async fn spawn_node(
+        bootstrap: Vec<Multiaddr>,
+        config: NodeConfig,
+    ) -> (PeerId, mpsc::Sender<Command>, mpsc::Receiver<NetworkStatUpdate>) {
+        let keys = libp2p::identity::Keypair::generate_ed25519();
+        let peer_id = keys.public().to_peer_id();
+        let (commands, command_rx) = mpsc::channel(8);
+        let (events, event_rx) = mpsc::channel(64);
+        let node = PeerNode::new(keys, command_rx, events, None, bootstrap, config).await.unwrap();
+        tokio::spawn(node.run());
+        (peer_id, commands, event_rx)
+    }
+
+    async fn listen(commands: &mpsc::Sender<Command>, addr: Multiaddr) {
+        let (ack, ack_rx) = tokio::sync::oneshot::channel();
+        commands.send(Command::StartListening { addr, sender: ack }).await.unwrap();
+        ack_rx.await.unwrap().unwrap();
+    }
+
+    /// Wait up to 20 seconds for a stats update matching `done`.
+    async fn wait_for_stats(
+        events: &mut mpsc::Receiver<NetworkStatUpdate>,
+        done: impl Fn(&NetworkStatUpdate) -> bool,
+    ) -> bool {
+        tokio::time::timeout(Duration::from_secs(20), async {
+            while let Some(stats) = events.recv().await {
+                if done(&stats) {
+                    return true;
+                }
+            }
+            false
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    #[tokio::test]
+    async fn two_nodes_connect_over_quic_on_loopback() {
+        let port = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let addr: Multiaddr = format!("/ip4/127.0.0.1/udp/{}/quic-v1", port).parse().unwrap();
+
+        // The listener only has a QUIC address, so any connection to it is QUIC.
+        let (listener_id, listener, _listener_events) = spawn_node(vec![], NodeConfig::default()).await;
+        listen(&listener, addr.clone()).await;
+
+        let bootstrap = vec![addr.with(Protocol::P2p(listener_id))];
+        let (_, dialer, mut dialer_events) = spawn_node(bootstrap, NodeConfig::default()).await;
+        let connected = wait_for_stats(&mut dialer_events, |s| {
+            s.connected_peers.contains(&listener_id.to_string())
+        })
+        .await;
+        drop((listener, dialer));
+        assert!(connected, "dialer must connect to the listener over QUIC");
+    }
+
+    #[tokio::test]
+    async fn peer_advertising_tcp_and_quic_is_reached_over_quic() {
+        let mut listener = libp2p::SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)
+            .unwrap()
+            .with_quic()
+            .with_behaviour(|_| libp2p::swarm::dummy::Behaviour)
+            .unwrap()
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build();
+        let listener_id = *listener.local_peer_id();
+        listener.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        listener.listen_on("/ip4/127.0.0.1/udp/0/quic-v1".parse().unwrap()).unwrap();
+        let mut addrs = Vec::new();
+        while addrs.len() < 2 {
+            if let SwarmEvent::NewListenAddr { address, .. } = listener.select_next_some().await {
+                addrs.push(address);
+            }
+        }
+        // TCP listed first: the dialer must still pick QUIC.
+        addrs.sort_by_key(is_quic);
+        let bootstrap = addrs.into_iter().map(|a| a.with(Protocol::P2p(listener_id))).collect();
+        let (_, dialer, _dialer_events) = spawn_node(bootstrap, NodeConfig::default()).await;
+
+        // Record every inbound attempt until the connection is up and for a
+        // moment after, so a losing TCP dial racing QUIC would show up too.
+        let mut incoming = Vec::new();
+        let connected = tokio::time::timeout(Duration::from_secs(20), async {
+            loop {
+                match listener.select_next_some().await {
+                    SwarmEvent::IncomingConnection { local_addr, .. } => incoming.push(local_addr),
+                    SwarmEvent::ConnectionEstablished { .. } => break,
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .is_ok();
+        let _ = tokio::time::timeout(Duration::from_millis(500), async {
+            loop {
+                if let SwarmEvent::IncomingConnection { local_addr, .. } = listener.select_next_some().await {
+                    incoming.push(local_addr);
+                }
+            }
+        })
+        .await;
+        drop(dialer);
+        assert!(connected, "dialer must connect");
+        assert!(incoming.iter().all(is_quic), "TCP must only be a fallback, got {:?}", incoming);
+    }
+
+    // ── Kademlia ────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn bootstrap_finds_peers_known_to_the_bootstrap_node() {
+        let loopback = || -> Multiaddr { "/ip4/127.0.0.1/tcp/0".parse().unwrap() };
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/{}", port).parse().unwrap();
+        // Without mDNS, loopback peers can only find each other through the
+        // DHT. Only relay servers start in Kademlia server mode.
+        let server = || NodeConfig { mdns: false, relay_server: true, ..NodeConfig::default() };
+        let (hub_id, hub, mut hub_events) = spawn_node(vec![], server()).await;
+        listen(&hub, addr.clone()).await;
+        let bootstrap = vec![addr.with(Protocol::P2p(hub_id))];
+
+        let (_, first, _first_events) = spawn_node(bootstrap.clone(), server()).await;
+        listen(&first, loopback()).await;
+        let hub_knows_first = wait_for_stats(&mut hub_events, |s| s.routing_table_size >= 1).await;
+        assert!(hub_knows_first, "the bootstrap node must add peers on our DHT to its routing table");
+
+        // The second peer is a client that only knows the hub; once the hub
+        // joins its routing table, a bootstrap query has to learn the first
+        // server from it.
+        let (_, second, mut second_events) = spawn_node(bootstrap, NodeConfig { mdns: false, ..NodeConfig::default() }).await;
+        listen(&second, loopback()).await;
+        let found = wait_for_stats(&mut second_events, |s| s.routing_table_size >= 2).await;
+        drop((hub, first, second));
+        assert!(found, "bootstrap must fill the routing table beyond the bootstrap peer");
+    }
+
+    async fn spawn_cached_node(
+        cache_path: PathBuf,
+        bootstrap: Vec<Multiaddr>,
+    ) -> (mpsc::Sender<Command>, mpsc::Receiver<NetworkStatUpdate>, tokio::task::JoinHandle<()>) {
+        let keys = libp2p::identity::Keypair::generate_ed25519();
+        let (commands, command_rx) = mpsc::channel(8);
+        let (events, event_rx) = mpsc::channel(64);
+        let config = NodeConfig { mdns: false, ..NodeConfig::default() };
+        let node = PeerNode::new(keys, command_rx, events, Some(cache_path), bootstrap, config).await.unwrap();
+        (commands, event_rx, tokio::spawn(node.run()))
+    }
+
+    #[tokio::test]
+    async fn restart_reconnects_to_peers_from_the_address_book() {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let dir = std::env::temp_dir().join(format!("address_book_restart_{}", nanos));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache_path = dir.join("network_cache.json");
+        let free_port = || std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+
+        let addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/{}", free_port()).parse().unwrap();
+        let server = NodeConfig { mdns: false, relay_server: true, ..NodeConfig::default() };
+        let (peer_id, peer, _peer_events) = spawn_node(vec![], server).await;
+        listen(&peer, addr.clone()).await;
+
+        let (node, mut events, task) = spawn_cached_node(cache_path.clone(), vec![addr.with(Protocol::P2p(peer_id))]).await;
+        let recorded = wait_for_stats(&mut events, |s| s.known_peers >= 1).await;
+        node.send(Command::Shutdown).await.unwrap();
+        task.await.unwrap();
+        assert!(recorded, "an identified DHT peer must enter the address book");
+        assert!(dir.join("peer_addresses.json").exists(), "shutdown must save the address book");
+
+        // Nothing listens on the only bootstrap address, so the address book
+        // is the sole way back to the peer.
+        let dead: Multiaddr = format!("/ip4/127.0.0.1/tcp/{}", free_port()).parse().unwrap();
+        let (restarted, mut events, _task) = spawn_cached_node(cache_path, vec![dead]).await;
+        let expected = peer_id.to_string();
+        let reconnected = wait_for_stats(&mut events, |s| s.connected_peers.contains(&expected)).await;
+        drop((peer, restarted));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(reconnected, "a restarted node must dial peers from its address book");
+    }
+
+    // ── NAT traversal ───────────────────────────────────────────────────────
+
+    /// A bare circuit relay v2 server listening on loopback TCP.
+    async fn spawn_relay() -> Multiaddr {
+        let mut relay = libp2p::SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)
+            .unwrap()
+            .with_behaviour(|key| relay::Behaviour::new(key.public().to_peer_id(), relay::Config::default()))
+            .unwrap()
+            .build();
+        let relay_id = *relay.local_peer_id();
+        relay.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = relay.select_next_some().await {
+                break address;
+            }
+        };
+        // Reservations advertise the relay's external addresses.
+        relay.add_external_address(addr.clone());
+        tokio::spawn(async move {
+            loop {
+                relay.select_next_some().await;
+            }
+        });
+        addr.with(Protocol::P2p(relay_id))
+    }
+
+    #[tokio::test]
+    async fn peers_reach_each_other_through_a_relay_reservation() {
+        let relay = spawn_relay().await;
+        let config = NodeConfig { relays: vec![relay.clone()], ..NodeConfig::default() };
+
+        // The listener never listens directly; it is reachable only through the relay.
+        let (listener_id, listener, mut listener_events) = spawn_node(vec![], config).await;
+        let reserved = wait_for_stats(&mut listener_events, |s| s.relay_reservations == 1).await;
+        assert!(reserved, "relay must accept the reservation");
+
+        let circuit = relay.with(Protocol::P2pCircuit).with(Protocol::P2p(listener_id));
+        let (_, dialer, mut dialer_events) = spawn_node(vec![circuit], NodeConfig::default()).await;
+        let connected = wait_for_stats(&mut dialer_events, |s| {
+            s.connected_peers.contains(&listener_id.to_string())
+        })
+        .await;
+        drop((listener, dialer));
+        assert!(connected, "dialer must reach the listener through the relay circuit");
+    }
+
+    #[test]
+    fn relay_addresses_need_a_peer_id() {
+        let peer = PeerId::random();
+        let with_id: Multiaddr = format!("/ip4/10.0.0.1/tcp/4001/p2p/{}", peer).parse().unwrap();
+        assert_eq!(peer_id_of(&with_id), Some(peer));
+        assert_eq!(peer_id_of(&"/ip4/10.0.0.1/tcp/4001".parse().unwrap()), None);
+    }
 }
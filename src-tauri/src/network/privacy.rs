@@ -0,0 +1,475 @@
+use super::histogram::{CATEGORY_RANGE, TOTAL_RANGE};
+use super::types::ManifestationResult;
+use serde::{Deserialize, Serialize};
+
+/// Smallest and largest privacy budget a user can choose. Below 1 the noise
+/// swamps any realistic network, and a sender could declare a tiny ε to get
+/// a wildly out-of-range score accepted; above 10 it protects next to nothing.
+pub const MIN_EPSILON: f64 = 1.0;
+pub const MAX_EPSILON: f64 = 10.0;
+
+/// Aggregators clamp each noised report to within this many noise standard
+/// deviations of its range. Honest reports almost never reach the clamp, but
+/// one fabricated report can then move a mean no further than a four-sigma
+/// draw of its declared noise would.
+const CLAMP_STANDARD_DEVIATIONS: f64 = 4.0;
+
+/// Laplace noise is truncated (by resampling) at this many scale units. The
+/// cut is symmetric, so the noise stays zero-mean; the chance of exceeding
+/// it, e^-20, is the δ of the resulting (ε, δ) guarantee.
+const LAPLACE_TAIL: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseMechanism {
+    /// Value plus Laplace noise; reports stay close to the truth.
+    Laplace,
+    /// Each value is replaced by one of two extremes, with the probability
+    /// of the upper one growing with the true value (Duchi et al.).
+    RandomizedResponse,
+}
+
+/// Opt-in local differential privacy: noise added to a result before it is
+/// signed. Carried in the payload so aggregators can account for it.
+///
+/// `epsilon` is the budget for one whole publication. Half of it goes to the
+/// total and the other half is split evenly over the category scores, so the
+/// total stays useful while each category, with 54 of them sharing, needs a
+/// large network to say much.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LocalPrivacy {
+    pub mechanism: NoiseMechanism,
+    pub epsilon: f64,
+}
+
+impl LocalPrivacy {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_EPSILON..=MAX_EPSILON).contains(&self.epsilon) {
+            return Err(format!(
+                "Privacy epsilon {} is out of range ({} - {})",
+                self.epsilon, MIN_EPSILON, MAX_EPSILON
+            ));
+        }
+        Ok(())
+    }
+
+    /// Noise parameters for the total and for each category score of a
+    /// result carrying `categories` of them. The total gets the whole budget
+    /// when there are none.
+    pub fn budgets(&self, categories: usize) -> (LocalPrivacy, LocalPrivacy) {
+        if categories == 0 {
+            return (*self, *self);
+        }
+        let half = self.epsilon / 2.0;
+        (
+            LocalPrivacy { epsilon: half, ..*self },
+            LocalPrivacy { epsilon: half / categories as f64, ..*self },
+        )
+    }
+
+    /// Laplace scale for a value spanning `width`.
+    fn laplace_scale(&self, width: f64) -> f64 {
+        width / self.epsilon
+    }
+
+    /// Randomized response reports ±c on the [-1, 1]-rescaled value.
+    fn response_bound(&self) -> f64 {
+        let e = self.epsilon.exp();
+        (e + 1.0) / (e - 1.0)
+    }
+
+    /// How far outside its range a noised value can fall.
+    pub fn max_overshoot(&self, width: f64) -> f64 {
+        match self.mechanism {
+            NoiseMechanism::Laplace => LAPLACE_TAIL * self.laplace_scale(width),
+            NoiseMechanism::RandomizedResponse => (self.response_bound() - 1.0) / 2.0 * width,
+        }
+    }
+
+    /// Variance the noise adds to a value spanning `width`. For randomized
+    /// response this is an upper bound: the exact variance depends on the
+    /// true value.
+    pub fn noise_variance(&self, width: f64) -> f64 {
+        match self.mechanism {
+            NoiseMechanism::Laplace => 2.0 * self.laplace_scale(width).powi(2),
+            NoiseMechanism::RandomizedResponse => (self.response_bound() * width / 2.0).powi(2),
+        }
+    }
+}
+
+/// A reported value as an aggregator should count it: clamped to within
+/// `CLAMP_STANDARD_DEVIATIONS` of `(min, max)`, with its noise variance.
+fn clamped(value: f64, (min, max): (f64, f64), noise: Option<LocalPrivacy>) -> (f64, f64) {
+    let Some(noise) = noise else { return (value, 0.0) };
+    let variance = noise.noise_variance(max - min);
+    let reach = CLAMP_STANDARD_DEVIATIONS * variance.sqrt();
+    (value.clamp(min - reach, max + reach), variance)
+}
+
+/// The total of `result` as a mean estimate should count it, and its noise
+/// variance; unchanged and zero for exact results.
+pub fn counted_total(result: &ManifestationResult) -> (f64, f64) {
+    let noise = result.privacy.map(|p| p.budgets(result.category_scores.len()).0);
+    clamped(result.score, TOTAL_RANGE, noise)
+}
+
+/// Like `counted_total`, for the category score `score` of `result`.
+pub fn counted_category(result: &ManifestationResult, score: f64) -> (f64, f64) {
+    let noise = result.privacy.map(|p| p.budgets(result.category_scores.len()).1);
+    clamped(score, CATEGORY_RANGE, noise)
+}
+
+/// Adding the noise, which only the publishing desktop app does.
+#[cfg(any(feature = "gui", test))]
+mod noising {
+    use super::{LocalPrivacy, NoiseMechanism, LAPLACE_TAIL};
+    use crate::network::histogram::{CATEGORY_RANGE, TOTAL_RANGE};
+    use crate::network::types::ManifestationResult;
+    use rand::Rng;
+
+    impl LocalPrivacy {
+        /// Replace the total and each category score of `result` with noised
+        /// values and record the mechanism in the payload.
+        pub fn perturb<R: Rng + ?Sized>(self, result: &mut ManifestationResult, rng: &mut R) {
+            let (total, category) = self.budgets(result.category_scores.len());
+            result.score = total.noise(result.score, TOTAL_RANGE, rng);
+            for score in result.category_scores.values_mut() {
+                *score = category.noise(*score, CATEGORY_RANGE, rng);
+            }
+            result.privacy = Some(self);
+        }
+
+        /// Unbiased noised copy of `value` within `(min, max)`.
+        pub(super) fn noise<R: Rng + ?Sized>(&self, value: f64, (min, max): (f64, f64), rng: &mut R) -> f64 {
+            let width = max - min;
+            match self.mechanism {
+                NoiseMechanism::Laplace => {
+                    let scale = self.laplace_scale(width);
+                    loop {
+                        // Inverse CDF on u ∈ (-0.5, 0.5); u = -0.5 would give -inf.
+                        let u: f64 = rng.gen::<f64>() - 0.5;
+                        let noise = -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln();
+                        if noise.is_finite() && noise.abs() <= LAPLACE_TAIL * scale {
+                            return value + noise;
+                        }
+                    }
+                }
+                NoiseMechanism::RandomizedResponse => {
+                    let c = self.response_bound();
+                    let t = (2.0 * (value - min) / width - 1.0).clamp(-1.0, 1.0);
+                    let report = if rng.gen_bool((0.5 + t / (2.0 * c)).clamp(0.0, 1.0)) { c } else { -c };
+                    min + (report + 1.0) / 2.0 * width
+                }
+            }
+        }
+    }
+}
+
+/// Unbiased mean of a series mixing exact and noised reports.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MeanEstimate {
+    pub mean: f64,
+    /// Standard error of `mean` due to the privacy noise alone.
+    pub noise_standard_error: f64,
+    pub sample_size: usize,
+    /// How many of the `sample_size` reports were noised.
+    pub noisy_samples: usize,
+}
+
+/// Running sums behind a `MeanEstimate`. Each noised report is an unbiased
+/// estimate of its true value, so their plain mean is unbiased too; the
+/// noise variances add up to its standard error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeanAccumulator {
+    samples: usize,
+    noisy_samples: usize,
+    sum: f64,
+    noise_variance: f64,
+}
+
+impl MeanAccumulator {
+    pub fn add(&mut self, value: f64, noise_variance: f64) {
+        self.samples += 1;
+        self.noisy_samples += usize::from(noise_variance > 0.0);
+        self.sum += value;
+        self.noise_variance += noise_variance;
+    }
+
+    pub fn remove(&mut self, value: f64, noise_variance: f64) {
+        self.samples = self.samples.saturating_sub(1);
+        self.noisy_samples = self.noisy_samples.saturating_sub(usize::from(noise_variance > 0.0));
+        if self.samples == 0 {
+            // Reset rather than accumulate rounding error once drained.
+            *self = Self::default();
+        } else {
+            self.sum -= value;
+            self.noise_variance = if self.noisy_samples == 0 {
+                0.0
+            } else {
+                (self.noise_variance - noise_variance).max(0.0)
+            };
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples == 0
+    }
+
+    pub fn estimate(&self) -> Option<MeanEstimate> {
+        (!self.is_empty()).then(|| {
+            let n = self.samples as f64;
+            MeanEstimate {
+                mean: self.sum / n,
+                noise_standard_error: self.noise_variance.sqrt() / n,
+                sample_size: self.samples,
+                noisy_samples: self.noisy_samples,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashMap;
+
+    const LAPLACE: LocalPrivacy = LocalPrivacy { mechanism: NoiseMechanism::Laplace, epsilon: 1.0 };
+    const RESPONSE: LocalPrivacy = LocalPrivacy { mechanism: NoiseMechanism::RandomizedResponse, epsilon: 1.0 };
+
+    fn result(score: f64, rating: f64) -> ManifestationResult {
+        ManifestationResult {
+            score,
+            timestamp: 0,
+            category_scores: HashMap::from([("14".to_string(), rating)]),
+            question_set: crate::questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+        }
+    }
+
+    /// Complete answer sheets with every rated question answered, so each
+    /// result carries all 54 category scores.
+    fn full_results(rng: &mut StdRng) -> Vec<ManifestationResult> {
+        (0..100).map(|_| {
+            let answers = crate::questions::rated_questions()
+                .map(|q| (q.id.to_string(), f64::from(rng.gen_range(0..=10u8))))
+                .collect();
+            let scores = crate::scoring::score_answers(&answers).unwrap();
+            ManifestationResult { score: scores.total, category_scores: scores.category_scores, ..result(0.0, 0.0) }
+        }).collect()
+    }
+
+    /// Noise `n` full results and compare the mean estimates of the total
+    /// and of one category with the true means: the error must stay within
+    /// four standard errors (about 1 in 16,000 by chance; the seed is fixed).
+    /// Returns the standard error of the total.
+    fn assert_unbiased(privacy: LocalPrivacy, n: usize, seed: u64) -> f64 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut totals = MeanAccumulator::default();
+        let mut ratings = MeanAccumulator::default();
+        let (mut true_total, mut true_rating) = (0.0, 0.0);
+        let sheets = full_results(&mut rng);
+        for sheet in sheets.iter().cycle().take(n) {
+            let mut r = sheet.clone();
+            true_total += r.score;
+            true_rating += r.category_scores["14"];
+            privacy.perturb(&mut r, &mut rng);
+            r.validate().expect("noised result stays valid");
+            let (total, variance) = counted_total(&r);
+            totals.add(total, variance);
+            let (rating, variance) = counted_category(&r, r.category_scores["14"]);
+            ratings.add(rating, variance);
+        }
+        for (accumulator, truth) in [(totals, true_total), (ratings, true_rating)] {
+            let estimate = accumulator.estimate().unwrap();
+            let error = (estimate.mean - truth / n as f64).abs();
+            assert!(
+                error <= 4.0 * estimate.noise_standard_error,
+                "{:?}: error {} exceeds 4 × {}",
+                privacy.mechanism,
+                error,
+                estimate.noise_standard_error
+            );
+            assert_eq!(estimate.noisy_samples, n);
+        }
+        totals.estimate().unwrap().noise_standard_error
+    }
+
+    #[test]
+    fn laplace_mean_is_unbiased() {
+        for seed in 0..5 {
+            assert_unbiased(LAPLACE, 20_000, seed);
+        }
+    }
+
+    #[test]
+    fn randomized_response_mean_is_unbiased() {
+        for seed in 0..5 {
+            assert_unbiased(RESPONSE, 20_000, seed);
+        }
+    }
+
+    #[test]
+    fn full_results_give_a_useful_total_at_realistic_network_size() {
+        // 10,000 users at ε = 2: the total gets ε = 1 and its mean is known
+        // to within a few percent of its 0–10,000 range, even though every
+        // result also reports 54 noised categories.
+        for mechanism in [NoiseMechanism::Laplace, NoiseMechanism::RandomizedResponse] {
+            let privacy = LocalPrivacy { mechanism, epsilon: 2.0 };
+            let standard_error = assert_unbiased(privacy, 10_000, 1);
+            assert!(standard_error < 0.02 * TOTAL_RANGE.1, "{:?}: {}", mechanism, standard_error);
+        }
+    }
+
+    #[test]
+    fn one_adversarial_report_moves_the_mean_by_at_most_its_clamp() {
+        // 1,000 honest reports at the largest ε, plus one fabricated report
+        // at the smallest ε claiming the highest total validation accepts.
+        let mut rng = StdRng::seed_from_u64(13);
+        let honest_privacy = LocalPrivacy { mechanism: NoiseMechanism::Laplace, epsilon: MAX_EPSILON };
+        let mut totals = MeanAccumulator::default();
+        let mut true_total = 0.0;
+        let sheets = full_results(&mut rng);
+        for sheet in sheets.iter().cycle().take(1_000) {
+            let mut r = sheet.clone();
+            true_total += r.score;
+            honest_privacy.perturb(&mut r, &mut rng);
+            let (total, variance) = counted_total(&r);
+            totals.add(total, variance);
+        }
+        let honest = totals.estimate().unwrap();
+
+        let attacker = LocalPrivacy { mechanism: NoiseMechanism::Laplace, epsilon: MIN_EPSILON };
+        let mut forged = result(0.0, 0.0);
+        forged.category_scores.clear();
+        forged.privacy = Some(attacker);
+        forged.score = TOTAL_RANGE.1 + attacker.max_overshoot(TOTAL_RANGE.1);
+        forged.validate().expect("the forged report passes validation");
+        let (total, variance) = counted_total(&forged);
+        assert!(total < forged.score, "the report is clamped");
+        totals.add(total, variance);
+        let poisoned = totals.estimate().unwrap();
+
+        // The shift is that of a four-sigma draw of the declared noise, and
+        // the mean stays within four standard errors of the truth.
+        let reach = CLAMP_STANDARD_DEVIATIONS * attacker.noise_variance(TOTAL_RANGE.1).sqrt();
+        let shift = poisoned.mean - honest.mean;
+        assert!(shift <= (TOTAL_RANGE.1 + reach) / 1_001.0, "shift {}", shift);
+        let error = (poisoned.mean - true_total / 1_000.0).abs();
+        assert!(error <= 4.0 * poisoned.noise_standard_error, "error {} vs {}", error, poisoned.noise_standard_error);
+    }
+
+    #[test]
+    fn standard_error_shrinks_with_sample_size() {
+        let mut small = MeanAccumulator::default();
+        let mut large = MeanAccumulator::default();
+        let variance = LAPLACE.noise_variance(10.0);
+        for _ in 0..100 {
+            small.add(5.0, variance);
+        }
+        for _ in 0..10_000 {
+            large.add(5.0, variance);
+        }
+        let ratio = small.estimate().unwrap().noise_standard_error / large.estimate().unwrap().noise_standard_error;
+        assert!((ratio - 10.0).abs() < 1e-9, "1/sqrt(n) scaling, got {}", ratio);
+    }
+
+    #[test]
+    fn empirical_variance_matches_declared_variance() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let n = 50_000;
+        let declared = LAPLACE.noise_variance(10.0);
+        let sum_sq: f64 = (0..n).map(|_| (LAPLACE.noise(5.0, (0.0, 10.0), &mut rng) - 5.0).powi(2)).sum();
+        let empirical = sum_sq / n as f64;
+        assert!((empirical / declared - 1.0).abs() < 0.05, "empirical {} vs declared {}", empirical, declared);
+    }
+
+    #[test]
+    fn randomized_response_variance_is_an_upper_bound() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let n = 50_000;
+        for value in [0.0, 5.0, 10.0] {
+            let sum_sq: f64 = (0..n).map(|_| (RESPONSE.noise(value, (0.0, 10.0), &mut rng) - value).powi(2)).sum();
+            assert!(sum_sq / n as f64 <= RESPONSE.noise_variance(10.0) * 1.02);
+        }
+    }
+
+    #[test]
+    fn randomized_response_reports_one_of_two_values() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let overshoot = RESPONSE.max_overshoot(10.0);
+        for _ in 0..100 {
+            let reported = RESPONSE.noise(3.0, (0.0, 10.0), &mut rng);
+            let low = (reported + overshoot).abs() < 1e-9;
+            let high = (reported - 10.0 - overshoot).abs() < 1e-9;
+            assert!(low || high, "unexpected report {}", reported);
+        }
+    }
+
+    #[test]
+    fn laplace_noise_is_truncated() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let overshoot = LAPLACE.max_overshoot(10.0);
+        for _ in 0..10_000 {
+            let reported = LAPLACE.noise(0.0, (0.0, 10.0), &mut rng);
+            assert!(reported.abs() <= overshoot);
+        }
+    }
+
+    #[test]
+    fn budget_is_split_between_total_and_categories() {
+        let (total, category) = LAPLACE.budgets(54);
+        assert_eq!(total.epsilon, 0.5);
+        assert_eq!(category.epsilon, 0.5 / 54.0);
+        // Laplace variance is 2(width/ε)²: halving ε quadruples it.
+        assert!((total.noise_variance(10.0) / LAPLACE.noise_variance(10.0) - 4.0).abs() < 1e-9);
+        assert_eq!(LAPLACE.budgets(0).0, LAPLACE, "a bare total gets the whole budget");
+    }
+
+    #[test]
+    fn perturb_records_mechanism_and_noises_categories() {
+        let mut r = result(5000.0, 5.0);
+        LAPLACE.perturb(&mut r, &mut StdRng::seed_from_u64(1));
+        assert_eq!(r.privacy, Some(LAPLACE));
+        assert_ne!(r.score, 5000.0);
+        assert_ne!(r.category_scores["14"], 5.0);
+    }
+
+    #[test]
+    fn exact_reports_are_counted_unchanged() {
+        let r = result(12_345.0, 5.0);
+        assert_eq!(counted_total(&r), (12_345.0, 0.0));
+        assert_eq!(counted_category(&r, 5.0), (5.0, 0.0));
+    }
+
+    #[test]
+    fn epsilon_outside_range_is_rejected() {
+        for epsilon in [0.0, 0.1, 0.5, 10.5, f64::NAN, f64::INFINITY] {
+            let privacy = LocalPrivacy { mechanism: NoiseMechanism::Laplace, epsilon };
+            assert!(privacy.validate().is_err(), "epsilon {}", epsilon);
+        }
+        assert!(LAPLACE.validate().is_ok());
+    }
+
+    #[test]
+    fn accumulator_drains_to_empty() {
+        let mut a = MeanAccumulator::default();
+        a.add(3.0, 0.0);
+        a.add(7.0, 2.0);
+        assert_eq!(a.estimate().unwrap().mean, 5.0);
+        assert_eq!(a.estimate().unwrap().noisy_samples, 1);
+        a.remove(7.0, 2.0);
+        assert_eq!(a.estimate().unwrap().noise_standard_error, 0.0);
+        a.remove(3.0, 0.0);
+        assert_eq!(a.estimate(), None);
+    }
+
+    #[test]
+    fn mechanism_serialises_as_snake_case() {
+        let json = serde_json::to_value(RESPONSE).unwrap();
+        assert_eq!(json["mechanism"], "randomized_response");
+    }
+}
@@ -0,0 +1,266 @@
+use super::succession::SignedSuccession;
+use super::types::{SignedManifestation, MAX_MESSAGE_SIZE};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+/// Envelope version written by this build.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Highest envelope version this build can decode.
+pub const MAX_SUPPORTED_VERSION: u32 = PROTOCOL_VERSION;
+
+/// A decoded message from the `manifestation-global` topic.
+#[derive(Debug, Clone)]
+pub enum GossipMessage {
+    /// A signed assessment result.
+    Result(SignedManifestation),
+    /// A key rotation, signed by the retired and the new key.
+    Succession(SignedSuccession),
+}
+
+/// Why a gossip message could not be turned into a `GossipMessage`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// Envelope from a newer protocol version; ignored rather than treated as garbage.
+    UnsupportedVersion(u32),
+    /// Known version but a message kind this build does not understand.
+    UnknownKind(String),
+    /// Larger than `MAX_MESSAGE_SIZE`; rejected without being parsed.
+    TooLarge(usize),
+    /// Not valid JSON, or missing/invalid fields.
+    Malformed(String),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported protocol version {}", v),
+            DecodeError::UnknownKind(k) => write!(f, "unknown message kind '{}'", k),
+            DecodeError::TooLarge(n) => write!(f, "message of {} bytes exceeds {} byte limit", n, MAX_MESSAGE_SIZE),
+            DecodeError::Malformed(e) => write!(f, "malformed message: {}", e),
+        }
+    }
+}
+
+/// Only the version field; used to pick a decoder before parsing the rest.
+/// v0 messages (bare `SignedManifestation` JSON) have no `version` key.
+#[derive(Deserialize)]
+struct EnvelopeHeader {
+    version: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    kind: String,
+    payload: Box<RawValue>,
+}
+
+impl GossipMessage {
+    const KIND_RESULT: &'static str = "result";
+    const KIND_SUCCESSION: &'static str = "succession";
+
+    /// Decode a message in any supported envelope version, including the
+    /// unversioned v0 format published by builds before the envelope existed.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(DecodeError::TooLarge(bytes.len()));
+        }
+        let header: EnvelopeHeader = serde_json::from_slice(bytes)
+            .map_err(|e| DecodeError::Malformed(e.to_string()))?;
+        match header.version {
+            None | Some(0) => Self::decode_v0(bytes),
+            Some(v) if v > MAX_SUPPORTED_VERSION => Err(DecodeError::UnsupportedVersion(v)),
+            Some(_) => Self::decode_v1(bytes),
+        }
+    }
+
+    fn decode_v0(bytes: &[u8]) -> Result<Self, DecodeError> {
+        serde_json::from_slice::<SignedManifestation>(bytes)
+            .map(GossipMessage::Result)
+            .map_err(|e| DecodeError::Malformed(e.to_string()))
+    }
+
+    fn decode_v1(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let envelope: Envelope = serde_json::from_slice(bytes)
+            .map_err(|e| DecodeError::Malformed(e.to_string()))?;
+        match envelope.kind.as_str() {
+            Self::KIND_RESULT => serde_json::from_str::<SignedManifestation>(envelope.payload.get())
+                .map(GossipMessage::Result)
+                .map_err(|e| DecodeError::Malformed(e.to_string())),
+            Self::KIND_SUCCESSION => serde_json::from_str::<SignedSuccession>(envelope.payload.get())
+                .map(GossipMessage::Succession)
+                .map_err(|e| DecodeError::Malformed(e.to_string())),
+            _ => Err(DecodeError::UnknownKind(envelope.kind)),
+        }
+    }
+}
+
+/// Encoding for publishing, which only the desktop app does.
+#[cfg(any(feature = "gui", test))]
+mod encoding {
+    use super::{GossipMessage, PROTOCOL_VERSION};
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct EnvelopeRef<'a, T: Serialize> {
+        version: u32,
+        kind: &'a str,
+        payload: &'a T,
+    }
+
+    impl GossipMessage {
+        pub fn kind(&self) -> &'static str {
+            match self {
+                GossipMessage::Result(_) => Self::KIND_RESULT,
+                GossipMessage::Succession(_) => Self::KIND_SUCCESSION,
+            }
+        }
+
+        /// Encode as a current-version envelope.
+        pub fn encode(&self) -> Result<Vec<u8>, String> {
+            let kind = self.kind();
+            match self {
+                GossipMessage::Result(signed) => serde_json::to_vec(&EnvelopeRef {
+                    version: PROTOCOL_VERSION,
+                    kind,
+                    payload: signed,
+                }),
+                GossipMessage::Succession(signed) => serde_json::to_vec(&EnvelopeRef {
+                    version: PROTOCOL_VERSION,
+                    kind,
+                    payload: signed,
+                }),
+            }
+            .map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::UserIdentity;
+    use crate::network::types::ManifestationResult;
+    use proptest::prelude::*;
+
+    fn signed_result() -> SignedManifestation {
+        let payload = ManifestationResult {
+            score: 4200.0,
+            timestamp: 1_700_000_000,
+            category_scores: std::collections::HashMap::from([("14".to_string(), 7.0)]),
+            question_set: crate::questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+        };
+        SignedManifestation::new(payload, &UserIdentity::generate()).unwrap()
+    }
+
+    fn expect_result(decoded: Result<GossipMessage, DecodeError>) -> SignedManifestation {
+        match decoded {
+            Ok(GossipMessage::Result(signed)) => signed,
+            other => panic!("expected a result message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn encode_writes_current_version_and_kind() {
+        let bytes = GossipMessage::Result(signed_result()).encode().unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["version"], PROTOCOL_VERSION);
+        assert_eq!(json["kind"], "result");
+        assert!(json["payload"]["signature"].is_string());
+    }
+
+    #[test]
+    fn v1_roundtrip_preserves_signature() {
+        let bytes = GossipMessage::Result(signed_result()).encode().unwrap();
+        let decoded = expect_result(GossipMessage::decode(&bytes));
+        assert!(decoded.verify());
+        assert_eq!(decoded.payload.score, 4200.0);
+    }
+
+    #[test]
+    fn v0_bare_signed_manifestation_is_accepted() {
+        let bytes = serde_json::to_vec(&signed_result()).unwrap();
+        let decoded = expect_result(GossipMessage::decode(&bytes));
+        assert!(decoded.verify());
+    }
+
+    #[test]
+    fn future_version_is_unsupported_not_malformed() {
+        let bytes = br#"{"version":99,"kind":"result","payload":{"anything":"new"}}"#;
+        assert_eq!(GossipMessage::decode(bytes).unwrap_err(), DecodeError::UnsupportedVersion(99));
+    }
+
+    #[test]
+    fn future_version_with_unknown_layout_is_still_unsupported() {
+        let bytes = br#"{"version":2,"frames":[1,2,3]}"#;
+        assert_eq!(GossipMessage::decode(bytes).unwrap_err(), DecodeError::UnsupportedVersion(2));
+    }
+
+    #[test]
+    fn unknown_kind_in_supported_version_is_reported() {
+        let bytes = br#"{"version":1,"kind":"telemetry","payload":{}}"#;
+        assert_eq!(
+            GossipMessage::decode(bytes).unwrap_err(),
+            DecodeError::UnknownKind("telemetry".to_string())
+        );
+    }
+
+    #[test]
+    fn garbage_is_malformed() {
+        assert!(matches!(GossipMessage::decode(b"not json"), Err(DecodeError::Malformed(_))));
+        assert!(matches!(GossipMessage::decode(b"[1,2]"), Err(DecodeError::Malformed(_))));
+    }
+
+    #[test]
+    fn oversized_message_is_rejected_before_parsing() {
+        // Valid JSON, but padded past the limit
+        let mut bytes = GossipMessage::Result(signed_result()).encode().unwrap();
+        bytes.truncate(bytes.len() - 1);
+        bytes.extend(std::iter::repeat_n(b' ', MAX_MESSAGE_SIZE));
+        bytes.push(b'}');
+        assert_eq!(GossipMessage::decode(&bytes).unwrap_err(), DecodeError::TooLarge(bytes.len()));
+    }
+
+    #[test]
+    fn message_at_size_limit_is_parsed() {
+        let mut bytes = GossipMessage::Result(signed_result()).encode().unwrap();
+        bytes.truncate(bytes.len() - 1);
+        bytes.resize(MAX_MESSAGE_SIZE - 1, b' ');
+        bytes.push(b'}');
+        assert!(GossipMessage::decode(&bytes).is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn any_oversized_input_is_too_large(extra in 1usize..4096, fill in any::<u8>()) {
+            let bytes = vec![fill; MAX_MESSAGE_SIZE + extra];
+            prop_assert_eq!(GossipMessage::decode(&bytes).unwrap_err(), DecodeError::TooLarge(bytes.len()));
+        }
+
+        #[test]
+        fn arbitrary_bytes_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+            let _ = GossipMessage::decode(&bytes);
+        }
+    }
+
+    #[test]
+    fn succession_roundtrips_with_its_own_kind() {
+        let signed = SignedSuccession::new(&UserIdentity::generate(), &UserIdentity::generate(), 1_700_000_000).unwrap();
+        let bytes = GossipMessage::Succession(signed.clone()).encode().unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["kind"], "succession");
+        match GossipMessage::decode(&bytes) {
+            Ok(GossipMessage::Succession(decoded)) => assert!(decoded == signed && decoded.verify()),
+            other => panic!("expected a succession message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn v1_result_with_bad_payload_is_malformed() {
+        let bytes = br#"{"version":1,"kind":"result","payload":{"score":1}}"#;
+        assert!(matches!(GossipMessage::decode(bytes), Err(DecodeError::Malformed(_))));
+    }
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Room left for clocks running ahead of ours and for propagation when a
+/// result reaches peers near the end of its maximum message age.
+pub const CLOCK_SKEW_MARGIN_SECS: u64 = 10 * 60;
+
+/// Queued publications are released no later than this after queuing, and
+/// dropped unsent once older. A result stamped up to a day early by
+/// rounding then arrives at most 48 h minus `CLOCK_SKEW_MARGIN_SECS` old,
+/// within the maximum message age peers accept.
+pub const MAX_QUEUE_AGE_SECS: u64 = 24 * 60 * 60 - CLOCK_SKEW_MARGIN_SECS;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedPublication {
+    pub topic: String,
+    /// Encoded, padded gossip message.
+    pub message: String,
+    pub queued_at: u64,
+    pub release_at: u64,
+}
+
+/// Outgoing publications held until their release time, persisted to disk
+/// on every change so a restart neither loses nor prematurely sends them.
+#[derive(Debug, Default)]
+pub struct PublishQueue {
+    entries: Vec<QueuedPublication>,
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct PublishQueueFile {
+    entries: Vec<QueuedPublication>,
+}
+
+impl PublishQueue {
+    /// Load the queue persisted at `path`, if any.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let entries = path.as_deref()
+            .and_then(|p| std::fs::File::open(p).ok())
+            .and_then(|file| serde_json::from_reader::<_, PublishQueueFile>(file).ok())
+            .map(|f| f.entries)
+            .unwrap_or_default();
+        Self { entries, path }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The publications due at `now`, earliest first. They stay queued until
+    /// `remove`d, so one that fails to go out, or is cut short by a crash,
+    /// is released again. Entries past `MAX_QUEUE_AGE_SECS` are dropped.
+    pub fn due(&mut self, now: u64) -> Vec<QueuedPublication> {
+        let before = self.entries.len();
+        self.entries.retain(|p| now.saturating_sub(p.queued_at) <= MAX_QUEUE_AGE_SECS);
+        let expired = before - self.entries.len();
+        if expired > 0 {
+            println!("Dropped {} queued publication(s) past the maximum queue age", expired);
+            self.save();
+        }
+        let mut due: Vec<_> = self.entries.iter().filter(|p| p.release_at <= now).cloned().collect();
+        due.sort_by_key(|p| p.release_at);
+        due
+    }
+
+    /// Remove a publication once it has been published or given up on.
+    pub fn remove(&mut self, publication: &QueuedPublication) {
+        if let Some(index) = self.entries.iter().position(|p| p == publication) {
+            self.entries.remove(index);
+            self.save();
+        }
+    }
+
+    /// Drop every queued publication, e.g. when the user stops sharing.
+    /// Returns how many were dropped.
+    pub fn clear(&mut self) -> usize {
+        let dropped = self.entries.len();
+        self.entries.clear();
+        self.save();
+        dropped
+    }
+
+    fn save(&self) {
+        let Some(path) = self.path.as_deref() else { return };
+        if let Err(e) = write_queue(path, &self.entries) {
+            eprintln!("Failed to save publish queue: {}", e);
+        }
+    }
+}
+
+/// Queuing happens only for the desktop app's own publications.
+#[cfg(any(feature = "gui", test))]
+impl PublishQueue {
+    pub fn push(&mut self, publication: QueuedPublication) {
+        self.entries.push(publication);
+        self.save();
+    }
+}
+
+fn write_queue(path: &Path, entries: &[QueuedPublication]) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    serde_json::to_writer(file, &PublishQueueFile { entries: entries.to_vec() }).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::publish_timing::MAX_PUBLISH_DELAY_SECS;
+
+    const NOW: u64 = 1_700_000_123;
+
+    fn publication(queued_at: u64, release_at: u64) -> QueuedPublication {
+        QueuedPublication {
+            topic: "t".into(),
+            message: format!("{{\"release_at\":{}}}", release_at),
+            queued_at,
+            release_at,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!("{}_{}.json", name, nanos))
+    }
+
+    #[test]
+    fn released_results_stay_within_the_maximum_message_age() {
+        let max_age = crate::network::admission::AdmissionConfig::default().max_age_secs;
+        // Stamped up to a day early by rounding, then held as long as allowed
+        let oldest_arrival = 24 * 60 * 60 - 1 + MAX_QUEUE_AGE_SECS;
+        assert!(oldest_arrival + CLOCK_SKEW_MARGIN_SECS <= max_age);
+    }
+
+    #[test]
+    fn releases_only_due_entries_in_order() {
+        let mut queue = PublishQueue::default();
+        queue.push(publication(NOW, NOW + 30));
+        queue.push(publication(NOW, NOW + 10));
+        queue.push(publication(NOW, NOW + 600));
+        assert!(queue.due(NOW).is_empty());
+        let due = queue.due(NOW + 60);
+        assert_eq!(due.iter().map(|p| p.release_at).collect::<Vec<_>>(), vec![NOW + 10, NOW + 30]);
+        assert_eq!(queue.len(), 3, "due entries stay queued until removed");
+        due.iter().for_each(|p| queue.remove(p));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn expired_entries_are_dropped() {
+        let mut queue = PublishQueue::default();
+        queue.push(publication(NOW, NOW + 10));
+        assert!(queue.due(NOW + MAX_QUEUE_AGE_SECS + 1).is_empty());
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn cleared_queue_stays_empty_after_restart() {
+        let path = temp_path("publish_queue_cleared");
+        let mut queue = PublishQueue::load(Some(path.clone()));
+        queue.push(publication(NOW, NOW + 10));
+        queue.push(publication(NOW, NOW + 500));
+        assert_eq!(queue.clear(), 2);
+        drop(queue);
+
+        let mut restored = PublishQueue::load(Some(path.clone()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(restored.len(), 0);
+        assert!(restored.due(NOW + 600).is_empty());
+    }
+
+    #[test]
+    fn queue_survives_restart() {
+        let path = temp_path("publish_queue");
+        let mut queue = PublishQueue::load(Some(path.clone()));
+        queue.push(publication(NOW, NOW + 10));
+        queue.push(publication(NOW, NOW + 500));
+        drop(queue);
+
+        let mut restored = PublishQueue::load(Some(path.clone()));
+        assert_eq!(restored.len(), 2);
+        let due = restored.due(NOW + 20);
+        assert_eq!(due.len(), 1);
+        restored.remove(&due[0]);
+
+        let restored = PublishQueue::load(Some(path.clone()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(restored.len(), 1, "released entries are not sent again after a restart");
+    }
+
+    #[test]
+    fn unpublished_entries_survive_a_restart() {
+        let path = temp_path("publish_queue_unpublished");
+        let mut queue = PublishQueue::load(Some(path.clone()));
+        queue.push(publication(NOW, NOW + 10));
+        assert_eq!(queue.due(NOW + 20).len(), 1);
+        // Crash before the publish went out
+        drop(queue);
+
+        let mut restored = PublishQueue::load(Some(path.clone()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(restored.due(NOW + 20).len(), 1);
+    }
+
+    #[test]
+    fn maximum_delay_is_released_before_the_queue_drops_it() {
+        let mut queue = PublishQueue::default();
+        queue.push(publication(NOW, NOW + MAX_PUBLISH_DELAY_SECS));
+        assert_eq!(queue.due(NOW + MAX_PUBLISH_DELAY_SECS).len(), 1);
+    }
+}
@@ -0,0 +1,127 @@
+use super::publish_queue::MAX_QUEUE_AGE_SECS;
+use serde::{Deserialize, Serialize};
+
+/// Longest random delay a user can choose before a publication is released:
+/// anything longer would outlive the queue and be dropped unsent.
+pub const MAX_PUBLISH_DELAY_SECS: u64 = MAX_QUEUE_AGE_SECS;
+
+/// Padded publications are a multiple of this many bytes, so their size
+/// does not reveal how many categories, noise digits or proofs they carry.
+pub const PADDING_BLOCK: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampPrecision {
+    Hour,
+    Day,
+}
+
+impl TimestampPrecision {
+    /// Round `timestamp` down to the start of its hour or (UTC) day.
+    pub fn coarsen(self, timestamp: u64) -> u64 {
+        let unit = match self {
+            TimestampPrecision::Hour => 60 * 60,
+            TimestampPrecision::Day => 24 * 60 * 60,
+        };
+        timestamp - timestamp % unit
+    }
+}
+
+/// The last timestamp results were published under and the sequence number
+/// the next result with that timestamp gets. Persisted, so a restart within
+/// the same rounded hour or day does not reuse a number peers saw as a replay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicationSequence {
+    pub timestamp: u64,
+    pub next: u32,
+}
+
+impl PublicationSequence {
+    /// Sequence number for a result stamped `timestamp`: 0 for a new
+    /// timestamp, then counting up while it repeats.
+    pub fn claim(&mut self, timestamp: u64) -> u32 {
+        if self.timestamp != timestamp {
+            *self = Self { timestamp, next: 0 };
+        }
+        self.next += 1;
+        self.next - 1
+    }
+}
+
+/// Opt-in timing privacy for publishing: a coarse timestamp, and release
+/// through `PeerNode`'s queue after a random delay instead of immediately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishTiming {
+    pub precision: TimestampPrecision,
+    /// Upper bound of the uniformly random delay, in seconds.
+    pub max_delay_secs: u64,
+}
+
+impl PublishTiming {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_delay_secs > MAX_PUBLISH_DELAY_SECS {
+            return Err(format!(
+                "Publish delay {} s exceeds the maximum of {} s",
+                self.max_delay_secs, MAX_PUBLISH_DELAY_SECS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Pad an encoded gossip message with trailing spaces to the next multiple
+/// of `PADDING_BLOCK` (capped at `limit`). JSON parsers, including older
+/// builds, ignore trailing whitespace.
+pub fn pad(mut message: Vec<u8>, limit: usize) -> Vec<u8> {
+    let padded = message.len().div_ceil(PADDING_BLOCK).max(1) * PADDING_BLOCK;
+    message.resize(padded.min(limit).max(message.len()), b' ');
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_123;
+
+    #[test]
+    fn coarsen_rounds_down_to_hour_and_day() {
+        assert_eq!(TimestampPrecision::Hour.coarsen(NOW) % 3600, 0);
+        assert!(NOW - TimestampPrecision::Hour.coarsen(NOW) < 3600);
+        assert_eq!(TimestampPrecision::Day.coarsen(NOW) % 86_400, 0);
+        assert_eq!(TimestampPrecision::Day.coarsen(86_400 * 3), 86_400 * 3);
+    }
+
+    #[test]
+    fn sequence_counts_up_within_a_timestamp() {
+        let mut sequence = PublicationSequence::default();
+        let hour = TimestampPrecision::Hour.coarsen(NOW);
+        assert_eq!(sequence.claim(hour), 0);
+        assert_eq!(sequence.claim(hour), 1);
+        assert_eq!(sequence.claim(hour + 3600), 0);
+    }
+
+    #[test]
+    fn delay_above_maximum_is_rejected() {
+        let timing = PublishTiming { precision: TimestampPrecision::Hour, max_delay_secs: MAX_PUBLISH_DELAY_SECS + 1 };
+        assert!(timing.validate().is_err());
+        assert!(PublishTiming { max_delay_secs: 600, ..timing }.validate().is_ok());
+    }
+
+    #[test]
+    fn pad_rounds_up_to_block_size() {
+        assert_eq!(pad(vec![b'{'; 10], 16 * 1024).len(), PADDING_BLOCK);
+        assert_eq!(pad(vec![b'{'; PADDING_BLOCK], 16 * 1024).len(), PADDING_BLOCK);
+        assert_eq!(pad(vec![b'{'; PADDING_BLOCK + 1], 16 * 1024).len(), 2 * PADDING_BLOCK);
+        // Never beyond the limit, never truncated
+        assert_eq!(pad(vec![b'{'; 5000], 6000).len(), 6000);
+        assert_eq!(pad(vec![b'{'; 7000], 6000).len(), 7000);
+    }
+
+    #[test]
+    fn padded_json_still_parses() {
+        let padded = pad(br#"{"a":1}"#.to_vec(), 16 * 1024);
+        let value: serde_json::Value = serde_json::from_slice(&padded).unwrap();
+        assert_eq!(value["a"], 1);
+    }
+}
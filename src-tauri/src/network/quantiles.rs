@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+
+/// Quantiles reported for the total score and each category.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Quantiles {
+    pub p10: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+/// The largest distribution the sorted layout below is benchmarked for. The
+/// aggregator holds one score per author, so its `max_authors` must not
+/// exceed this without revisiting the layout.
+pub const MAX_SORTED_SCORES: usize = 10_000;
+
+/// A multiset of scores kept sorted as values are added and removed, so
+/// quantile reads are O(1) and rank lookups O(log n).
+///
+/// Updates binary-search their position and shift the tail: O(n) moves, but
+/// of at most `MAX_SORTED_SCORES` contiguous `f64`s, which is cheaper in
+/// practice than a pointer-based order-statistic tree at this size, and
+/// exact unlike a t-digest. Removal matters because the aggregator replaces
+/// an author's previous result rather than only appending.
+#[derive(Debug, Clone, Default)]
+pub struct ScoreDistribution {
+    sorted: Vec<f64>,
+    sum: f64,
+}
+
+impl ScoreDistribution {
+    pub fn insert(&mut self, value: f64) {
+        let at = self.sorted.partition_point(|x| x.total_cmp(&value).is_lt());
+        self.sorted.insert(at, value);
+        self.sum += value;
+    }
+
+    /// Remove one occurrence of `value`; returns `false` if it was not present.
+    pub fn remove(&mut self, value: f64) -> bool {
+        let at = self.sorted.partition_point(|x| x.total_cmp(&value).is_lt());
+        if self.sorted.get(at).is_none_or(|x| x.total_cmp(&value).is_ne()) {
+            return false;
+        }
+        self.sorted.remove(at);
+        // Reset rather than accumulate rounding error once the set drains.
+        self.sum = if self.sorted.is_empty() { 0.0 } else { self.sum - value };
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.sorted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sorted.is_empty()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (!self.is_empty()).then(|| self.sum / self.len() as f64)
+    }
+
+    /// The value at index `floor(len * q)` of the sorted scores (the last
+    /// one for `q >= 1`). `None` if empty.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let idx = (self.sorted.len() as f64 * q) as usize;
+        self.sorted.get(idx).or(self.sorted.last()).copied()
+    }
+
+    pub fn quantiles(&self) -> Option<Quantiles> {
+        Some(Quantiles {
+            p10: self.quantile(0.10)?,
+            p25: self.quantile(0.25)?,
+            p50: self.quantile(0.50)?,
+            p75: self.quantile(0.75)?,
+            p90: self.quantile(0.90)?,
+            p99: self.quantile(0.99)?,
+        })
+    }
+}
+
+/// Ranking a local score, which only the desktop app asks for.
+#[cfg(any(feature = "gui", test))]
+mod ranking {
+    use super::ScoreDistribution;
+    use super::super::views::{PercentileRank, RankConfidence};
+
+    impl ScoreDistribution {
+        /// Mid-rank percentile of `value`; `None` if empty.
+        pub fn percentile_rank(&self, value: f64) -> Option<PercentileRank> {
+            if self.is_empty() {
+                return None;
+            }
+            let below = self.sorted.partition_point(|x| x.total_cmp(&value).is_lt());
+            let not_above = self.sorted.partition_point(|x| x.total_cmp(&value).is_le());
+            let ties = not_above - below;
+            let n = self.len();
+            Some(PercentileRank {
+                percentile: (below as f64 + ties as f64 / 2.0) / n as f64 * 100.0,
+                sample_size: n,
+                confidence: RankConfidence::for_sample_size(n),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn distribution(values: &[f64]) -> ScoreDistribution {
+        let mut d = ScoreDistribution::default();
+        for &v in values {
+            d.insert(v);
+        }
+        d
+    }
+
+    /// Reference: sort a copy and index it, as `get_stats` used to.
+    fn sorted_quantile(values: &[f64], q: f64) -> Option<f64> {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let idx = (sorted.len() as f64 * q) as usize;
+        sorted.get(idx).or(sorted.last()).copied()
+    }
+
+    #[test]
+    fn empty_distribution_has_no_statistics() {
+        let d = ScoreDistribution::default();
+        assert_eq!(d.quantile(0.9), None);
+        assert_eq!(d.mean(), None);
+        assert_eq!(d.quantiles(), None);
+    }
+
+    #[test]
+    fn quantile_indexes_sorted_values() {
+        let d = distribution(&[10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0, 80.0, 90.0, 100.0]);
+        assert_eq!(d.quantile(0.9), Some(100.0));
+        assert_eq!(distribution(&[1.0, 2.0, 3.0, 4.0, 5.0]).quantile(0.9), Some(5.0));
+        assert_eq!(distribution(&[1.0, 3.0, 5.0]).quantile(0.5), Some(3.0));
+    }
+
+    #[test]
+    fn insertion_order_does_not_matter() {
+        let ordered = distribution(&[10.0, 20.0, 30.0, 40.0, 50.0]);
+        let shuffled = distribution(&[30.0, 10.0, 50.0, 40.0, 20.0]);
+        assert_eq!(ordered.quantiles(), shuffled.quantiles());
+    }
+
+    #[test]
+    fn quantile_one_uses_last_value() {
+        assert_eq!(distribution(&[1.0, 2.0, 3.0, 4.0, 5.0]).quantile(1.0), Some(5.0));
+        assert_eq!(distribution(&[42.0]).quantile(1.0), Some(42.0));
+    }
+
+    #[test]
+    fn remove_takes_out_one_occurrence() {
+        let mut d = distribution(&[5.0, 5.0, 7.0]);
+        assert!(d.remove(5.0));
+        assert_eq!(d.len(), 2);
+        assert_eq!(d.mean(), Some(6.0));
+        assert!(!d.remove(6.0), "absent value");
+        assert!(d.remove(5.0));
+        assert!(d.remove(7.0));
+        assert!(d.is_empty());
+        assert_eq!(d.mean(), None);
+    }
+
+    #[test]
+    fn reports_all_quantiles() {
+        let values: Vec<f64> = (0..100).map(f64::from).collect();
+        let q = distribution(&values).quantiles().unwrap();
+        assert_eq!((q.p10, q.p25, q.p50, q.p75, q.p90, q.p99), (10.0, 25.0, 50.0, 75.0, 90.0, 99.0));
+    }
+
+    #[test]
+    fn percentile_rank_counts_values_below_and_half_the_ties() {
+        let d = distribution(&[10.0, 20.0, 20.0, 30.0]);
+        assert_eq!(d.percentile_rank(5.0).unwrap().percentile, 0.0);
+        assert_eq!(d.percentile_rank(20.0).unwrap().percentile, 50.0);
+        assert_eq!(d.percentile_rank(25.0).unwrap().percentile, 75.0);
+        assert_eq!(d.percentile_rank(99.0).unwrap().percentile, 100.0);
+        assert_eq!(d.percentile_rank(20.0).unwrap().sample_size, 4);
+        assert_eq!(ScoreDistribution::default().percentile_rank(20.0), None);
+    }
+
+    #[test]
+    fn percentile_rank_of_median_is_fifty() {
+        let values: Vec<f64> = (1..=99).map(f64::from).collect();
+        assert_eq!(distribution(&values).percentile_rank(50.0).unwrap().percentile, 50.0);
+    }
+
+    proptest! {
+        #[test]
+        fn percentile_rank_is_within_bounds(
+            values in proptest::collection::vec(0.0..100.0f64, 1..100),
+            probe in -10.0..110.0f64,
+        ) {
+            let rank = distribution(&values).percentile_rank(probe).unwrap();
+            prop_assert!((0.0..=100.0).contains(&rank.percentile));
+            prop_assert_eq!(rank.sample_size, values.len());
+        }
+
+        #[test]
+        fn matches_sorting_after_inserts_and_removals(
+            values in proptest::collection::vec(0.0..10_000.0f64, 0..200),
+            removals in proptest::collection::vec(any::<prop::sample::Index>(), 0..50),
+            q in 0.0..=1.0f64,
+        ) {
+            let mut d = distribution(&values);
+            let mut remaining = values.clone();
+            for index in removals {
+                if remaining.is_empty() {
+                    break;
+                }
+                let value = remaining.swap_remove(index.index(remaining.len()));
+                prop_assert!(d.remove(value));
+            }
+            prop_assert_eq!(d.len(), remaining.len());
+            prop_assert_eq!(d.quantile(q), sorted_quantile(&remaining, q));
+            if let Some(mean) = d.mean() {
+                let expected = remaining.iter().sum::<f64>() / remaining.len() as f64;
+                prop_assert!((mean - expected).abs() < 1e-6);
+            }
+        }
+    }
+}
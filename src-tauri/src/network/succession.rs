@@ -0,0 +1,327 @@
+use crate::identity::UserIdentity;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::collections::{BTreeSet, HashMap};
+
+/// Domain prefix of the bytes both keys sign.
+const SIGNING_DOMAIN: &[u8] = b"manifestation/succession/v1";
+
+/// Statement that `new_key` replaces `old_key` from `timestamp` on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Succession {
+    /// Base64 public key being retired.
+    pub old_key: String,
+    /// Base64 public key taking over.
+    pub new_key: String,
+    pub timestamp: u64,
+}
+
+/// A succession signed by both keys: the old key authorises the handover
+/// and the new key proves it is held by whoever made it.
+///
+/// Like `SignedManifestation`, the exact succession bytes covered by the
+/// signatures travel with the struct (canonical JSON when signed locally),
+/// so verification never re-serialises.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "SignedSuccessionWire")]
+pub struct SignedSuccession {
+    pub succession: Succession,
+    pub old_signature: String,
+    pub new_signature: String,
+    /// Succession bytes both signatures cover, after `SIGNING_DOMAIN`.
+    signed_bytes: Vec<u8>,
+}
+
+/// Wire layout of `SignedSuccession`; `succession` is kept as raw JSON.
+#[derive(Deserialize)]
+struct SignedSuccessionWire {
+    succession: Box<RawValue>,
+    old_signature: String,
+    new_signature: String,
+}
+
+impl TryFrom<SignedSuccessionWire> for SignedSuccession {
+    type Error = String;
+
+    fn try_from(wire: SignedSuccessionWire) -> Result<Self, Self::Error> {
+        let signed_bytes = wire.succession.get().as_bytes().to_vec();
+        let succession = serde_json::from_slice(&signed_bytes).map_err(|e| e.to_string())?;
+        Ok(Self { succession, old_signature: wire.old_signature, new_signature: wire.new_signature, signed_bytes })
+    }
+}
+
+impl Serialize for SignedSuccession {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Wire<'a> {
+            succession: &'a RawValue,
+            old_signature: &'a str,
+            new_signature: &'a str,
+        }
+        let succession = serde_json::from_slice(&self.signed_bytes).map_err(serde::ser::Error::custom)?;
+        Wire { succession, old_signature: &self.old_signature, new_signature: &self.new_signature }.serialize(serializer)
+    }
+}
+
+impl SignedSuccession {
+    /// Verify both signatures over the carried bytes and check that
+    /// `succession` still matches what was signed.
+    pub fn verify(&self) -> bool {
+        let bytes = [SIGNING_DOMAIN, self.signed_bytes.as_slice()].concat();
+        self.succession.old_key != self.succession.new_key
+            && UserIdentity::verify(&bytes, &self.old_signature, &self.succession.old_key)
+            && UserIdentity::verify(&bytes, &self.new_signature, &self.succession.new_key)
+            && serde_json::from_slice::<Succession>(&self.signed_bytes).is_ok_and(|signed| signed == self.succession)
+    }
+}
+
+/// Signing a rotation, which only the desktop app does.
+#[cfg(any(feature = "gui", test))]
+mod signing {
+    use super::{SignedSuccession, Succession, SIGNING_DOMAIN};
+    use crate::identity::UserIdentity;
+    use crate::network::canonical::to_canonical_vec;
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+    impl SignedSuccession {
+        pub fn new(old: &UserIdentity, new: &UserIdentity, timestamp: u64) -> Result<Self, String> {
+            let succession = Succession {
+                old_key: old.public_key_b64(),
+                new_key: new.public_key_b64(),
+                timestamp,
+            };
+            let signed_bytes = to_canonical_vec(&succession)?;
+            let bytes = [SIGNING_DOMAIN, signed_bytes.as_slice()].concat();
+            Ok(Self {
+                old_signature: BASE64.encode(old.sign(&bytes).to_bytes()),
+                new_signature: BASE64.encode(new.sign(&bytes).to_bytes()),
+                succession,
+                signed_bytes,
+            })
+        }
+    }
+}
+
+/// Why a verified succession was not recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuccessionConflict {
+    /// This exact succession is already known.
+    Known,
+    /// The old key already has a different successor; the first one seen wins.
+    AlreadyRetired,
+    /// The new key already succeeds another key, or is an ancestor of the old one.
+    NotFresh,
+}
+
+impl std::fmt::Display for SuccessionConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SuccessionConflict::Known => write!(f, "succession already known"),
+            SuccessionConflict::AlreadyRetired => write!(f, "old key already has a successor"),
+            SuccessionConflict::NotFresh => write!(f, "new key is already part of a chain"),
+        }
+    }
+}
+
+/// Known key successions, so per-author aggregation can treat a chain of
+/// keys as one identity. Bounded: beyond `capacity` the oldest successions
+/// whose chain has no live vote are forgotten first. Forgetting one from a
+/// live chain would let its retired key vote again alongside the new one,
+/// so those go only past twice the capacity.
+#[derive(Debug)]
+pub struct SuccessionChains {
+    capacity: usize,
+    /// old key → its succession
+    next: HashMap<String, Succession>,
+    /// new key → the key it replaced
+    previous: HashMap<String, String>,
+    /// (timestamp, old key), oldest first
+    by_age: BTreeSet<(u64, String)>,
+}
+
+impl SuccessionChains {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, next: HashMap::new(), previous: HashMap::new(), by_age: BTreeSet::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.next.len()
+    }
+
+    /// Record a (verified) succession. `live` tells whether a chain head
+    /// currently holds a vote.
+    pub fn record(&mut self, succession: Succession, live: impl Fn(&str) -> bool) -> Result<(), SuccessionConflict> {
+        if let Some(existing) = self.next.get(&succession.old_key) {
+            return Err(if *existing == succession {
+                SuccessionConflict::Known
+            } else {
+                SuccessionConflict::AlreadyRetired
+            });
+        }
+        if self.previous.contains_key(&succession.new_key)
+            || self.next.contains_key(&succession.new_key)
+            || self.head(&succession.new_key) == self.head(&succession.old_key)
+        {
+            return Err(SuccessionConflict::NotFresh);
+        }
+        self.by_age.insert((succession.timestamp, succession.old_key.clone()));
+        self.previous.insert(succession.new_key.clone(), succession.old_key.clone());
+        self.next.insert(succession.old_key.clone(), succession);
+        while self.by_age.len() > self.capacity {
+            let stale = self.by_age.iter().find(|(_, old_key)| !live(&self.head(old_key))).cloned();
+            let Some(entry) = stale.or_else(|| {
+                (self.by_age.len() > 2 * self.capacity).then(|| self.by_age.first().cloned()).flatten()
+            }) else { break };
+            self.by_age.remove(&entry);
+            if let Some(evicted) = self.next.remove(&entry.1) {
+                self.previous.remove(&evicted.new_key);
+            }
+        }
+        Ok(())
+    }
+
+    /// The current key of the chain `key` belongs to.
+    pub fn head(&self, key: &str) -> String {
+        let mut current = key;
+        // Chains cannot cycle (see `record`); the bound is only a safeguard.
+        for _ in 0..=self.next.len() {
+            match self.next.get(current) {
+                Some(succession) => current = &succession.new_key,
+                None => break,
+            }
+        }
+        current.to_string()
+    }
+
+    /// When `key` was retired, if it has a successor.
+    pub fn retired_at(&self, key: &str) -> Option<u64> {
+        self.next.get(key).map(|s| s.timestamp)
+    }
+
+    pub fn snapshot(&self) -> Vec<Succession> {
+        self.by_age
+            .iter()
+            .filter_map(|(_, old_key)| self.next.get(old_key).cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::canonical::to_canonical_vec;
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn succession(old: &str, new: &str, timestamp: u64) -> Succession {
+        Succession { old_key: old.into(), new_key: new.into(), timestamp }
+    }
+
+    fn no_votes(_: &str) -> bool {
+        false
+    }
+
+    #[test]
+    fn signed_succession_verifies_with_both_keys() {
+        let old = UserIdentity::generate();
+        let new = UserIdentity::generate();
+        let signed = SignedSuccession::new(&old, &new, NOW).unwrap();
+        assert!(signed.verify());
+        let roundtrip: SignedSuccession = serde_json::from_slice(&serde_json::to_vec(&signed).unwrap()).unwrap();
+        assert!(roundtrip.verify());
+    }
+
+    #[test]
+    fn succession_is_signed_as_canonical_json_and_verified_as_received() {
+        let old = UserIdentity::generate();
+        let new = UserIdentity::generate();
+        let signed = SignedSuccession::new(&old, &new, NOW).unwrap();
+        assert_eq!(signed.signed_bytes, to_canonical_vec(&signed.succession).unwrap());
+
+        // A succession signed over its struct-order encoding, as earlier
+        // builds did, still verifies: the received bytes are what count.
+        let succession = signed.succession.clone();
+        let struct_order = serde_json::to_vec(&succession).unwrap();
+        assert_ne!(struct_order, signed.signed_bytes);
+        let bytes = [SIGNING_DOMAIN, struct_order.as_slice()].concat();
+        let wire = format!(
+            r#"{{"succession":{},"old_signature":"{}","new_signature":"{}"}}"#,
+            String::from_utf8(struct_order).unwrap(),
+            BASE64.encode(old.sign(&bytes).to_bytes()),
+            BASE64.encode(new.sign(&bytes).to_bytes()),
+        );
+        let legacy: SignedSuccession = serde_json::from_str(&wire).unwrap();
+        assert!(legacy.verify());
+        assert_eq!(legacy.succession, succession);
+    }
+
+    #[test]
+    fn succession_missing_either_signature_is_invalid() {
+        let old = UserIdentity::generate();
+        let new = UserIdentity::generate();
+        let mut signed = SignedSuccession::new(&old, &new, NOW).unwrap();
+        // Claiming someone else's key as successor needs that key's signature
+        signed.succession.new_key = UserIdentity::generate().public_key_b64();
+        assert!(!signed.verify());
+
+        let mut swapped = SignedSuccession::new(&old, &new, NOW).unwrap();
+        std::mem::swap(&mut swapped.old_signature, &mut swapped.new_signature);
+        assert!(!swapped.verify());
+    }
+
+    #[test]
+    fn head_follows_the_whole_chain() {
+        let mut chains = SuccessionChains::new(10);
+        chains.record(succession("a", "b", NOW), no_votes).unwrap();
+        chains.record(succession("b", "c", NOW + 1), no_votes).unwrap();
+        assert_eq!(chains.head("a"), "c");
+        assert_eq!(chains.head("b"), "c");
+        assert_eq!(chains.head("c"), "c");
+        assert_eq!(chains.retired_at("a"), Some(NOW));
+        assert_eq!(chains.retired_at("c"), None);
+    }
+
+    #[test]
+    fn first_successor_wins_and_cycles_are_refused() {
+        let mut chains = SuccessionChains::new(10);
+        chains.record(succession("a", "b", NOW), no_votes).unwrap();
+        assert_eq!(chains.record(succession("a", "b", NOW), no_votes), Err(SuccessionConflict::Known));
+        assert_eq!(chains.record(succession("a", "x", NOW), no_votes), Err(SuccessionConflict::AlreadyRetired));
+        assert_eq!(chains.record(succession("x", "b", NOW), no_votes), Err(SuccessionConflict::NotFresh));
+        assert_eq!(chains.record(succession("b", "a", NOW), no_votes), Err(SuccessionConflict::NotFresh));
+    }
+
+    #[test]
+    fn oldest_successions_are_forgotten_beyond_capacity() {
+        let mut chains = SuccessionChains::new(2);
+        chains.record(succession("a", "b", NOW), no_votes).unwrap();
+        chains.record(succession("c", "d", NOW + 1), no_votes).unwrap();
+        chains.record(succession("e", "f", NOW + 2), no_votes).unwrap();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains.head("a"), "a");
+        assert_eq!(chains.snapshot().len(), 2);
+    }
+
+    #[test]
+    fn successions_of_live_chains_are_kept_past_capacity() {
+        let live = |head: &str| head == "b";
+        let mut chains = SuccessionChains::new(2);
+        chains.record(succession("a", "b", NOW), live).unwrap();
+        chains.record(succession("c", "d", NOW + 1), live).unwrap();
+        chains.record(succession("e", "f", NOW + 2), live).unwrap();
+        // c → d is the oldest without a vote, so it goes instead of a → b.
+        assert_eq!(chains.retired_at("a"), Some(NOW));
+        assert_eq!(chains.retired_at("c"), None);
+
+        // Up to twice the capacity even when every chain is live.
+        let all = |_: &str| true;
+        chains.record(succession("g", "h", NOW + 3), all).unwrap();
+        chains.record(succession("i", "j", NOW + 4), all).unwrap();
+        assert_eq!(chains.len(), 4);
+        assert_eq!(chains.retired_at("a"), Some(NOW), "kept up to twice the capacity");
+        chains.record(succession("k", "l", NOW + 5), all).unwrap();
+        assert_eq!(chains.len(), 4);
+        assert_eq!(chains.retired_at("a"), None, "the oldest goes beyond it");
+    }
+}
@@ -1,100 +1,288 @@
+use super::admission::RejectionStats;
+use super::aggregate::{AggregatorConfig, MeanEstimates};
+use super::cohort::Withheld;
+use super::histogram::{CATEGORY_RANGE, TOTAL_RANGE};
+use super::privacy::LocalPrivacy;
+use super::quantiles::Quantiles;
 use crate::identity::UserIdentity;
-use libp2p::{gossipsub, identify, kad, mdns, ping, swarm::NetworkBehaviour};
-use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use crate::questions;
+use libp2p::{allow_block_list, autonat, dcutr, gossipsub, identify, kad, mdns, ping, relay, swarm::NetworkBehaviour};
+use libp2p::swarm::behaviour::toggle::Toggle;
 use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
 use tokio::sync::oneshot;
 use libp2p::Multiaddr;
-use sha2::{Sha256, Digest};
-use cid::Cid;
-use multihash::Multihash;
 use std::error::Error;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 
-/// Maximum number of scores retained per slot in the sliding window (~80 KB at capacity).
+/// Maximum number of authors retained in the network aggregate (one result each).
 pub const MAX_SCORES_CAPACITY: usize = 10_000;
 
+/// Maximum number of per-category scores in a result. The questionnaire
+/// (`src/data/questions.ts`) has 40 questions; with sub-points expanded there
+/// are 54 rated items, and the frontend publishes one score per rated item.
+pub const MAX_CATEGORIES: usize = 54;
+
+/// Length of a question-set hash (`questions::question_set_hash`), in hex characters.
+pub const QUESTION_SET_HASH_LEN: usize = 16;
+
+/// Maximum length of a category key, in bytes.
+pub const MAX_CATEGORY_KEY_LEN: usize = 32;
+
+/// Largest gossip message accepted for decoding. A result with every
+/// category filled in encodes to roughly 2 KB.
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024;
+
+/// Gossipsub topic carrying all published results.
+pub const GLOBAL_TOPIC: &str = "manifestation-global";
+
+/// Kademlia protocol of the app's own DHT, kept apart from the public IPFS one.
+pub const KAD_PROTOCOL: libp2p::StreamProtocol = libp2p::StreamProtocol::new("/manifestation/kad/1.0.0");
+
 #[derive(NetworkBehaviour)]
 pub struct AppBehaviour {
     pub gossipsub: gossipsub::Behaviour,
     pub kademlia: kad::Behaviour<kad::store::MemoryStore>,
     pub identify: identify::Behaviour,
     pub ping: ping::Behaviour,
-    pub mdns: mdns::tokio::Behaviour,
+    /// Local network discovery; off on `manifestation-relayd`.
+    pub mdns: Toggle<mdns::tokio::Behaviour>,
+    /// Peers banned for a low gossipsub score; connections to them are refused.
+    pub blocked_peers: allow_block_list::Behaviour<allow_block_list::BlockedPeers>,
+    /// Asks peers to dial back to find out whether this node is publicly reachable.
+    pub autonat: autonat::Behaviour,
+    /// Reserves slots on relays so peers can reach this node behind a NAT.
+    pub relay_client: relay::client::Behaviour,
+    /// Upgrades relayed connections to direct ones by hole punching.
+    pub dcutr: dcutr::Behaviour,
+    /// Circuit relay v2 server; only enabled on `manifestation-relayd`.
+    pub relay_server: Toggle<relay::Behaviour>,
+}
+
+/// Reachability of this node as detected by AutoNAT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NatStatus {
+    /// Not enough probes yet.
+    #[default]
+    Unknown,
+    /// Peers could dial back to one of our addresses.
+    Public,
+    /// Dial-backs failed; peers need a relay to reach us.
+    Private,
+}
+
+impl From<&autonat::NatStatus> for NatStatus {
+    fn from(status: &autonat::NatStatus) -> Self {
+        match status {
+            autonat::NatStatus::Public(_) => NatStatus::Public,
+            autonat::NatStatus::Private => NatStatus::Private,
+            autonat::NatStatus::Unknown => NatStatus::Unknown,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CategoryStats {
     pub avg: f64,
     pub p90: f64,
+    pub quantiles: Quantiles,
 }
 
 /// A `ManifestationResult` signed with the author's Ed25519 key.
 /// The gossipsub layer uses `MessageAuthenticity::Anonymous` — this struct
 /// provides application-level authentication without leaking the node PeerId.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// The exact payload bytes covered by the signature travel with the struct:
+/// on the wire `payload` is emitted verbatim from `signed_bytes`, and on
+/// receipt it is captured as-is, so verification never re-serialises.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "SignedManifestationWire")]
 pub struct SignedManifestation {
     pub payload: ManifestationResult,
     /// Base64-encoded Ed25519 public key of the author
     pub public_key: String,
-    /// Base64-encoded detached Ed25519 signature over the canonical JSON of `payload`
+    /// Base64-encoded detached Ed25519 signature over `signed_bytes`
     pub signature: String,
+    /// Payload bytes the signature covers (canonical JSON when signed locally)
+    signed_bytes: Vec<u8>,
 }
 
-impl SignedManifestation {
-    pub fn new(payload: ManifestationResult, identity: &UserIdentity) -> Result<Self, String> {
-        let msg_bytes = serde_json::to_vec(&payload).map_err(|e| e.to_string())?;
-        let sig = identity.sign(&msg_bytes);
-        let sig_b64 = BASE64.encode(sig.to_bytes());
-        let pk_b64 = identity.public_key_b64();
-        Ok(Self { payload, public_key: pk_b64, signature: sig_b64 })
+/// Wire layout of `SignedManifestation`; `payload` is kept as raw JSON.
+#[derive(Deserialize)]
+struct SignedManifestationWire {
+    payload: Box<RawValue>,
+    public_key: String,
+    signature: String,
+}
+
+impl TryFrom<SignedManifestationWire> for SignedManifestation {
+    type Error = String;
+
+    fn try_from(wire: SignedManifestationWire) -> Result<Self, Self::Error> {
+        let signed_bytes = wire.payload.get().as_bytes().to_vec();
+        let payload = serde_json::from_slice(&signed_bytes).map_err(|e| e.to_string())?;
+        Ok(Self { payload, public_key: wire.public_key, signature: wire.signature, signed_bytes })
+    }
+}
+
+impl Serialize for SignedManifestation {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Wire<'a> {
+            payload: &'a RawValue,
+            public_key: &'a str,
+            signature: &'a str,
+        }
+        let payload = serde_json::from_slice(&self.signed_bytes).map_err(serde::ser::Error::custom)?;
+        Wire { payload, public_key: &self.public_key, signature: &self.signature }.serialize(serializer)
     }
+}
 
+impl SignedManifestation {
+    /// Verify the signature over the carried bytes and check that `payload`
+    /// still matches what was signed.
     pub fn verify(&self) -> bool {
-        match serde_json::to_vec(&self.payload) {
-            Ok(msg_bytes) => UserIdentity::verify(&msg_bytes, &self.signature, &self.public_key),
+        if !UserIdentity::verify(&self.signed_bytes, &self.signature, &self.public_key) {
+            return false;
+        }
+        match serde_json::from_slice::<ManifestationResult>(&self.signed_bytes) {
+            Ok(signed_payload) => signed_payload == self.payload,
             Err(e) => {
-                eprintln!("Failed to serialize payload: {}", e);
+                eprintln!("Failed to decode signed payload: {}", e);
                 false
             },
         }
     }
 }
 
+/// Signing and content addressing of the user's own results, which only
+/// the desktop app publishes.
+#[cfg(any(feature = "gui", test))]
+mod signing {
+    use super::{ManifestationResult, SignedManifestation};
+    use crate::identity::UserIdentity;
+    use crate::network::canonical::to_canonical_vec;
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+    use cid::Cid;
+    use multihash::Multihash;
+    use sha2::{Digest, Sha256};
+
+    impl SignedManifestation {
+        pub fn new(payload: ManifestationResult, identity: &UserIdentity) -> Result<Self, String> {
+            let signed_bytes = payload.canonical_bytes()?;
+            let sig = identity.sign(&signed_bytes);
+            let sig_b64 = BASE64.encode(sig.to_bytes());
+            let pk_b64 = identity.public_key_b64();
+            Ok(Self { payload, public_key: pk_b64, signature: sig_b64, signed_bytes })
+        }
+    }
+
+    impl ManifestationResult {
+        /// Canonical JSON encoding used for signing, verification and CIDs.
+        pub fn canonical_bytes(&self) -> Result<Vec<u8>, String> {
+            to_canonical_vec(self)
+        }
+
+        pub fn get_cid(&self) -> Result<String, String> {
+            let json_bytes = self.canonical_bytes()?;
+
+            let digest = Sha256::digest(&json_bytes);
+            let hash = Multihash::<64>::wrap(0x12, &digest)
+                .map_err(|e| e.to_string())?;
+
+            // Use DAG-JSON (0x0129)
+            let cid = Cid::new_v1(0x0129, hash);
+            Ok(cid.to_string())
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkStatUpdate {
     pub peer_count: usize,
     pub connected_peers: Vec<String>,
+    /// Number of identities contributing to the aggregate (one vote each).
     pub total_manifestations: usize,
     pub avg_score: Option<f64>,
     pub percentile_90: Option<f64>,
+    /// p10–p99 of the total score; `None` until the minimum cohort size is reached.
+    pub score_quantiles: Option<Quantiles>,
+    /// Set when the total-score statistics above are withheld.
+    pub withheld: Option<Withheld>,
     pub category_stats: std::collections::HashMap<String, CategoryStats>,
+    /// Categories with too few results to report.
+    pub withheld_categories: std::collections::HashMap<String, Withheld>,
+    /// Aggregated results carrying local differential-privacy noise. They are
+    /// left out of the exact statistics above and only count in `estimates`.
+    pub noisy_results: usize,
+    /// Unbiased means over exact and noised results, with the noise's standard error.
+    pub estimates: MeanEstimates,
+    /// Own publications waiting in the delay queue.
+    pub queued_publications: usize,
+    /// Key successions the aggregate follows.
+    pub known_successions: usize,
+    /// Whether AutoNAT found this node publicly reachable.
+    pub nat_status: NatStatus,
+    /// Configured relays currently holding a reservation for this node.
+    pub relay_reservations: usize,
+    /// Peers in the Kademlia routing table.
+    pub routing_table_size: usize,
+    /// Peers in the persisted address book.
+    pub known_peers: usize,
     pub bandwidth_in: u64,
     pub bandwidth_out: u64,
+    /// Gossip messages ignored because they use a newer protocol version or unknown kind.
+    pub unsupported_messages: u64,
+    /// Question-set hash the statistics above are computed for.
+    pub question_set: String,
+    /// Valid results from peers on a different question set, neither aggregated nor forwarded.
+    pub other_question_set_results: u64,
+    /// Valid results signed by unlinkable derived keys, each key counted as its own author.
+    pub unlinkable_results: u64,
+    /// Received results dropped before aggregation, by reason.
+    pub rejections: RejectionStats,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ManifestationResult {
     pub score: f64,
     pub timestamp: u64,
     pub category_scores: std::collections::HashMap<String, f64>,
+    /// Hash of the question set the scores were computed against. Empty for
+    /// results from builds that predate it; omitted from the encoding then,
+    /// so their signatures and CIDs are unchanged.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub question_set: String,
+    /// Set when the scores carry local differential-privacy noise; they may
+    /// then fall outside their usual ranges. Omitted for exact results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub privacy: Option<LocalPrivacy>,
+    /// Set when signed by a key derived for unlinkable publishing, so peers
+    /// can report how many such results they aggregate. Omitted for
+    /// root-key results.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unlinkable: bool,
+    /// Orders an author's results that share a timestamp, as rounded
+    /// timestamps do: 0 for the first, counting up. Omitted when 0, so
+    /// such results encode as before.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub sequence: u32,
 }
 
-impl ManifestationResult {
-    pub fn get_cid(&self) -> Result<String, String> {
-        let json_bytes = serde_json::to_vec(self).map_err(|e| e.to_string())?;
-
-        let digest = Sha256::digest(&json_bytes);
-        let hash = Multihash::<64>::wrap(0x12, &digest)
-            .map_err(|e| e.to_string())?;
-
-        // Use DAG-JSON (0x0129)
-        let cid = Cid::new_v1(0x0129, hash);
-        Ok(cid.to_string())
-    }
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
 
+impl ManifestationResult {
     pub fn validate(&self) -> Result<(), String> {
-        // 1. Score validation — valid range is 0–10,000 (max from question weights)
-        if self.score < 0.0 || self.score > 10_000.0 {
-            return Err(format!("Score {} is out of range (0.0 - 10,000.0)", self.score));
+        // 1. Score validation — valid range is 0–10,000 (max from question weights),
+        //    widened by the most the declared privacy noise can move it.
+        //    NaN fails every comparison, so finiteness is checked explicitly.
+        let (total_min, total_max) = self.noised_range(TOTAL_RANGE, |(total, _)| total)?;
+        if !self.score.is_finite() || self.score < total_min || self.score > total_max {
+            return Err(format!("Score {} is out of range ({:.1} - {:.1})", self.score, total_min, total_max));
         }
 
         // 2. Timestamp validation (not in future)
@@ -108,20 +296,75 @@ impl ManifestationResult {
             return Err(format!("Timestamp {} is in the future", self.timestamp));
         }
 
-        // 3. Category scores validation
+        // 3. Question set: empty (pre-versioning builds) or a well-formed hash
+        if !self.question_set.is_empty()
+            && (self.question_set.len() != QUESTION_SET_HASH_LEN
+                || !self.question_set.bytes().all(|b| b.is_ascii_hexdigit()))
+        {
+            return Err(format!("Question set '{}' is not a valid hash", self.question_set));
+        }
+
+        // 4. Category scores validation
+        if self.category_scores.len() > MAX_CATEGORIES {
+            return Err(format!(
+                "Too many categories: {} (maximum {})",
+                self.category_scores.len(),
+                MAX_CATEGORIES
+            ));
+        }
+        let local = self.uses_local_question_set();
+        let (category_min, category_max) = self.noised_range(CATEGORY_RANGE, |(_, category)| category)?;
         for (category, &score) in &self.category_scores {
-            // 4. Privacy validation (PII check in keys) — must run before range check
-            if category.contains('@') || category.contains("http") {
-                return Err(format!("Category '{}' contains potential PII or invalid characters", category));
+            // 5. Keys must be question ids. For our own question set that is
+            //    checked exactly; other versions only get the shape check.
+            validate_category_key(category)?;
+            if local && !questions::is_known_category(category) {
+                return Err(format!("Category '{}' is not a question in this question set", category));
             }
-
-            if score < 0.0 || score > 10.0 {
-                return Err(format!("Category '{}' score {} is out of range (0.0 - 10.0)", category, score));
+            if !score.is_finite() || !(category_min..=category_max).contains(&score) {
+                return Err(format!(
+                    "Category '{}' score {} is out of range ({:.1} - {:.1})",
+                    category, score, category_min, category_max
+                ));
             }
         }
 
         Ok(())
     }
+
+    /// Bounds a value in `(min, max)` may take once the declared noise is
+    /// applied, with `share` picking its part of the budget.
+    fn noised_range(
+        &self,
+        (min, max): (f64, f64),
+        share: impl Fn((LocalPrivacy, LocalPrivacy)) -> LocalPrivacy,
+    ) -> Result<(f64, f64), String> {
+        let Some(privacy) = self.privacy else { return Ok((min, max)) };
+        privacy.validate()?;
+        let overshoot = share(privacy.budgets(self.category_scores.len())).max_overshoot(max - min);
+        Ok((min - overshoot, max + overshoot))
+    }
+
+    /// Whether this result was scored against this build's questionnaire and
+    /// can be aggregated with local results.
+    pub fn uses_local_question_set(&self) -> bool {
+        self.question_set == questions::question_set_hash()
+    }
+}
+
+/// Category keys are question ids (`"2"`, `"19e"`): short ASCII identifiers.
+fn validate_category_key(category: &str) -> Result<(), String> {
+    if category.is_empty() || category.len() > MAX_CATEGORY_KEY_LEN {
+        return Err(format!(
+            "Category key length {} is out of range (1 - {})",
+            category.len(),
+            MAX_CATEGORY_KEY_LEN
+        ));
+    }
+    if !category.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-') {
+        return Err(format!("Category '{}' contains potential PII or invalid characters", category));
+    }
+    Ok(())
 }
 
 pub enum Command {
@@ -129,27 +372,101 @@ pub enum Command {
         addr: Multiaddr,
         sender: oneshot::Sender<Result<(), Box<dyn Error + Send>>>,
     },
-    Publish {
-        topic: String,
-        message: Vec<u8>,
-        sender: oneshot::Sender<Result<(), Box<dyn Error + Send>>>,
-    },
-    GetPeers {
-        sender: oneshot::Sender<Vec<String>>,
-    },
+    /// Requests only the desktop app makes.
+    #[cfg(feature = "gui")]
+    App(super::app_command::AppCommand),
     Shutdown,
 }
 
-/// Serialisable snapshot used to persist and restore received score windows across sessions.
+/// Serialisable snapshot used to persist and restore the per-author aggregate across sessions.
 #[derive(Serialize, Deserialize, Default)]
 pub struct NetworkScoresCache {
-    pub scores: Vec<f64>,
-    pub category_scores: std::collections::HashMap<String, Vec<f64>>,
+    /// Latest result per author public key (base64).
+    #[serde(default)]
+    pub latest_by_author: std::collections::HashMap<String, ManifestationResult>,
+    /// Key successions the aggregate follows.
+    #[serde(default)]
+    pub successions: Vec<super::succession::Succession>,
+}
+
+/// Tunables for `PeerNode`; `Default` gives the production values.
+#[derive(Debug, Clone)]
+pub struct NodeConfig {
+    pub aggregator: AggregatorConfig,
+    pub transports: TransportConfig,
+    /// Circuit relay v2 servers to reserve a slot on, each ending in `/p2p/<peer id>`.
+    pub relays: Vec<Multiaddr>,
+    /// Serve as a circuit relay for other peers.
+    pub relay_server: bool,
+    /// Addresses this node is publicly reachable at, advertised to peers
+    /// and in relay reservations. Others are confirmed by AutoNAT.
+    pub external_addresses: Vec<Multiaddr>,
+    /// Discover peers on the local network with mDNS.
+    pub mdns: bool,
+    /// Whether the user still shares results. Checked before each queued
+    /// publication is released; while unset the queue is dropped instead.
+    pub sharing_enabled: Arc<AtomicBool>,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            aggregator: AggregatorConfig::default(),
+            transports: TransportConfig::default(),
+            relays: vec![],
+            relay_server: false,
+            external_addresses: vec![],
+            mdns: true,
+            sharing_enabled: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+/// Transports the node listens and dials on. TCP is always on; QUIC is
+/// added alongside it and preferred when a peer advertises both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransportConfig {
+    pub quic: bool,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self { quic: true }
+    }
+}
+
+#[cfg(any(feature = "gui", test))]
+impl TransportConfig {
+    /// Addresses to listen on for the given IP (`0.0.0.0` for all interfaces).
+    pub fn listen_addrs(&self, ip: std::net::Ipv4Addr) -> Vec<Multiaddr> {
+        let mut addrs = vec![format!("/ip4/{}/tcp/0", ip)];
+        if self.quic {
+            addrs.push(format!("/ip4/{}/udp/0/quic-v1", ip));
+        }
+        addrs.into_iter().filter_map(|a| a.parse().ok()).collect()
+    }
+}
+
+/// A peer banned for misbehaviour, as persisted in `banned_peers.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BannedPeer {
+    pub peer_id: String,
+    /// Unix timestamp (seconds) of the ban.
+    pub banned_at: u64,
+    /// Gossipsub score that triggered the ban.
+    pub score: f64,
+}
+
+/// Serialisable ban list, persisted so bans survive restarts.
+#[derive(Serialize, Deserialize, Default)]
+pub struct BannedPeersCache {
+    pub peers: Vec<BannedPeer>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::network::privacy;
 
     fn valid_result() -> ManifestationResult {
         let now = std::time::SystemTime::now()
@@ -159,10 +476,21 @@ mod tests {
         ManifestationResult {
             score: 5_000.0,
             timestamp: now,
-            category_scores: std::collections::HashMap::from([("health".to_string(), 5.0)]),
+            category_scores: std::collections::HashMap::from([("3".to_string(), 5.0)]),
+            question_set: questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
         }
     }
 
+    /// A well-formed hash of some other questionnaire version.
+    const OTHER_QUESTION_SET: &str = "0123456789abcdef";
+
+    fn other_version_result() -> ManifestationResult {
+        ManifestationResult { question_set: OTHER_QUESTION_SET.to_string(), ..valid_result() }
+    }
+
     // ── Score boundary (kills line-96 mutants: `<→<=` and `>→>=`) ──────────
 
     #[test]
@@ -233,7 +561,11 @@ mod tests {
         let r = ManifestationResult {
             score: 5_000.0,
             timestamp: now,
-            category_scores: std::collections::HashMap::from([("cat".to_string(), 0.0)]),
+            question_set: questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+            category_scores: std::collections::HashMap::from([("2".to_string(), 0.0)]),
         };
         assert!(r.validate().is_ok(), "category score 0.0 should be valid");
     }
@@ -248,7 +580,11 @@ mod tests {
         let r = ManifestationResult {
             score: 5_000.0,
             timestamp: now,
-            category_scores: std::collections::HashMap::from([("cat".to_string(), 10.0)]),
+            question_set: questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+            category_scores: std::collections::HashMap::from([("2".to_string(), 10.0)]),
         };
         assert!(r.validate().is_ok(), "category score 10.0 should be valid");
     }
@@ -262,7 +598,11 @@ mod tests {
         let r = ManifestationResult {
             score: 5_000.0,
             timestamp: now,
-            category_scores: std::collections::HashMap::from([("cat".to_string(), -0.1)]),
+            question_set: questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+            category_scores: std::collections::HashMap::from([("2".to_string(), -0.1)]),
         };
         assert!(r.validate().is_err());
     }
@@ -276,7 +616,11 @@ mod tests {
         let r = ManifestationResult {
             score: 5_000.0,
             timestamp: now,
-            category_scores: std::collections::HashMap::from([("cat".to_string(), 10.1)]),
+            question_set: questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+            category_scores: std::collections::HashMap::from([("2".to_string(), 10.1)]),
         };
         assert!(r.validate().is_err());
     }
@@ -291,6 +635,10 @@ mod tests {
             score: 5_000.0,
             timestamp: now,
             category_scores: std::collections::HashMap::from([("user@example.com".to_string(), 5.0)]),
+            question_set: questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
         };
         assert!(r.validate().is_err());
     }
@@ -305,6 +653,10 @@ mod tests {
             score: 5_000.0,
             timestamp: now,
             category_scores: std::collections::HashMap::from([("http://evil.com".to_string(), 5.0)]),
+            question_set: questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
         };
         assert!(r.validate().is_err());
     }
@@ -313,4 +665,404 @@ mod tests {
     fn validate_fully_valid_result_passes() {
         assert!(valid_result().validate().is_ok());
     }
+
+    // ── Non-finite values and payload bounds ────────────────────────────────
+
+    #[test]
+    fn validate_rejects_non_finite_score() {
+        for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut r = valid_result();
+            r.score = bad;
+            assert!(r.validate().is_err(), "score {} must be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_non_finite_category_score() {
+        for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut r = valid_result();
+            r.category_scores.insert("2".to_string(), bad);
+            assert!(r.validate().is_err(), "category score {} must be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn validate_category_count_boundary() {
+        // Only reachable with another question set: ours has exactly MAX_CATEGORIES keys
+        let mut r = other_version_result();
+        r.category_scores = (0..MAX_CATEGORIES).map(|i| (format!("q{i}"), 5.0)).collect();
+        assert!(r.validate().is_ok(), "{} categories should be valid", MAX_CATEGORIES);
+        r.category_scores.insert("one_more".to_string(), 5.0);
+        assert!(r.validate().is_err());
+    }
+
+    #[test]
+    fn validate_category_key_length_boundary() {
+        let mut r = other_version_result();
+        r.category_scores = std::collections::HashMap::from([("k".repeat(MAX_CATEGORY_KEY_LEN), 5.0)]);
+        assert!(r.validate().is_ok());
+        r.category_scores = std::collections::HashMap::from([("k".repeat(MAX_CATEGORY_KEY_LEN + 1), 5.0)]);
+        assert!(r.validate().is_err());
+        r.category_scores = std::collections::HashMap::from([(String::new(), 5.0)]);
+        assert!(r.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_disallowed_key_characters() {
+        for key in ["has space", "dot.ted", "555 123 4567", "名前", "semi;colon", "new\nline"] {
+            let mut r = valid_result();
+            r.category_scores = std::collections::HashMap::from([(key.to_string(), 5.0)]);
+            let err = r.validate().unwrap_err();
+            assert!(err.contains("invalid characters"), "key {:?}: {}", key, err);
+        }
+    }
+
+    #[test]
+    fn validate_accepts_question_id_keys() {
+        let mut r = valid_result();
+        r.category_scores = ["1a", "2", "19e", "23h", "40"]
+            .iter()
+            .map(|k| (k.to_string(), 7.0))
+            .collect();
+        assert!(r.validate().is_ok());
+    }
+
+    // ── Category whitelist and question set ─────────────────────────────────
+
+    #[test]
+    fn validate_accepts_every_rated_question() {
+        let mut r = valid_result();
+        r.category_scores = questions::rated_questions().map(|q| (q.id.to_string(), 5.0)).collect();
+        assert!(r.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_keys_outside_the_question_set() {
+        // Identifier-shaped, so only the whitelist stops them
+        for key in ["5551234567", "alice", "focus", "1", "41", "19z"] {
+            let mut r = valid_result();
+            r.category_scores = std::collections::HashMap::from([(key.to_string(), 5.0)]);
+            let err = r.validate().unwrap_err();
+            assert!(err.contains("not a question"), "key {:?}: {}", key, err);
+        }
+    }
+
+    #[test]
+    fn validate_other_question_set_gets_shape_check_only() {
+        let mut r = other_version_result();
+        r.category_scores = std::collections::HashMap::from([("41".to_string(), 5.0)]);
+        assert!(r.validate().is_ok());
+        assert!(!r.uses_local_question_set());
+        r.category_scores = std::collections::HashMap::from([("a@b".to_string(), 5.0)]);
+        assert!(r.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_malformed_question_set() {
+        for bad in ["abc", "0123456789abcdeg", "0123456789abcdef0", "not a hash at all"] {
+            let mut r = valid_result();
+            r.question_set = bad.to_string();
+            assert!(r.validate().is_err(), "question set {:?} must be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn result_without_question_set_keeps_legacy_encoding() {
+        let json = r#"{"category_scores":{"2":5.0},"score":50.0,"timestamp":1700000000}"#;
+        let r: ManifestationResult = serde_json::from_str(json).unwrap();
+        assert_eq!(r.question_set, "");
+        assert!(!r.uses_local_question_set());
+        assert_eq!(r.canonical_bytes().unwrap(), json.as_bytes());
+    }
+
+    #[test]
+    fn question_set_is_covered_by_the_signature() {
+        let identity = UserIdentity::generate();
+        let signed = SignedManifestation::new(valid_result(), &identity).unwrap();
+        let wire = String::from_utf8(serde_json::to_vec(&signed).unwrap()).unwrap();
+        let tampered = wire.replacen(questions::question_set_hash(), OTHER_QUESTION_SET, 1);
+        let received: SignedManifestation = serde_json::from_str(&tampered).unwrap();
+        assert!(!received.verify());
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn result_with(score: f64, categories: Vec<(String, f64)>) -> ManifestationResult {
+            ManifestationResult {
+                score,
+                category_scores: categories.into_iter().collect(),
+                ..valid_result()
+            }
+        }
+
+        fn any_f64() -> impl Strategy<Value = f64> {
+            prop_oneof![
+                any::<f64>(),
+                Just(f64::NAN),
+                Just(f64::INFINITY),
+                Just(f64::NEG_INFINITY),
+                -1.0e4..2.0e4f64,
+            ]
+        }
+
+        proptest! {
+            #[test]
+            fn score_is_valid_iff_finite_and_in_range(score in any_f64()) {
+                let ok = result_with(score, vec![]).validate().is_ok();
+                prop_assert_eq!(ok, score.is_finite() && (0.0..=10_000.0).contains(&score));
+            }
+
+            #[test]
+            fn category_score_is_valid_iff_finite_and_in_range(score in any_f64()) {
+                let ok = result_with(5_000.0, vec![("2".to_string(), score)]).validate().is_ok();
+                prop_assert_eq!(ok, score.is_finite() && (0.0..=10.0).contains(&score));
+            }
+
+            #[test]
+            fn key_is_valid_iff_a_rated_question(key in "\\PC{0,40}|[0-9]{1,2}[a-z]?") {
+                let ok = result_with(5_000.0, vec![(key.clone(), 5.0)]).validate().is_ok();
+                prop_assert_eq!(ok, questions::is_known_category(&key));
+            }
+
+            #[test]
+            fn other_set_key_is_valid_iff_short_and_identifier_like(key in "\\PC{0,40}") {
+                let mut r = result_with(5_000.0, vec![(key.clone(), 5.0)]);
+                r.question_set = OTHER_QUESTION_SET.to_string();
+                let expected = !key.is_empty()
+                    && key.len() <= MAX_CATEGORY_KEY_LEN
+                    && key.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+                prop_assert_eq!(r.validate().is_ok(), expected);
+            }
+
+            #[test]
+            fn too_many_categories_always_fail(extra in 1usize..20) {
+                let categories = (0..MAX_CATEGORIES + extra).map(|i| (format!("q{i}"), 5.0)).collect();
+                prop_assert!(result_with(5_000.0, categories).validate().is_err());
+            }
+        }
+    }
+
+    // ── Local differential privacy ──────────────────────────────────────────
+
+    fn noised(mechanism: privacy::NoiseMechanism) -> ManifestationResult {
+        let mut r = valid_result();
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(42);
+        LocalPrivacy { mechanism, epsilon: 1.0 }.perturb(&mut r, &mut rng);
+        r
+    }
+
+    #[test]
+    fn validate_accepts_noised_scores_outside_exact_range() {
+        for mechanism in [privacy::NoiseMechanism::Laplace, privacy::NoiseMechanism::RandomizedResponse] {
+            let mut r = noised(mechanism);
+            r.category_scores.insert("3".to_string(), -4.0);
+            r.score = 11_000.0;
+            assert!(r.validate().is_ok(), "{:?}", mechanism);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_exact_scores_outside_range() {
+        // Without the privacy field the same values are out of range
+        let mut r = noised(privacy::NoiseMechanism::Laplace);
+        r.score = 11_000.0;
+        r.privacy = None;
+        assert!(r.validate().is_err());
+        let mut r = noised(privacy::NoiseMechanism::Laplace);
+        r.category_scores.insert("3".to_string(), -4.0);
+        r.privacy = None;
+        assert!(r.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_noise_beyond_declared_mechanism() {
+        let mut r = noised(privacy::NoiseMechanism::RandomizedResponse);
+        r.score = 1e9;
+        assert!(r.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_invalid_epsilon() {
+        let mut r = noised(privacy::NoiseMechanism::Laplace);
+        r.privacy = Some(LocalPrivacy { mechanism: privacy::NoiseMechanism::Laplace, epsilon: 1000.0 });
+        assert!(r.validate().unwrap_err().contains("epsilon"));
+    }
+
+    #[test]
+    fn noised_result_signature_roundtrips() {
+        let signed = SignedManifestation::new(noised(privacy::NoiseMechanism::Laplace), &UserIdentity::generate()).unwrap();
+        let received: SignedManifestation = serde_json::from_slice(&serde_json::to_vec(&signed).unwrap()).unwrap();
+        assert!(received.verify());
+        assert!(received.payload.privacy.is_some());
+    }
+
+    #[test]
+    fn exact_result_encoding_omits_privacy() {
+        let json = String::from_utf8(valid_result().canonical_bytes().unwrap()).unwrap();
+        assert!(!json.contains("privacy"));
+    }
+
+    // ── Unlinkable publishing ───────────────────────────────────────────────
+
+    #[test]
+    fn unlinkable_result_verifies_under_derived_key() {
+        use crate::network::unlinkable::{signing_identity, KeyRotation, UnlinkableSettings};
+        let root = UserIdentity::generate();
+        let mut r = valid_result();
+        let settings = UnlinkableSettings { rotation: KeyRotation::PerEpoch };
+        let derived = signing_identity(&root, settings, r.timestamp);
+        r.unlinkable = true;
+        assert!(r.validate().is_ok());
+        let signed = SignedManifestation::new(r, &derived).unwrap();
+        assert!(signed.verify());
+        assert_ne!(signed.public_key, root.public_key_b64());
+    }
+
+    #[test]
+    fn root_key_result_encoding_omits_unlinkable_marker() {
+        let json = String::from_utf8(valid_result().canonical_bytes().unwrap()).unwrap();
+        assert!(!json.contains("unlinkable"));
+    }
+
+    // ── Canonical encoding ────────────────────────────────────────────────────
+
+    /// 40 categories inserted in the given order; `HashMap` then scrambles
+    /// iteration order with its own per-instance random seed.
+    fn forty_category_result(reverse: bool) -> ManifestationResult {
+        let mut keys: Vec<usize> = (0..40).collect();
+        if reverse {
+            keys.reverse();
+        }
+        ManifestationResult {
+            score: 6543.25,
+            timestamp: 1_700_000_000,
+            category_scores: keys
+                .into_iter()
+                .map(|i| (format!("category_{i:02}"), (i % 11) as f64 * 0.9))
+                .collect(),
+            question_set: questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+        }
+    }
+
+    #[test]
+    fn test_canonical_bytes_ignore_map_insertion_order() {
+        let a = forty_category_result(false);
+        let b = forty_category_result(true);
+        assert_eq!(a.canonical_bytes().unwrap(), b.canonical_bytes().unwrap());
+        assert_eq!(a.get_cid().unwrap(), b.get_cid().unwrap());
+    }
+
+    #[test]
+    fn test_signed_manifestation_signs_canonical_bytes() {
+        let identity = UserIdentity::generate();
+        let payload = forty_category_result(false);
+        let signed = SignedManifestation::new(payload.clone(), &identity).unwrap();
+        assert_eq!(signed.signed_bytes.as_slice(), payload.canonical_bytes().unwrap().as_slice());
+    }
+
+    #[test]
+    fn test_signed_manifestation_wire_roundtrip_verifies() {
+        let identity = UserIdentity::generate();
+        let signed = SignedManifestation::new(forty_category_result(false), &identity).unwrap();
+        let wire = serde_json::to_vec(&signed).unwrap();
+
+        let received: SignedManifestation = serde_json::from_slice(&wire).unwrap();
+        assert!(received.verify());
+        // Forwarding re-emits the original bytes, so a second hop verifies too.
+        let forwarded = serde_json::to_vec(&received).unwrap();
+        assert_eq!(wire, forwarded);
+        let second_hop: SignedManifestation = serde_json::from_slice(&forwarded).unwrap();
+        assert!(second_hop.verify());
+    }
+
+    #[test]
+    fn test_verify_uses_received_bytes_not_reserialization() {
+        // A peer that signed non-canonical JSON (e.g. an older build) is still
+        // verifiable because the exact bytes travel with the message.
+        let identity = UserIdentity::generate();
+        let payload_json = r#"{"timestamp":1700000000,"score":50.0,"category_scores":{"b":2.0,"a":1.0}}"#;
+        let sig = identity.sign(payload_json.as_bytes());
+        let wire = format!(
+            r#"{{"payload":{},"public_key":"{}","signature":"{}"}}"#,
+            payload_json,
+            identity.public_key_b64(),
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, sig.to_bytes()),
+        );
+        let received: SignedManifestation = serde_json::from_str(&wire).unwrap();
+        assert!(received.verify());
+        assert_eq!(received.signed_bytes.as_slice(), payload_json.as_bytes());
+    }
+
+    #[test]
+    fn test_verify_rejects_modified_wire_bytes() {
+        let identity = UserIdentity::generate();
+        let signed = SignedManifestation::new(forty_category_result(false), &identity).unwrap();
+        let wire = String::from_utf8(serde_json::to_vec(&signed).unwrap()).unwrap();
+        let tampered = wire.replacen("6543.25", "9543.25", 1);
+        let received: SignedManifestation = serde_json::from_str(&tampered).unwrap();
+        assert!(!received.verify());
+    }
+
+    /// Child half of `test_canonical_encoding_is_stable_across_processes`.
+    /// Only emits output when spawned by the parent test.
+    #[test]
+    #[ignore]
+    fn canonical_child_emit_signed() {
+        if std::env::var_os("MANIFESTATION_CANONICAL_CHILD").is_none() {
+            return;
+        }
+        let identity = UserIdentity::generate();
+        let signed = SignedManifestation::new(forty_category_result(true), &identity).unwrap();
+        println!("SIGNED:{}", String::from_utf8(serde_json::to_vec(&signed).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_canonical_encoding_is_stable_across_processes() {
+        // A separate process gets different `HashMap` seeds, exactly like a
+        // remote peer. Its signed result must verify here and hash to the
+        // same CID as the locally built payload.
+        let exe = std::env::current_exe().unwrap();
+        let output = std::process::Command::new(exe)
+            .args(["network::types::tests::canonical_child_emit_signed", "--exact", "--ignored", "--nocapture"])
+            .env("MANIFESTATION_CANONICAL_CHILD", "1")
+            .output()
+            .expect("spawn child test process");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let line = stdout
+            .lines()
+            .find_map(|l| l.split_once("SIGNED:").map(|(_, json)| json))
+            .unwrap_or_else(|| panic!("child produced no signed result: {}", stdout));
+
+        let received: SignedManifestation = serde_json::from_str(line).unwrap();
+        assert!(received.verify(), "result signed in another process must verify");
+
+        let local = forty_category_result(false);
+        assert_eq!(received.payload, local);
+        assert_eq!(received.signed_bytes.as_slice(), local.canonical_bytes().unwrap().as_slice());
+        assert_eq!(received.payload.get_cid().unwrap(), local.get_cid().unwrap());
+    }
+
+    #[test]
+    fn quic_listen_address_follows_the_setting() {
+        let ip = std::net::Ipv4Addr::UNSPECIFIED;
+        let both = TransportConfig::default().listen_addrs(ip);
+        assert_eq!(both.len(), 2);
+        assert_eq!(both[1].to_string(), "/ip4/0.0.0.0/udp/0/quic-v1");
+        let tcp_only = TransportConfig { quic: false }.listen_addrs(ip);
+        assert_eq!(tcp_only.iter().map(|a| a.to_string()).collect::<Vec<_>>(), vec!["/ip4/0.0.0.0/tcp/0"]);
+    }
+
+    #[test]
+    fn nat_status_maps_autonat_status() {
+        let addr: Multiaddr = "/ip4/1.2.3.4/tcp/4001".parse().unwrap();
+        assert_eq!(NatStatus::from(&autonat::NatStatus::Public(addr)), NatStatus::Public);
+        assert_eq!(NatStatus::from(&autonat::NatStatus::Private), NatStatus::Private);
+        assert_eq!(NatStatus::from(&autonat::NatStatus::Unknown), NatStatus::Unknown);
+        assert_eq!(serde_json::to_value(NatStatus::Private).unwrap(), "private");
+    }
 }
@@ -0,0 +1,79 @@
+use crate::identity::UserIdentity;
+use serde::{Deserialize, Serialize};
+
+/// Length of a publishing epoch: one UTC day.
+pub const EPOCH_SECS: u64 = 24 * 60 * 60;
+
+pub fn epoch_of(timestamp: u64) -> u64 {
+    timestamp / EPOCH_SECS
+}
+
+/// How often unlinkable publishing switches to a new signing key.
+///
+/// A key per publication is deliberately not offered: without a proof that
+/// limits how many keys one identity signs with per epoch, every result
+/// would count as another author and bypass per-author rate limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRotation {
+    /// One key per epoch: results within a day share a key, so a newer one
+    /// replaces the older, but days cannot be linked. Settings saved with the
+    /// withdrawn per-publication rotation fall back to it.
+    #[serde(alias = "per_publication")]
+    PerEpoch,
+}
+
+/// Opt-in unlinkable publishing: results are signed with keys derived from
+/// the root identity instead of the root key itself, so observers cannot
+/// join them into a longitudinal profile.
+///
+/// Peers cannot tell which derived keys share an identity, so each key
+/// counts as its own author in aggregates and cohorts: one vote per day
+/// rather than one overall. Dropping these results would not stop flooding,
+/// since anyone can sign with fresh root keys instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnlinkableSettings {
+    pub rotation: KeyRotation,
+}
+
+/// The key to sign a result stamped `timestamp` with.
+pub fn signing_identity(root: &UserIdentity, settings: UnlinkableSettings, timestamp: u64) -> UserIdentity {
+    let context = match settings.rotation {
+        KeyRotation::PerEpoch => epoch_of(timestamp).to_be_bytes(),
+    };
+    root.derive(&context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+    const PER_EPOCH: UnlinkableSettings = UnlinkableSettings { rotation: KeyRotation::PerEpoch };
+
+    fn sign(root: &UserIdentity, ts: u64) -> String {
+        signing_identity(root, PER_EPOCH, ts).public_key_b64()
+    }
+
+    #[test]
+    fn per_epoch_key_changes_only_with_the_epoch() {
+        let root = UserIdentity::generate();
+        let first = sign(&root, NOW);
+        assert_eq!(first, sign(&root, NOW + 60));
+        assert_ne!(first, sign(&root, NOW + EPOCH_SECS));
+        assert_ne!(first, root.public_key_b64());
+    }
+
+    #[test]
+    fn rotation_serialises_as_snake_case() {
+        let json = serde_json::to_value(PER_EPOCH).unwrap();
+        assert_eq!(json["rotation"], "per_epoch");
+    }
+
+    #[test]
+    fn withdrawn_per_publication_setting_loads_as_per_epoch() {
+        let saved = serde_json::json!({ "rotation": "per_publication", "rate_limit_proof": true });
+        let settings: UnlinkableSettings = serde_json::from_value(saved).unwrap();
+        assert_eq!(settings, PER_EPOCH);
+    }
+}
@@ -0,0 +1,82 @@
+use super::cohort::Withheld;
+use super::histogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How much weight a percentile rank can bear, from the number of samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RankConfidence {
+    /// Fewer than `MEDIUM_CONFIDENCE_SAMPLES`: indicative only.
+    Low,
+    Medium,
+    /// At least `HIGH_CONFIDENCE_SAMPLES`: within about ±10 points at the median.
+    High,
+}
+
+pub const MEDIUM_CONFIDENCE_SAMPLES: usize = 30;
+pub const HIGH_CONFIDENCE_SAMPLES: usize = 100;
+
+impl RankConfidence {
+    pub fn for_sample_size(n: usize) -> Self {
+        if n >= HIGH_CONFIDENCE_SAMPLES {
+            RankConfidence::High
+        } else if n >= MEDIUM_CONFIDENCE_SAMPLES {
+            RankConfidence::Medium
+        } else {
+            RankConfidence::Low
+        }
+    }
+}
+
+/// Where a score falls within a distribution.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PercentileRank {
+    /// Percentage of samples below the score, counting ties as half (0–100).
+    pub percentile: f64,
+    pub sample_size: usize,
+    pub confidence: RankConfidence,
+}
+
+/// A user's rank against the network aggregate, overall and per category.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreRank {
+    /// `None` while the aggregate is below the minimum cohort size.
+    pub overall: Option<PercentileRank>,
+    /// Set when `overall` is withheld.
+    pub withheld: Option<Withheld>,
+    /// Queried categories with enough network data.
+    pub categories: HashMap<String, PercentileRank>,
+    /// Queried categories whose rank is withheld.
+    pub withheld_categories: HashMap<String, Withheld>,
+}
+
+/// Score histograms of the aggregate, for drawing the distribution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreHistograms {
+    /// Total scores, 0–10,000 in 100-point bins. `None` when withheld.
+    pub total: Option<Histogram>,
+    pub withheld: Option<Withheld>,
+    /// Ratings per category, 0–10 in 1-point bins.
+    pub categories: HashMap<String, Histogram>,
+    pub withheld_categories: HashMap<String, Withheld>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confidence_grows_with_sample_size() {
+        assert_eq!(RankConfidence::for_sample_size(1), RankConfidence::Low);
+        assert_eq!(RankConfidence::for_sample_size(MEDIUM_CONFIDENCE_SAMPLES - 1), RankConfidence::Low);
+        assert_eq!(RankConfidence::for_sample_size(MEDIUM_CONFIDENCE_SAMPLES), RankConfidence::Medium);
+        assert_eq!(RankConfidence::for_sample_size(HIGH_CONFIDENCE_SAMPLES - 1), RankConfidence::Medium);
+        assert_eq!(RankConfidence::for_sample_size(HIGH_CONFIDENCE_SAMPLES), RankConfidence::High);
+    }
+
+    #[test]
+    fn confidence_serialises_lowercase() {
+        assert_eq!(serde_json::to_string(&RankConfidence::Medium).unwrap(), r#""medium""#);
+    }
+}
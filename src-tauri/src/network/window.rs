@@ -0,0 +1,349 @@
+use super::cohort::{check_cohort, Withheld};
+use super::quantiles::Quantiles;
+use super::types::ManifestationResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Shortest accepted half-life. Timestamps may be coarsened to the day, so a
+/// shorter one only concentrates the weight on whichever results happen to
+/// round to the most recent day.
+pub const MIN_HALF_LIFE_DAYS: f64 = 1.0;
+
+/// Time span of results a windowed query covers, ending now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeWindow {
+    #[serde(rename = "7d")]
+    Last7Days,
+    #[serde(rename = "30d")]
+    Last30Days,
+    #[serde(rename = "90d")]
+    Last90Days,
+    #[serde(rename = "all")]
+    AllTime,
+}
+
+impl TimeWindow {
+    /// Oldest timestamp inside the window at `now`; 0 for all time.
+    pub fn cutoff(self, now: u64) -> u64 {
+        let days = match self {
+            TimeWindow::Last7Days => 7,
+            TimeWindow::Last30Days => 30,
+            TimeWindow::Last90Days => 90,
+            TimeWindow::AllTime => return 0,
+        };
+        now.saturating_sub(days * DAY_SECS)
+    }
+}
+
+/// A windowed statistics request.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowQuery {
+    pub window: TimeWindow,
+    /// Weight each result by `0.5^(age / half_life)`. `None` weighs all equally.
+    pub half_life_days: Option<f64>,
+}
+
+impl WindowQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.half_life_days {
+            Some(h) if !h.is_finite() || h < MIN_HALF_LIFE_DAYS => Err(format!(
+                "Half-life {} must be a number of days no less than {}",
+                h, MIN_HALF_LIFE_DAYS
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn weight(&self, timestamp: u64, now: u64) -> f64 {
+        match self.half_life_days {
+            Some(half_life) => {
+                let age_days = now.saturating_sub(timestamp) as f64 / DAY_SECS as f64;
+                0.5_f64.powf(age_days / half_life)
+            }
+            None => 1.0,
+        }
+    }
+}
+
+/// Statistics over one series (total score or a category) within a window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowedSeries {
+    /// Results in the window.
+    pub sample_size: usize,
+    /// Kish effective sample size; equals `sample_size` without decay.
+    pub effective_sample_size: f64,
+    /// Weighted mean.
+    pub avg: f64,
+    /// Weighted quantiles.
+    pub quantiles: Quantiles,
+}
+
+/// Aggregate statistics over the results inside a time window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowedStats {
+    pub window: TimeWindow,
+    pub half_life_days: Option<f64>,
+    /// `None` when the window holds fewer results than the minimum cohort size.
+    pub total: Option<WindowedSeries>,
+    /// Set when `total` is withheld.
+    pub withheld: Option<Withheld>,
+    pub categories: HashMap<String, WindowedSeries>,
+    /// Categories rated in the window but by too few results to report.
+    pub withheld_categories: HashMap<String, Withheld>,
+}
+
+/// Compute windowed, optionally decayed statistics over `results`, which
+/// must already be limited to the window (see `Aggregator::results_since`).
+/// The total and each category are withheld separately when they have fewer
+/// than `min_cohort_size` results in this window, or when decay leaves them
+/// an effective sample size below it.
+pub fn windowed_stats<'a>(
+    results: impl Iterator<Item = &'a ManifestationResult>,
+    query: WindowQuery,
+    now: u64,
+    min_cohort_size: usize,
+) -> WindowedStats {
+    let mut totals = Vec::new();
+    let mut by_category: HashMap<&str, Vec<(f64, f64)>> = HashMap::new();
+    for result in results {
+        let weight = query.weight(result.timestamp, now);
+        totals.push((result.score, weight));
+        for (category, &score) in &result.category_scores {
+            by_category.entry(category).or_default().push((score, weight));
+        }
+    }
+    let (total, withheld) = match release(totals, min_cohort_size) {
+        Ok(total) => (Some(total), None),
+        Err(withheld) => (None, Some(withheld)),
+    };
+    let mut categories = HashMap::new();
+    let mut withheld_categories = HashMap::new();
+    for (category, samples) in by_category {
+        match release(samples, min_cohort_size) {
+            Ok(stats) => {
+                categories.insert(category.to_string(), stats);
+            }
+            Err(withheld) => {
+                withheld_categories.insert(category.to_string(), withheld);
+            }
+        }
+    }
+    WindowedStats {
+        window: query.window,
+        half_life_days: query.half_life_days,
+        total,
+        withheld,
+        categories,
+        withheld_categories,
+    }
+}
+
+/// Statistics over `samples`, or why they are withheld. A steep decay can
+/// put nearly all the weight on one or two results, so the cohort rule also
+/// applies to the effective sample size, which `Withheld::sample_size` then
+/// reports rounded down. Weights that all underflow count as an effective
+/// size of 0.
+fn release(samples: Vec<(f64, f64)>, min_cohort_size: usize) -> Result<WindowedSeries, Withheld> {
+    check_cohort(samples.len(), min_cohort_size)?;
+    let Some(stats) = series(samples) else { return Err(check_cohort(0, min_cohort_size).unwrap_err()) };
+    // The tolerance keeps n equal weights from summing to just under n.
+    check_cohort((stats.effective_sample_size + 1e-9).floor() as usize, min_cohort_size)?;
+    Ok(stats)
+}
+
+/// Weighted statistics over `(value, weight)` samples; `None` if empty or
+/// if decay has rounded every weight down to (nearly) zero, which would
+/// make the mean 0/0.
+fn series(mut samples: Vec<(f64, f64)>) -> Option<WindowedSeries> {
+    let max_weight = samples.iter().map(|s| s.1).fold(0.0, f64::max);
+    if max_weight <= f64::MIN_POSITIVE {
+        return None;
+    }
+    // Scale the largest weight to 1. Tiny weights would otherwise square to
+    // 0 and make the effective sample size infinite; scaling changes neither
+    // it nor the weighted statistics.
+    for sample in &mut samples {
+        sample.1 /= max_weight;
+    }
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let weight_sum: f64 = samples.iter().map(|s| s.1).sum();
+    let weight_sq_sum: f64 = samples.iter().map(|s| s.1 * s.1).sum();
+    let avg = samples.iter().map(|(v, w)| v * w).sum::<f64>() / weight_sum;
+
+    // The first value whose cumulative weight exceeds q of the total. With
+    // equal weights this picks index floor(n·q), like `ScoreDistribution`.
+    let quantile = |q: f64| {
+        let target = q * weight_sum;
+        let mut cumulative = 0.0;
+        for &(value, weight) in &samples {
+            cumulative += weight;
+            if cumulative > target {
+                return value;
+            }
+        }
+        samples[samples.len() - 1].0
+    };
+
+    Some(WindowedSeries {
+        sample_size: samples.len(),
+        effective_sample_size: weight_sum * weight_sum / weight_sq_sum,
+        avg,
+        quantiles: Quantiles {
+            p10: quantile(0.10),
+            p25: quantile(0.25),
+            p50: quantile(0.50),
+            p75: quantile(0.75),
+            p90: quantile(0.90),
+            p99: quantile(0.99),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn result(score: f64, days_ago: u64) -> ManifestationResult {
+        ManifestationResult {
+            score,
+            timestamp: NOW - days_ago * DAY_SECS,
+            category_scores: HashMap::from([("14".to_string(), score / 1000.0)]),
+            question_set: String::new(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+        }
+    }
+
+    fn query(window: TimeWindow, half_life_days: Option<f64>) -> WindowQuery {
+        WindowQuery { window, half_life_days }
+    }
+
+    #[test]
+    fn cutoffs_cover_the_named_number_of_days() {
+        assert_eq!(TimeWindow::Last7Days.cutoff(NOW), NOW - 7 * DAY_SECS);
+        assert_eq!(TimeWindow::Last30Days.cutoff(NOW), NOW - 30 * DAY_SECS);
+        assert_eq!(TimeWindow::Last90Days.cutoff(NOW), NOW - 90 * DAY_SECS);
+        assert_eq!(TimeWindow::AllTime.cutoff(NOW), 0);
+    }
+
+    #[test]
+    fn windows_serialise_as_short_names() {
+        let json = serde_json::to_string(&[TimeWindow::Last7Days, TimeWindow::AllTime]).unwrap();
+        assert_eq!(json, r#"["7d","all"]"#);
+        assert_eq!(serde_json::from_str::<TimeWindow>(r#""90d""#).unwrap(), TimeWindow::Last90Days);
+    }
+
+    #[test]
+    fn undecayed_stats_match_plain_statistics() {
+        let results: Vec<_> = (0..10).map(|i| result(f64::from(i) * 1000.0, i as u64)).collect();
+        let stats = windowed_stats(results.iter(), query(TimeWindow::AllTime, None), NOW, 1);
+        let total = stats.total.unwrap();
+        assert_eq!(total.sample_size, 10);
+        assert_eq!(total.effective_sample_size, 10.0);
+        assert_eq!(total.avg, 4500.0);
+        assert_eq!(total.quantiles.p50, 5000.0);
+        assert_eq!(total.quantiles.p90, 9000.0);
+        assert_eq!(stats.categories["14"].sample_size, 10);
+    }
+
+    #[test]
+    fn decay_favours_recent_results() {
+        // One fresh high score, one low score ten half-lives old
+        let results = [result(9000.0, 0), result(1000.0, 70)];
+        let stats = windowed_stats(results.iter(), query(TimeWindow::AllTime, Some(7.0)), NOW, 1);
+        let total = stats.total.unwrap();
+        assert!(total.avg > 8990.0, "avg {}", total.avg);
+        assert_eq!(total.quantiles.p50, 9000.0);
+        assert!(total.effective_sample_size < 1.01);
+        assert_eq!(total.sample_size, 2);
+    }
+
+    #[test]
+    fn steep_decay_withholds_series_with_too_small_effective_size() {
+        // Five results, but four are so old that the fresh one carries
+        // almost all the weight: publishing would reveal its score.
+        let results: Vec<_> = [0, 30, 31, 32, 33].iter().map(|&days| result(5000.0, days)).collect();
+        let steep = windowed_stats(results.iter(), query(TimeWindow::AllTime, Some(1.0)), NOW, 5);
+        assert_eq!(steep.total, None);
+        assert_eq!(steep.withheld.unwrap().sample_size, 1);
+        assert_eq!(steep.withheld_categories["14"].sample_size, 1);
+
+        let gentle = windowed_stats(results.iter(), query(TimeWindow::AllTime, Some(365.0)), NOW, 4);
+        assert!(gentle.total.unwrap().effective_sample_size >= 4.0);
+    }
+
+    #[test]
+    fn fully_decayed_window_is_withheld_not_nan() {
+        // 90 days at a one-hour half-life: every weight underflows to 0.
+        let results: Vec<_> = (0..5).map(|i| result(5000.0, 90 + i)).collect();
+        let stats = windowed_stats(results.iter(), query(TimeWindow::AllTime, Some(1.0 / 24.0)), NOW, 1);
+        assert_eq!(stats.total, None);
+        assert_eq!(stats.withheld.unwrap().sample_size, 0);
+        assert!(stats.categories.is_empty());
+        assert_eq!(stats.withheld_categories["14"].sample_size, 0);
+        assert_eq!(series(vec![(1.0, 0.0), (2.0, f64::MIN_POSITIVE / 4.0)]), None);
+    }
+
+    #[test]
+    fn tiny_weights_do_not_inflate_the_effective_size() {
+        // At a 0.01-day half-life the freshest weight is 0.5^600 and its
+        // square underflows; the day-older results weigh nothing beside it.
+        let results: Vec<_> = (6..11).map(|days| result(5000.0, days)).collect();
+        let stats = windowed_stats(results.iter(), query(TimeWindow::AllTime, Some(0.01)), NOW, 5);
+        assert_eq!(stats.total, None);
+        assert_eq!(stats.withheld.unwrap().sample_size, 1);
+        assert_eq!(stats.withheld_categories["14"].sample_size, 1);
+    }
+
+    #[test]
+    fn one_half_life_halves_the_weight() {
+        let q = query(TimeWindow::AllTime, Some(30.0));
+        assert_eq!(q.weight(NOW, NOW), 1.0);
+        assert!((q.weight(NOW - 30 * DAY_SECS, NOW) - 0.5).abs() < 1e-12);
+        assert_eq!(q.weight(NOW + 60, NOW), 1.0, "future timestamps are not boosted");
+    }
+
+    #[test]
+    fn empty_window_has_no_total() {
+        let stats = windowed_stats(std::iter::empty(), query(TimeWindow::Last7Days, None), NOW, 1);
+        assert_eq!(stats.total, None);
+        assert!(stats.categories.is_empty());
+    }
+
+    #[test]
+    fn small_window_is_withheld() {
+        let results: Vec<_> = (0..4).map(|i| result(5000.0, i)).collect();
+        let stats = windowed_stats(results.iter(), query(TimeWindow::AllTime, None), NOW, 5);
+        assert_eq!(stats.total, None);
+        assert_eq!(stats.withheld.unwrap().sample_size, 4);
+        assert!(stats.categories.is_empty());
+        assert_eq!(stats.withheld_categories["14"].sample_size, 4);
+    }
+
+    #[test]
+    fn categories_are_withheld_independently() {
+        let mut results: Vec<_> = (0..5).map(|i| result(5000.0, i)).collect();
+        results[0].category_scores.insert("2".to_string(), 9.0);
+        let stats = windowed_stats(results.iter(), query(TimeWindow::AllTime, None), NOW, 5);
+        assert!(stats.total.is_some());
+        assert_eq!(stats.withheld, None);
+        assert!(stats.categories.contains_key("14"));
+        assert!(!stats.categories.contains_key("2"));
+        assert_eq!(stats.withheld_categories["2"].sample_size, 1);
+    }
+
+    #[test]
+    fn invalid_half_life_is_rejected() {
+        for bad in [0.0, -1.0, 0.01, f64::NAN, f64::INFINITY] {
+            assert!(query(TimeWindow::AllTime, Some(bad)).validate().is_err());
+        }
+        assert!(query(TimeWindow::AllTime, Some(14.0)).validate().is_ok());
+        assert!(query(TimeWindow::AllTime, Some(MIN_HALF_LIFE_DAYS)).validate().is_ok());
+        assert!(query(TimeWindow::AllTime, None).validate().is_ok());
+    }
+}
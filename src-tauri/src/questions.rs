@@ -0,0 +1,179 @@
+use crate::network::canonical::to_canonical_vec;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+/// One entry of the questionnaire. Mirrors `Question` in `src/data/questions.ts`;
+/// only the fields that affect scoring and category keys are kept.
+#[derive(Debug, Serialize)]
+pub struct Question {
+    pub id: &'static str,
+    pub points: u32,
+    /// Rated sub-questions; when present the parent itself is not rated.
+    pub sub_points: &'static [Question],
+}
+
+impl Question {
+    pub fn is_rated(&self) -> bool {
+        self.sub_points.is_empty()
+    }
+}
+
+/// The canonical question set, in questionnaire order. Must be kept in step
+/// with `src/data/questions.ts`; `registry_matches_frontend_questions` fails
+/// when the two drift apart.
+pub static QUESTIONS: &[Question] = &[
+    Question { id: "1", points: 100, sub_points: &[
+        Question { id: "1a", points: 25, sub_points: &[] },
+        Question { id: "1b", points: 25, sub_points: &[] },
+        Question { id: "1c", points: 25, sub_points: &[] },
+        Question { id: "1d", points: 25, sub_points: &[] },
+    ] },
+    Question { id: "2", points: 100, sub_points: &[] },
+    Question { id: "3", points: 250, sub_points: &[] },
+    Question { id: "4", points: 250, sub_points: &[] },
+    Question { id: "5", points: 250, sub_points: &[] },
+    Question { id: "6", points: 50, sub_points: &[] },
+    Question { id: "7", points: 200, sub_points: &[] },
+    Question { id: "8", points: 50, sub_points: &[] },
+    Question { id: "9", points: 200, sub_points: &[] },
+    Question { id: "10", points: 300, sub_points: &[] },
+    Question { id: "11", points: 250, sub_points: &[] },
+    Question { id: "12", points: 100, sub_points: &[] },
+    Question { id: "13", points: 50, sub_points: &[] },
+    Question { id: "14", points: 50, sub_points: &[] },
+    Question { id: "15", points: 50, sub_points: &[] },
+    Question { id: "16", points: 50, sub_points: &[] },
+    Question { id: "17", points: 50, sub_points: &[] },
+    Question { id: "18", points: 200, sub_points: &[] },
+    Question { id: "19", points: 500, sub_points: &[
+        Question { id: "19a", points: 100, sub_points: &[] },
+        Question { id: "19b", points: 100, sub_points: &[] },
+        Question { id: "19c", points: 100, sub_points: &[] },
+        Question { id: "19d", points: 100, sub_points: &[] },
+        Question { id: "19e", points: 100, sub_points: &[] },
+    ] },
+    Question { id: "20", points: 750, sub_points: &[] },
+    Question { id: "21", points: 200, sub_points: &[] },
+    Question { id: "22", points: 50, sub_points: &[] },
+    Question { id: "23", points: 1250, sub_points: &[
+        Question { id: "23a", points: 500, sub_points: &[] },
+        Question { id: "23b", points: 150, sub_points: &[] },
+        Question { id: "23c", points: 150, sub_points: &[] },
+        Question { id: "23d", points: 100, sub_points: &[] },
+        Question { id: "23e", points: 50, sub_points: &[] },
+        Question { id: "23f", points: 50, sub_points: &[] },
+        Question { id: "23g", points: 200, sub_points: &[] },
+        Question { id: "23h", points: 50, sub_points: &[] },
+    ] },
+    Question { id: "24", points: 600, sub_points: &[] },
+    Question { id: "25", points: 100, sub_points: &[] },
+    Question { id: "26", points: 100, sub_points: &[] },
+    Question { id: "27", points: 100, sub_points: &[] },
+    Question { id: "28", points: 600, sub_points: &[] },
+    Question { id: "29", points: 50, sub_points: &[] },
+    Question { id: "30", points: 250, sub_points: &[] },
+    Question { id: "31", points: 50, sub_points: &[] },
+    Question { id: "32", points: 200, sub_points: &[] },
+    Question { id: "33", points: 50, sub_points: &[] },
+    Question { id: "34", points: 50, sub_points: &[] },
+    Question { id: "35", points: 50, sub_points: &[] },
+    Question { id: "36", points: 200, sub_points: &[] },
+    Question { id: "37", points: 200, sub_points: &[] },
+    Question { id: "38", points: 800, sub_points: &[] },
+    Question { id: "39", points: 1200, sub_points: &[] },
+    Question { id: "40", points: 100, sub_points: &[] },
+];
+
+/// Every rated question (leaf), in questionnaire order. Their ids are the
+/// only valid category keys in a published result.
+pub fn rated_questions() -> impl Iterator<Item = &'static Question> {
+    QUESTIONS.iter().flat_map(|q| if q.is_rated() { std::slice::from_ref(q) } else { q.sub_points })
+}
+
+pub fn is_known_category(id: &str) -> bool {
+    rated_questions().any(|q| q.id == id)
+}
+
+/// Identifies this build's question set: the first 8 bytes of the SHA-256 of
+/// the canonical JSON of `QUESTIONS`, hex-encoded. Any change to ids, points
+/// or structure changes the hash, so results scored against different
+/// questionnaires are never aggregated together.
+pub fn question_set_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| {
+        let bytes = to_canonical_vec(&QUESTIONS).expect("question registry serialises");
+        Sha256::digest(&bytes)[..8].iter().map(|b| format!("{:02x}", b)).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (id, points) pairs in the order they appear in `questions.ts`.
+    fn frontend_questions() -> Vec<(String, u32)> {
+        let source = include_str!("../../src/data/questions.ts");
+        let mut out = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("id: '") {
+            rest = &rest[start + 5..];
+            let end = rest.find('\'').unwrap();
+            let id = rest[..end].to_string();
+            let points_at = rest.find("points: ").unwrap() + 8;
+            rest = &rest[points_at..];
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            out.push((id, digits.parse().unwrap()));
+        }
+        out
+    }
+
+    fn registry_in_order() -> Vec<(String, u32)> {
+        QUESTIONS
+            .iter()
+            .flat_map(|q| std::iter::once(q).chain(q.sub_points))
+            .map(|q| (q.id.to_string(), q.points))
+            .collect()
+    }
+
+    #[test]
+    fn registry_matches_frontend_questions() {
+        assert_eq!(registry_in_order(), frontend_questions());
+    }
+
+    #[test]
+    fn registry_has_forty_questions_and_fifty_four_rated_items() {
+        assert_eq!(QUESTIONS.len(), 40);
+        assert_eq!(rated_questions().count(), crate::network::types::MAX_CATEGORIES);
+    }
+
+    #[test]
+    fn sub_points_sum_to_parent_points() {
+        for q in QUESTIONS.iter().filter(|q| !q.is_rated()) {
+            let sum: u32 = q.sub_points.iter().map(|s| s.points).sum();
+            assert_eq!(sum, q.points, "question {}", q.id);
+        }
+    }
+
+    #[test]
+    fn maximum_total_is_ten_thousand() {
+        let total: u32 = rated_questions().map(|q| q.points).sum();
+        assert_eq!(total, 10_000);
+    }
+
+    #[test]
+    fn parents_with_sub_points_are_not_categories() {
+        assert!(is_known_category("1a"));
+        assert!(is_known_category("2"));
+        assert!(!is_known_category("1"));
+        assert!(!is_known_category("focus"));
+    }
+
+    #[test]
+    fn question_set_hash_is_stable_hex() {
+        let hash = question_set_hash();
+        assert_eq!(hash.len(), 16);
+        assert!(hash.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(hash, question_set_hash());
+    }
+}
@@ -0,0 +1,478 @@
+use crate::network::aggregate::AggregatorConfig;
+use crate::network::cohort::DEFAULT_MIN_COHORT_SIZE;
+use crate::network::types::NetworkStatUpdate;
+use crate::network::{Command, NodeConfig, PeerNode, TransportConfig};
+use libp2p::{Multiaddr, PeerId};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+pub const USAGE: &str = "\
+Usage: manifestation-relayd [OPTIONS]
+
+Always-on bootstrap, relay and gossip peer for the Manifestation network.
+
+Options:
+  --config <FILE>            TOML config file; flags below override it
+  --data-dir <DIR>           Directory for the node key and aggregate cache
+  --listen <MULTIADDR>       Address to listen on (repeatable)
+  --external <MULTIADDR>     Publicly reachable address to advertise (repeatable)
+  --bootstrap <MULTIADDR>    Peer to dial on startup (repeatable)
+  --no-quic                  Listen and dial on TCP only
+  --snapshot-listen <ADDR>   Serve aggregate snapshots over HTTP, e.g. 0.0.0.0:8080
+  --retention-days <DAYS>    Drop an author's result this many days after its timestamp
+  --min-cohort-size <N>      Withhold statistics over fewer results than this (default 5)
+  -h, --help                 Print this help";
+
+/// Port listened on when no `listen` address is configured.
+const DEFAULT_PORT: u16 = 4001;
+
+/// Longest time a snapshot client gets to send its request line.
+const SNAPSHOT_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `manifestation-relayd` settings, from a TOML file and/or CLI flags.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RelaydConfig {
+    /// Holds `identity.key` and `network_cache.json`.
+    pub data_dir: PathBuf,
+    /// Listen addresses; empty means port 4001 on all interfaces.
+    pub listen: Vec<Multiaddr>,
+    pub external_addresses: Vec<Multiaddr>,
+    /// Peers to dial on startup; empty means `PeerNode::default_bootstrap_peers`.
+    pub bootstrap_peers: Vec<Multiaddr>,
+    pub quic: bool,
+    /// Where to serve `GET /snapshot`; off when unset.
+    pub snapshot_listen: Option<SocketAddr>,
+    /// Days an author's result stays in the aggregate; kept until evicted
+    /// by the author cap when unset.
+    pub retention_days: Option<u64>,
+    /// Statistics over fewer results than this are withheld; at least 2.
+    pub min_cohort_size: usize,
+}
+
+impl Default for RelaydConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("relayd-data"),
+            listen: vec![],
+            external_addresses: vec![],
+            bootstrap_peers: vec![],
+            quic: true,
+            snapshot_listen: None,
+            retention_days: None,
+            min_cohort_size: DEFAULT_MIN_COHORT_SIZE,
+        }
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} needs a value", flag))
+}
+
+fn parse_addr(value: &str) -> Result<Multiaddr, String> {
+    value.parse().map_err(|e| format!("Invalid multiaddress {}: {}", value, e))
+}
+
+impl RelaydConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Parse command-line arguments (without the program name). A
+    /// `--config` file is read first; repeated flags replace its lists.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let args: Vec<String> = args.into_iter().collect();
+        let mut config = match args.iter().position(|a| a == "--config") {
+            Some(i) => Self::load(Path::new(args.get(i + 1).ok_or("--config needs a value")?))?,
+            None => Self::default(),
+        };
+        let (mut listen, mut external, mut bootstrap) = (vec![], vec![], vec![]);
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--config" => {
+                    next_value(&mut args, &flag)?;
+                }
+                "--data-dir" => config.data_dir = next_value(&mut args, &flag)?.into(),
+                "--listen" => listen.push(parse_addr(&next_value(&mut args, &flag)?)?),
+                "--external" => external.push(parse_addr(&next_value(&mut args, &flag)?)?),
+                "--bootstrap" => bootstrap.push(parse_addr(&next_value(&mut args, &flag)?)?),
+                "--no-quic" => config.quic = false,
+                "--snapshot-listen" => {
+                    let value = next_value(&mut args, &flag)?;
+                    let addr = value.parse().map_err(|e| format!("Invalid socket address {}: {}", value, e))?;
+                    config.snapshot_listen = Some(addr);
+                }
+                "--retention-days" => {
+                    let value = next_value(&mut args, &flag)?;
+                    let days = value.parse().map_err(|e| format!("Invalid number of days {}: {}", value, e))?;
+                    config.retention_days = Some(days);
+                }
+                "--min-cohort-size" => {
+                    let value = next_value(&mut args, &flag)?;
+                    config.min_cohort_size = value.parse().map_err(|e| format!("Invalid cohort size {}: {}", value, e))?;
+                }
+                other => return Err(format!("Unknown argument {}\n\n{}", other, USAGE)),
+            }
+        }
+        if !listen.is_empty() {
+            config.listen = listen;
+        }
+        if !external.is_empty() {
+            config.external_addresses = external;
+        }
+        if !bootstrap.is_empty() {
+            config.bootstrap_peers = bootstrap;
+        }
+        config.aggregator_config()?;
+        Ok(config)
+    }
+
+    /// Limits for the node's aggregate.
+    pub fn aggregator_config(&self) -> Result<AggregatorConfig, String> {
+        AggregatorConfig::default()
+            .with_retention_days(self.retention_days)?
+            .with_min_cohort_size(self.min_cohort_size)
+    }
+
+    /// The configured listen addresses, or the default port on every interface.
+    pub fn listen_addrs(&self) -> Vec<Multiaddr> {
+        if !self.listen.is_empty() {
+            return self.listen.clone();
+        }
+        let mut addrs = vec![format!("/ip4/0.0.0.0/tcp/{}", DEFAULT_PORT)];
+        if self.quic {
+            addrs.push(format!("/ip4/0.0.0.0/udp/{}/quic-v1", DEFAULT_PORT));
+        }
+        addrs.into_iter().filter_map(|a| a.parse().ok()).collect()
+    }
+}
+
+/// A started relay node.
+pub struct Relayd {
+    pub peer_id: PeerId,
+    /// Bound address of the snapshot server, if enabled.
+    pub snapshot_addr: Option<SocketAddr>,
+    commands: mpsc::Sender<Command>,
+    node: JoinHandle<()>,
+}
+
+impl Relayd {
+    /// Stop the node, saving the aggregate cache.
+    pub async fn shutdown(self) {
+        if let Err(e) = self.commands.send(Command::Shutdown).await {
+            eprintln!("Failed to send Shutdown command: {}", e);
+        }
+        if let Err(e) = self.node.await {
+            eprintln!("Peer node task failed: {}", e);
+        }
+    }
+}
+
+/// Start the node: a Kademlia server, circuit relay and gossip peer that
+/// validates, forwards and aggregates results like the app does.
+pub async fn start(config: RelaydConfig) -> Result<Relayd, String> {
+    std::fs::create_dir_all(&config.data_dir).map_err(|e| format!("{}: {}", config.data_dir.display(), e))?;
+    let keypair = crate::load_or_generate_keypair(&config.data_dir.join("identity.key"), None)
+        .map_err(|e| format!("Failed to load node key: {}", e))?;
+    let peer_id = keypair.public().to_peer_id();
+
+    let (cmd_tx, cmd_rx) = mpsc::channel(32);
+    let (event_tx, mut event_rx) = mpsc::channel(32);
+    let node_config = NodeConfig {
+        aggregator: config.aggregator_config()?,
+        transports: TransportConfig { quic: config.quic },
+        relay_server: true,
+        external_addresses: config.external_addresses.clone(),
+        // A public server has no LAN peers worth announcing itself to.
+        mdns: false,
+        ..NodeConfig::default()
+    };
+    let cache_path = config.data_dir.join("network_cache.json");
+    let node = PeerNode::new(keypair, cmd_rx, event_tx, Some(cache_path), config.bootstrap_peers.clone(), node_config)
+        .await
+        .map_err(|e| format!("Failed to create peer node: {}", e))?;
+
+    let mut acks = Vec::new();
+    for addr in config.listen_addrs() {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        cmd_tx
+            .send(Command::StartListening { addr: addr.clone(), sender: ack_tx })
+            .await
+            .map_err(|e| e.to_string())?;
+        acks.push((addr, ack_rx));
+    }
+    let node = tokio::spawn(node.run());
+    for (addr, ack_rx) in acks {
+        match ack_rx.await {
+            Ok(Ok(())) => println!("Listening on {}", addr),
+            Ok(Err(e)) => return Err(format!("Failed to listen on {}: {}", addr, e)),
+            Err(_) => return Err("Peer node stopped during startup".into()),
+        }
+    }
+
+    let latest = Arc::new(Mutex::new(None::<NetworkStatUpdate>));
+    let latest_writer = latest.clone();
+    tokio::spawn(async move {
+        while let Some(stats) = event_rx.recv().await {
+            if let Ok(mut guard) = latest_writer.lock() {
+                *guard = Some(stats);
+            }
+        }
+    });
+
+    let snapshot_addr = match config.snapshot_listen {
+        Some(addr) => {
+            let listener = TcpListener::bind(addr).await.map_err(|e| format!("Failed to bind {}: {}", addr, e))?;
+            let bound = listener.local_addr().map_err(|e| e.to_string())?;
+            println!("Serving aggregate snapshots on http://{}/snapshot", bound);
+            tokio::spawn(serve_snapshots(listener, latest));
+            Some(bound)
+        }
+        None => None,
+    };
+
+    Ok(Relayd { peer_id, snapshot_addr, commands: cmd_tx, node })
+}
+
+/// Run until Ctrl-C, then shut down cleanly.
+pub async fn run(config: RelaydConfig) -> Result<(), String> {
+    let relayd = start(config).await?;
+    println!("manifestation-relayd running as {}", relayd.peer_id);
+    tokio::signal::ctrl_c().await.map_err(|e| e.to_string())?;
+    relayd.shutdown().await;
+    Ok(())
+}
+
+async fn serve_snapshots(listener: TcpListener, latest: Arc<Mutex<Option<NetworkStatUpdate>>>) {
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("Snapshot server accept failed: {}", e);
+                continue;
+            }
+        };
+        let latest = latest.clone();
+        tokio::spawn(async move {
+            let mut request = [0u8; 1024];
+            let n = match tokio::time::timeout(SNAPSHOT_READ_TIMEOUT, stream.read(&mut request)).await {
+                Ok(Ok(n)) => n,
+                _ => return,
+            };
+            let snapshot = latest.lock().ok().and_then(|guard| guard.clone());
+            let response = snapshot_response(&request[..n], snapshot);
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                eprintln!("Snapshot server write failed: {}", e);
+            }
+        });
+    }
+}
+
+/// Answer an HTTP request: `GET /snapshot` returns the latest aggregate as
+/// JSON. Connected peer ids are left out so the snapshot does not list who
+/// is online.
+fn snapshot_response(request: &[u8], snapshot: Option<NetworkStatUpdate>) -> String {
+    let request_line = request.split(|&b| b == b'\r' || b == b'\n').next().unwrap_or_default();
+    let mut parts = request_line.split(|&b| b == b' ');
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some(b"GET"), Some(b"/snapshot")) => match snapshot {
+            Some(mut snapshot) => {
+                snapshot.connected_peers.clear();
+                match serde_json::to_string(&snapshot) {
+                    Ok(json) => ("200 OK", json),
+                    Err(e) => ("500 Internal Server Error", serde_json::json!({ "error": e.to_string() }).to_string()),
+                }
+            }
+            None => ("503 Service Unavailable", r#"{"error":"no snapshot yet"}"#.to_string()),
+        },
+        (Some(b"GET"), Some(_)) => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+        _ => ("405 Method Not Allowed", r#"{"error":"method not allowed"}"#.to_string()),
+    };
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!("{}_{}", name, nanos))
+    }
+
+    #[test]
+    fn defaults_listen_on_tcp_and_quic_port_4001() {
+        let config = RelaydConfig::from_args(vec![]).unwrap();
+        let listen: Vec<String> = config.listen_addrs().iter().map(|a| a.to_string()).collect();
+        assert_eq!(listen, ["/ip4/0.0.0.0/tcp/4001", "/ip4/0.0.0.0/udp/4001/quic-v1"]);
+        assert_eq!(config.snapshot_listen, None);
+    }
+
+    #[test]
+    fn flags_override_the_config_file() {
+        let dir = temp_dir("relayd_config");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("relayd.toml");
+        std::fs::write(
+            &path,
+            r#"
+data_dir = "/var/lib/relayd"
+listen = ["/ip4/0.0.0.0/tcp/5000"]
+external_addresses = ["/ip4/203.0.113.7/tcp/5000"]
+snapshot_listen = "127.0.0.1:8080"
+"#,
+        )
+        .unwrap();
+        let config = RelaydConfig::from_args(args(&format!(
+            "--config {} --listen /ip4/127.0.0.1/tcp/6000 --no-quic",
+            path.display()
+        )));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let config = config.unwrap();
+        assert_eq!(config.data_dir, PathBuf::from("/var/lib/relayd"));
+        assert_eq!(config.listen_addrs(), vec!["/ip4/127.0.0.1/tcp/6000".parse::<Multiaddr>().unwrap()]);
+        assert_eq!(config.external_addresses.len(), 1);
+        assert_eq!(config.snapshot_listen, Some("127.0.0.1:8080".parse().unwrap()));
+        assert!(!config.quic);
+    }
+
+    #[test]
+    fn bad_arguments_are_reported() {
+        assert!(RelaydConfig::from_args(args("--listen")).is_err());
+        assert!(RelaydConfig::from_args(args("--listen not-an-address")).is_err());
+        assert!(RelaydConfig::from_args(args("--frobnicate")).is_err());
+        assert!(toml::from_str::<RelaydConfig>("unknown_key = 1").is_err());
+    }
+
+    #[test]
+    fn configured_retention_evicts_old_authors() {
+        use crate::network::aggregate::Aggregator;
+        use crate::network::types::ManifestationResult;
+
+        const NOW: u64 = 1_700_000_000;
+        const DAY: u64 = 24 * 60 * 60;
+        let config: RelaydConfig = toml::from_str("retention_days = 3").unwrap();
+        let mut aggregator = Aggregator::new(config.aggregator_config().unwrap());
+        let result = |timestamp| ManifestationResult {
+            score: 5000.0,
+            timestamp,
+            category_scores: Default::default(),
+            question_set: crate::questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+        };
+        aggregator.insert("old", result(NOW), NOW);
+        aggregator.insert("recent", result(NOW + 2 * DAY), NOW + 2 * DAY);
+        aggregator.prune(NOW + 3 * DAY + 1);
+        assert_eq!(aggregator.len(), 1, "the author older than three days is evicted");
+
+        assert!(RelaydConfig::from_args(args("--retention-days 1")).is_err());
+        assert!(RelaydConfig::from_args(args("--retention-days soon")).is_err());
+        assert_eq!(RelaydConfig::from_args(args("--retention-days 30")).unwrap().retention_days, Some(30));
+    }
+
+    #[test]
+    fn min_cohort_size_is_configurable_but_at_least_two() {
+        let config: RelaydConfig = toml::from_str("min_cohort_size = 10").unwrap();
+        assert_eq!(config.aggregator_config().unwrap().min_cohort_size, 10);
+        assert_eq!(RelaydConfig::default().aggregator_config().unwrap().min_cohort_size, DEFAULT_MIN_COHORT_SIZE);
+        assert_eq!(RelaydConfig::from_args(args("--min-cohort-size 3")).unwrap().min_cohort_size, 3);
+        assert!(RelaydConfig::from_args(args("--min-cohort-size 1")).is_err());
+        assert!(RelaydConfig::from_args(args("--min-cohort-size few")).is_err());
+    }
+
+    #[test]
+    fn snapshot_routes() {
+        assert!(snapshot_response(b"GET /snapshot HTTP/1.1\r\n\r\n", None).starts_with("HTTP/1.1 503"));
+        assert!(snapshot_response(b"GET /other HTTP/1.1\r\n\r\n", None).starts_with("HTTP/1.1 404"));
+        assert!(snapshot_response(b"POST /snapshot HTTP/1.1\r\n\r\n", None).starts_with("HTTP/1.1 405"));
+    }
+
+    #[tokio::test]
+    async fn relayd_relays_serves_snapshots_and_keeps_its_key() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let listen: Multiaddr = format!("/ip4/127.0.0.1/tcp/{}", port).parse().unwrap();
+        let data_dir = temp_dir("relayd_data");
+        let config = RelaydConfig {
+            data_dir: data_dir.clone(),
+            listen: vec![listen.clone()],
+            external_addresses: vec![listen.clone()],
+            quic: false,
+            snapshot_listen: Some("127.0.0.1:0".parse().unwrap()),
+            ..RelaydConfig::default()
+        };
+        let relayd = start(config.clone()).await.unwrap();
+        let relayd_id = relayd.peer_id;
+        let snapshot_addr = relayd.snapshot_addr.unwrap();
+
+        // An app node reserving a slot proves the relay server is on. As in
+        // practice, relayd is its bootstrap peer too.
+        let relay = listen.with(libp2p::multiaddr::Protocol::P2p(relayd_id));
+        let (client_tx, client_rx) = mpsc::channel(8);
+        let (client_events, mut client_events_rx) = mpsc::channel(64);
+        let client = PeerNode::new(
+            libp2p::identity::Keypair::generate_ed25519(),
+            client_rx,
+            client_events,
+            None,
+            vec![relay.clone()],
+            NodeConfig { relays: vec![relay], ..NodeConfig::default() },
+        )
+        .await
+        .unwrap();
+        tokio::spawn(client.run());
+        let reserved = tokio::time::timeout(Duration::from_secs(20), async {
+            while let Some(stats) = client_events_rx.recv().await {
+                if stats.relay_reservations == 1 {
+                    return true;
+                }
+            }
+            false
+        })
+        .await
+        .unwrap_or(false);
+
+        let mut stream = tokio::net::TcpStream::connect(snapshot_addr).await.unwrap();
+        stream.write_all(b"GET /snapshot HTTP/1.1\r\nHost: relayd\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        drop(client_tx);
+        relayd.shutdown().await;
+        let restarted = start(RelaydConfig { snapshot_listen: None, ..config }).await.unwrap();
+        let restarted_id = restarted.peer_id;
+        restarted.shutdown().await;
+        let _ = std::fs::remove_dir_all(&data_dir);
+
+        assert!(reserved, "relayd must accept relay reservations");
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "unexpected response: {}", response);
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let snapshot: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(snapshot["peer_count"], 1);
+        assert_eq!(snapshot["connected_peers"], serde_json::json!([]));
+        assert_eq!(restarted_id, relayd_id, "the node key must persist across restarts");
+    }
+}
@@ -0,0 +1,144 @@
+use crate::questions;
+use std::collections::HashMap;
+
+/// Inclusive bounds for a per-question rating (`MIN_RATING`/`MAX_RATING` in `src/constants.ts`).
+pub const MIN_RATING: f64 = 0.0;
+pub const MAX_RATING: f64 = 10.0;
+
+/// Scores derived from one completed answer sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scores {
+    /// Sum over rated questions of `points * (rating / MAX_RATING)`; 0–10,000.
+    pub total: f64,
+    /// Clamped rating of every rated question, keyed by question id.
+    pub category_scores: HashMap<String, f64>,
+}
+
+/// Compute the total and per-category scores from raw answers (question id →
+/// rating), exactly as `calculateScore` in `src/services/scoring.ts` does:
+/// ratings are clamped to 0–10, unanswered questions count as 0, and
+/// questions with sub-points are scored through their sub-questions.
+///
+/// Unlike the TypeScript version, ids outside the question set and
+/// non-finite ratings are errors rather than silently ignored, since the
+/// result is about to be published.
+pub fn score_answers(answers: &HashMap<String, f64>) -> Result<Scores, String> {
+    if let Some(id) = answers.keys().find(|id| !questions::is_known_category(id)) {
+        return Err(format!("Answer for unknown question '{}'", id));
+    }
+
+    let mut total = 0.0;
+    let mut category_scores = HashMap::new();
+    for question in questions::rated_questions() {
+        let rating = match answers.get(question.id) {
+            Some(rating) if !rating.is_finite() => {
+                return Err(format!("Rating {} for question '{}' is not a number", rating, question.id));
+            }
+            Some(rating) => rating.clamp(MIN_RATING, MAX_RATING),
+            None => MIN_RATING,
+        };
+        total += f64::from(question.points) * (rating / MAX_RATING);
+        category_scores.insert(question.id.to_string(), rating);
+    }
+    Ok(Scores { total, category_scores })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Fixtures {
+        cases: Vec<Fixture>,
+    }
+
+    #[derive(Deserialize)]
+    struct Fixture {
+        name: String,
+        answers: HashMap<String, f64>,
+        total: f64,
+    }
+
+    /// Shared with `tests/unit/services/scoring.test.ts`, which runs the same
+    /// cases through the TypeScript `calculateScore`.
+    fn fixtures() -> Vec<Fixture> {
+        let json = include_str!("../../tests/fixtures/scoring-parity.json");
+        serde_json::from_str::<Fixtures>(json).unwrap().cases
+    }
+
+    fn answers(pairs: &[(&str, f64)]) -> HashMap<String, f64> {
+        pairs.iter().map(|(id, rating)| (id.to_string(), *rating)).collect()
+    }
+
+    // ── Parity with src/services/scoring.ts ─────────────────────────────────
+
+    #[test]
+    fn totals_match_typescript_fixtures() {
+        let cases = fixtures();
+        assert!(!cases.is_empty());
+        for case in cases {
+            let scores = score_answers(&case.answers).unwrap();
+            assert!(
+                (scores.total - case.total).abs() < 1e-9,
+                "{}: got {}, expected {}", case.name, scores.total, case.total
+            );
+        }
+    }
+
+    #[test]
+    fn maximum_answers_score_ten_thousand() {
+        let all_max = questions::rated_questions().map(|q| (q.id.to_string(), MAX_RATING)).collect();
+        assert_eq!(score_answers(&all_max).unwrap().total, 10_000.0);
+    }
+
+    // ── Per-category scores ─────────────────────────────────────────────────
+
+    #[test]
+    fn every_rated_question_gets_a_category_score() {
+        let scores = score_answers(&answers(&[("2", 7.0)])).unwrap();
+        assert_eq!(scores.category_scores.len(), questions::rated_questions().count());
+        assert_eq!(scores.category_scores["2"], 7.0);
+        assert_eq!(scores.category_scores["1a"], 0.0, "unanswered counts as 0");
+        assert!(!scores.category_scores.contains_key("1"), "parents are not categories");
+    }
+
+    #[test]
+    fn category_scores_are_clamped() {
+        let scores = score_answers(&answers(&[("2", -3.0), ("3", 42.0)])).unwrap();
+        assert_eq!(scores.category_scores["2"], 0.0);
+        assert_eq!(scores.category_scores["3"], 10.0);
+    }
+
+    #[test]
+    fn scores_pass_result_validation() {
+        let scores = score_answers(&answers(&[("1a", 10.0), ("19e", 2.5)])).unwrap();
+        let result = crate::network::types::ManifestationResult {
+            score: scores.total,
+            timestamp: crate::network::utils::unix_now(),
+            category_scores: scores.category_scores,
+            question_set: questions::question_set_hash().to_string(),
+            privacy: None,
+            unlinkable: false,
+            sequence: 0,
+        };
+        assert!(result.validate().is_ok());
+    }
+
+    // ── Rejected input ──────────────────────────────────────────────────────
+
+    #[test]
+    fn unknown_question_ids_are_rejected() {
+        for id in ["1", "focus", "user@example.com"] {
+            let err = score_answers(&answers(&[(id, 5.0)])).unwrap_err();
+            assert!(err.contains("unknown question"), "{}: {}", id, err);
+        }
+    }
+
+    #[test]
+    fn non_finite_ratings_are_rejected() {
+        for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(score_answers(&answers(&[("2", bad)])).is_err());
+        }
+    }
+}
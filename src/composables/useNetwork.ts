@@ -50,7 +50,7 @@ export async function loadSharingState(): Promise<void> {
 
 /**
  * Fetch the most recently completed session from the local DB and publish it
- * to the P2P network via invoke('publish_result'). A no-op when no sessions exist.
+ * to the P2P network via invoke('publish_answers'); the backend recomputes the score. A no-op when no sessions exist.
  * Exported so it can be unit-tested independently.
  */
 export async function publishLastSession(): Promise<void> {
@@ -58,14 +58,14 @@ export async function publishLastSession(): Promise<void> {
   if (sessions.length === 0) return;
   const latest = sessions[0];
   const responses = await loadSessionResponses(latest.id);
-  const categoryScores: Record<string, number> = {};
+  const answers: Record<string, number> = {};
   for (const r of responses) {
     // Guard against rows seeded with a different column name in test fixtures
     if (r.question_id !== undefined && r.question_id !== null) {
-      categoryScores[r.question_id] = r.answer_value;
+      answers[r.question_id] = r.answer_value;
     }
   }
-  await invoke('publish_result', { score: latest.total_score, categoryScores });
+  await invoke('publish_answers', { answers });
 }
 
 export async function toggleSharing(enabled: boolean): Promise<void> {
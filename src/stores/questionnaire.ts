@@ -210,13 +210,14 @@ export const useQuestionnaireStore = defineStore('questionnaire', () => {
     }
   }
 
-  function publishSessionResult(finalScore: number, fullAnswers: Record<string, number>) {
+  function publishSessionResult(fullAnswers: Record<string, number>) {
     const { sharingEnabled } = useNetwork();
     if (!sharingEnabled.value) return;
 
-    void invoke('publish_result', {
-      score: finalScore,
-      categoryScores: { ...fullAnswers }
+    // The backend scores the answers itself, so the published score always
+    // matches the canonical formula.
+    void invoke('publish_answers', {
+      answers: { ...fullAnswers }
     }).catch(e => {
       console.error('Failed to publish result to network:', e);
     });
@@ -236,7 +237,7 @@ export const useQuestionnaireStore = defineStore('questionnaire', () => {
       currentIndex.value = 0;
       // Anonymous network sharing is a best-effort side effect and must never
       // keep the local save flow in a perpetual loading state.
-      publishSessionResult(finalScore, fullAnswers);
+      publishSessionResult(fullAnswers);
       return historyId;
     } catch (e) {
       console.error('Failed to submit session:', e);
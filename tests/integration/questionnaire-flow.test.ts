@@ -259,9 +259,9 @@ describe('E2E: Complete questionnaire flow', () => {
     consoleSpy.mockRestore();
   });
 
-  // ── publish_result integration ─────────────────────────────────────────────
+  // ── publish_answers integration ─────────────────────────────────────────────
 
-  it('submitSession calls publish_result when sharing is enabled', async () => {
+  it('submitSession calls publish_answers when sharing is enabled', async () => {
     // Enable sharing — sets sharingEnabled.value = true optimistically
     await toggleSharing(true);
 
@@ -272,10 +272,9 @@ describe('E2E: Complete questionnaire flow', () => {
 
     expect(histId).toBe('hist-e2e-001');
     expect(tauriMocks.mockInvoke).toHaveBeenCalledWith(
-      'publish_result',
+      'publish_answers',
       expect.objectContaining({
-        score: expect.any(Number),
-        categoryScores: expect.objectContaining({
+        answers: expect.objectContaining({
           [leafIds[0]]: 9,
           [leafIds[1]]: 0
         })
@@ -283,32 +282,32 @@ describe('E2E: Complete questionnaire flow', () => {
     );
 
     const publishCall = tauriMocks.mockInvoke.mock.calls.find(
-      (call: unknown[]) => call[0] === 'publish_result'
+      (call: unknown[]) => call[0] === 'publish_answers'
     );
     expect(publishCall?.[1]).toMatchObject({
-      categoryScores: expect.any(Object)
+      answers: expect.any(Object)
     });
     expect(
-      Object.keys((publishCall?.[1] as { categoryScores: Record<string, number> }).categoryScores)
+      Object.keys((publishCall?.[1] as { answers: Record<string, number> }).answers)
     ).toHaveLength(leafIds.length);
   });
 
-  it('submitSession does NOT call publish_result when sharing is disabled', async () => {
+  it('submitSession does NOT call publish_answers when sharing is disabled', async () => {
     // sharingEnabled starts false after _resetNetworkState() in beforeEach
     const store = useQuestionnaireStore();
     await store.submitSession();
 
     const publishCalls = tauriMocks.mockInvoke.mock.calls.filter(
-      (call: unknown[]) => call[0] === 'publish_result'
+      (call: unknown[]) => call[0] === 'publish_answers'
     );
     expect(publishCalls).toHaveLength(0);
   });
 
-  it('submitSession still succeeds and logs error if publish_result rejects', async () => {
+  it('submitSession still succeeds and logs error if publish_answers rejects', async () => {
     const consoleSpy = vi.spyOn(console, 'error').mockImplementation(() => {});
     await toggleSharing(true);
     tauriMocks.mockInvoke.mockImplementation((cmd: string) => {
-      if (cmd === 'publish_result') return Promise.reject(new Error('network unreachable'));
+      if (cmd === 'publish_answers') return Promise.reject(new Error('network unreachable'));
       return Promise.resolve(undefined);
     });
 
@@ -325,10 +324,10 @@ describe('E2E: Complete questionnaire flow', () => {
     consoleSpy.mockRestore();
   });
 
-  it('submitSession does not wait for publish_result to resolve', async () => {
+  it('submitSession does not wait for publish_answers to resolve', async () => {
     await toggleSharing(true);
     tauriMocks.mockInvoke.mockImplementation((cmd: string) => {
-      if (cmd === 'publish_result') {
+      if (cmd === 'publish_answers') {
         return new Promise(() => {});
       }
       return Promise.resolve(undefined);
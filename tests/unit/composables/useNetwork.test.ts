@@ -55,7 +55,7 @@ describe('useNetwork composable', () => {
       if (cmd === 'get_peer_count') return Promise.resolve(0);
       if (cmd === 'get_network_sharing') return Promise.resolve(false);
       if (cmd === 'set_network_sharing') return Promise.resolve();
-      if (cmd === 'publish_result') return Promise.resolve('bafy-mock-cid');
+      if (cmd === 'publish_answers') return Promise.resolve('bafy-mock-cid');
       return Promise.resolve();
     });
     // listen returns an unlisten stub that resolves immediately
@@ -242,7 +242,7 @@ describe('useNetwork composable', () => {
 
   // ── publishLastSession on enable ────────────────────────────────────────────
 
-  it('toggleSharing(true) calls publish_result with last session data when sessions exist', async () => {
+  it('toggleSharing(true) calls publish_answers with last session data when sessions exist', async () => {
     dbMocks.loadHistoricalSessions.mockResolvedValue([
       {
         id: 'sess-001',
@@ -258,21 +258,20 @@ describe('useNetwork composable', () => {
 
     await toggleSharing(true);
 
-    expect(mockInvoke).toHaveBeenCalledWith('publish_result', {
-      score: 7500,
-      categoryScores: { '1a': 9, '2': 7 }
+    expect(mockInvoke).toHaveBeenCalledWith('publish_answers', {
+      answers: { '1a': 9, '2': 7 }
     });
   });
 
-  it('toggleSharing(true) does NOT call publish_result when no sessions exist', async () => {
+  it('toggleSharing(true) does NOT call publish_answers when no sessions exist', async () => {
     // dbMocks.loadHistoricalSessions already returns [] by default in beforeEach
     await toggleSharing(true);
 
-    const publishCalls = mockInvoke.mock.calls.filter((c: unknown[]) => c[0] === 'publish_result');
+    const publishCalls = mockInvoke.mock.calls.filter((c: unknown[]) => c[0] === 'publish_answers');
     expect(publishCalls).toHaveLength(0);
   });
 
-  it('toggleSharing(false) does NOT call publish_result', async () => {
+  it('toggleSharing(false) does NOT call publish_answers', async () => {
     dbMocks.loadHistoricalSessions.mockResolvedValue([
       {
         id: 'sess-001',
@@ -284,7 +283,7 @@ describe('useNetwork composable', () => {
 
     await toggleSharing(false);
 
-    const publishCalls = mockInvoke.mock.calls.filter((c: unknown[]) => c[0] === 'publish_result');
+    const publishCalls = mockInvoke.mock.calls.filter((c: unknown[]) => c[0] === 'publish_answers');
     expect(publishCalls).toHaveLength(0);
   });
 
@@ -303,7 +302,7 @@ describe('useNetwork composable', () => {
     ]);
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'set_network_sharing') return Promise.resolve();
-      if (cmd === 'publish_result') return Promise.reject(new Error('gossipsub error'));
+      if (cmd === 'publish_answers') return Promise.reject(new Error('gossipsub error'));
       return Promise.resolve();
     });
 
@@ -317,7 +316,7 @@ describe('useNetwork composable', () => {
 
   it('publishLastSession is a no-op when loadHistoricalSessions returns empty', async () => {
     await publishLastSession(); // dbMocks default returns []
-    const publishCalls = mockInvoke.mock.calls.filter((c: unknown[]) => c[0] === 'publish_result');
+    const publishCalls = mockInvoke.mock.calls.filter((c: unknown[]) => c[0] === 'publish_answers');
     expect(publishCalls).toHaveLength(0);
   });
 
@@ -338,9 +337,8 @@ describe('useNetwork composable', () => {
 
     await publishLastSession();
 
-    expect(mockInvoke).toHaveBeenCalledWith('publish_result', {
-      score: 5000,
-      categoryScores: { '2': 8 } // undefined key excluded
+    expect(mockInvoke).toHaveBeenCalledWith('publish_answers', {
+      answers: { '2': 8 } // undefined key excluded
     });
   });
 
@@ -360,9 +358,8 @@ describe('useNetwork composable', () => {
 
     await publishLastSession();
 
-    expect(mockInvoke).toHaveBeenCalledWith('publish_result', {
-      score: 5000,
-      categoryScores: { '2': 8 }
+    expect(mockInvoke).toHaveBeenCalledWith('publish_answers', {
+      answers: { '2': 8 }
     });
   });
 
@@ -1,6 +1,7 @@
 import { describe, it, expect } from 'vitest';
 import { calculateScore, getMaxPossibleScore } from '@/services/scoring';
 import { questions } from '@/data/questions';
+import parityFixtures from '../../fixtures/scoring-parity.json';
 
 function buildBaseline(rating: number): Record<string, number> {
   const answers: Record<string, number> = {};
@@ -73,3 +74,11 @@ describe('Score Calculation Engine', () => {
     expect(calculateScore(answers) - baseline).toBeCloseTo(75);
   });
 });
+
+// The same fixtures are checked by the Rust scoring module (src-tauri/src/scoring.rs),
+// which computes the published score. A change to the formula must update both.
+describe('Score Calculation Engine – Rust parity fixtures', () => {
+  it.each(parityFixtures.cases)('$name', ({ answers, total }) => {
+    expect(calculateScore(answers as Record<string, number>)).toBeCloseTo(total, 9);
+  });
+});